@@ -0,0 +1,140 @@
+// Desktop notifications and sounds that alert the user when memory usage crosses a threshold
+use std::collections::HashMap;
+use std::process::Command;
+use std::thread;
+
+use tokio::time;
+use zbus::zvariant::Value;
+
+/// The icon that is shown next to notifications, as installed with the applet.
+const APP_ICON: &str = "be.samvervaeck.CosmicAppletRAM-symbolic";
+
+/// The sound from the freedesktop sound theme that is played when no sound file is configured.
+const ALERT_SOUND_ID: &str = "dialog-warning";
+
+/// The minimum amount of time between two alerts for the same threshold.
+pub const ALERT_COOLDOWN: time::Duration = time::Duration::from_secs(300);
+
+/// Remembers on which side of a threshold a metric was, so that alerts only fire when the metric
+/// rises above it and not on every tick that it stays there.
+#[derive(Debug, Default)]
+pub struct ThresholdAlert {
+    above: bool,
+    fired_at: Option<time::Instant>,
+}
+
+impl ThresholdAlert {
+
+    /// Record whether the metric is currently above the threshold and return whether an alert
+    /// should be fired.
+    ///
+    /// This only happens on a rising edge, and only if the previous alert was long enough ago.
+    pub fn update(&mut self, above: bool) -> bool {
+        let rising = above && !self.above;
+        self.above = above;
+        if !rising {
+            return false
+        }
+        if self.fired_at.is_some_and(|fired_at| fired_at.elapsed() < ALERT_COOLDOWN) {
+            return false
+        }
+        self.fired_at = Some(time::Instant::now());
+        true
+    }
+
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, &Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Show a desktop notification through the freedesktop notification service.
+pub async fn notify(summary: String, body: String) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    let proxy = NotificationsProxy::new(&connection).await?;
+    proxy.notify(
+        "RAM Usage",
+        0,
+        APP_ICON,
+        &summary,
+        &body,
+        &[],
+        HashMap::new(),
+        -1,
+    ).await?;
+    Ok(())
+}
+
+/// Play a sound file, or the alert sound of the sound theme if `file` is empty, and wait for it to
+/// finish.
+///
+/// Files are played with `paplay`, which works with both PulseAudio and PipeWire, and the sound
+/// theme through libcanberra. The player runs on a thread of its own, so that waiting for it
+/// doesn't hold up the runtime.
+pub async fn play_sound(file: String) -> Result<(), String> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    thread::spawn(move || {
+        let mut command = if file.is_empty() {
+            let mut command = Command::new("canberra-gtk-play");
+            command.arg(format!("--id={ALERT_SOUND_ID}"));
+            command
+        } else {
+            let mut command = Command::new("paplay");
+            command.arg(&file);
+            command
+        };
+        let result = match command.status() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("{:?} exited with {}", command.get_program(), status)),
+            Err(error) => Err(format!("failed to run {:?}: {}", command.get_program(), error)),
+        };
+        // Nobody is left to tell if the applet stopped waiting
+        let _ = sender.send(result);
+    });
+    receiver.await.map_err(|_| String::from("the sound player thread stopped unexpectedly"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn alerts_fire_on_rising_edges() {
+        let mut alert = ThresholdAlert::default();
+        assert!(!alert.update(false));
+        assert!(alert.update(true));
+        // Staying above the threshold is not a new crossing
+        assert!(!alert.update(true));
+        assert!(!alert.update(false));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn alerts_wait_for_the_cooldown() {
+        let mut alert = ThresholdAlert::default();
+        assert!(alert.update(true));
+        alert.update(false);
+        time::advance(ALERT_COOLDOWN - time::Duration::from_secs(1)).await;
+        assert!(!alert.update(true));
+
+        // The crossing within the cooldown was missed, so it takes another one
+        alert.update(false);
+        time::advance(time::Duration::from_secs(1)).await;
+        assert!(alert.update(true));
+    }
+}
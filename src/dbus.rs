@@ -0,0 +1,98 @@
+// D-Bus service that allows scripts and keybindings to interact with the applet
+use cosmic::iced::futures::channel::mpsc;
+use cosmic::iced::futures::SinkExt;
+use cosmic::iced::Subscription;
+use cosmic::iced_futures::stream;
+use tokio::sync::watch;
+use zbus::fdo::{RequestNameFlags, RequestNameReply};
+
+use crate::{Message, ResultExt, ID};
+
+/// The path at which the applet's interface is served on the session bus.
+const OBJECT_PATH: &str = "/be/samvervaeck/CosmicAppletRAM";
+
+/// The most samples that [`Applet::history`] returns, so that a long history can't turn into an
+/// unexpectedly large reply, nor has to be copied in full on every sample.
+pub const MAX_HISTORY_SAMPLES: usize = 1000;
+
+/// The object that is exposed over D-Bus.
+///
+/// Method calls that act on the applet are translated into a message that is injected into it.
+/// Queries are answered from what the applet publishes, without a round trip through it.
+struct Applet {
+    output: mpsc::Sender<Message>,
+    /// The newest part of the usage history as published on every sample, in the form of
+    /// [`History::samples`]
+    ///
+    /// [`History::samples`]: crate::history::History::samples
+    history: watch::Receiver<Vec<(u64, u64)>>,
+}
+
+#[zbus::interface(name = "be.samvervaeck.CosmicAppletRAM")]
+impl Applet {
+
+    /// Open the popup if it is closed, or close it if it is open.
+    async fn show_popup(&self) {
+        self.output
+            .clone()
+            .send(Message::TogglePopup)
+            .await
+            .log("Failed sending popup request to applet");
+    }
+
+    /// The recent usage history, oldest first, as pairs of the time at which a sample was taken,
+    /// in milliseconds since the Unix epoch, and the used memory in bytes.
+    ///
+    /// A sample is taken on every tick, but the update interval can change and ticks can be missed,
+    /// so samples aren't necessarily evenly spaced. At most the newest [`MAX_HISTORY_SAMPLES`]
+    /// are returned. Before the first tick, these are only the ones that an earlier run saved, if
+    /// the history is persisted.
+    async fn history(&self) -> Vec<(u64, u64)> {
+        self.history.borrow().clone()
+    }
+
+}
+
+/// Serve the interface, and claim the applet's well-known name unless another instance has it.
+///
+/// The panel starts an applet process for every output that it is shown on, and only one of them
+/// can own the name. The others still serve the interface under their unique name, rather than
+/// queueing for the well-known one and taking it over at some arbitrary moment.
+async fn serve(output: mpsc::Sender<Message>, history: watch::Receiver<Vec<(u64, u64)>>) -> zbus::Result<zbus::Connection> {
+    let connection = zbus::connection::Builder::session()?
+        .serve_at(OBJECT_PATH, Applet { output, history })?
+        .build()
+        .await?;
+    match connection.request_name_with_flags(ID, RequestNameFlags::DoNotQueue.into()).await {
+        Ok(RequestNameReply::Exists) | Err(zbus::Error::NameTaken) => {
+            tracing::info!(
+                "Another instance of the applet owns {} on the session bus, serving D-Bus requests only under {}",
+                ID,
+                connection.unique_name().map(ToString::to_string).unwrap_or_default(),
+            );
+        }
+        Ok(_) => {}
+        Err(error) => return Err(error),
+    }
+    Ok(connection)
+}
+
+/// Serve the interface on the session bus for as long as the applet is running.
+///
+/// The history is only read from the receiver that the subscription starts with.
+pub fn subscription(history: watch::Receiver<Vec<(u64, u64)>>) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "dbus-sub",
+        stream::channel(1, |output| async move {
+            match serve(output, history).await {
+                Ok(_connection) => {
+                    // The connection stops serving requests as soon as it is dropped
+                    std::future::pending::<()>().await
+                }
+                Err(error) => {
+                    tracing::error!("Failed to start D-Bus service: {}", error);
+                }
+            }
+        }),
+    )
+}
@@ -0,0 +1,167 @@
+// Icons and graphs that are generated as SVG, so that they render crisply at any scale
+use cosmic::iced::Color;
+
+/// The center of the ring within the 16x16 view box.
+const CENTER: f32 = 8.0;
+
+/// The radius of the ring, measured to the middle of its stroke.
+const RADIUS: f32 = 6.0;
+
+const STROKE_WIDTH: f32 = 2.5;
+
+/// The radius of the dot that replaces the icon in the most compact display mode.
+const DOT_RADIUS: f32 = 4.0;
+
+/// The height of the bar graph above and below its axis, and the width of the axis itself.
+const RATE_HALF_HEIGHT: f32 = 20.0;
+const RATE_AXIS_WIDTH: f32 = 0.5;
+
+/// The width of a bar of the bar graph, out of the one unit that every bar gets.
+const RATE_BAR_WIDTH: f32 = 0.7;
+
+/// The radii and the stroke width of the two rings of the dual gauge.
+///
+/// On small icons every unit of the view box is about a pixel, so the rings are thinner there to
+/// leave a visible gap between them.
+const DUAL_OUTER_RADIUS: f32 = 6.5;
+const DUAL_INNER_RADIUS: f32 = 3.75;
+const DUAL_STROKE_WIDTH: f32 = 2.0;
+const THIN_OUTER_RADIUS: f32 = 6.75;
+const THIN_INNER_RADIUS: f32 = 4.0;
+const THIN_STROKE_WIDTH: f32 = 1.5;
+
+/// Generate an SVG image of a ring that is filled clockwise from the top by the given fraction.
+///
+/// `track` is the color of the unfilled part of the ring, `fill` the color of the filled part.
+pub fn ring_svg(ratio: f32, track: Color, fill: Color) -> String {
+    let mut svg = String::from(SVG_START);
+    push_ring(&mut svg, RADIUS, STROKE_WIDTH, ratio, track, fill);
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Generate an SVG image of two rings, the outer one filled by `ratio` with `fill` and the inner
+/// one filled by `inner_ratio` with `inner_fill`.
+///
+/// Without an inner ratio, as when there is no swap, only the outer ring is drawn. `thin` draws
+/// both rings with a thinner stroke, for small icons.
+pub fn dual_ring_svg(
+    ratio: f32,
+    inner_ratio: Option<f32>,
+    track: Color,
+    fill: Color,
+    inner_fill: Color,
+    thin: bool,
+) -> String {
+    let (outer_radius, inner_radius, width) = if thin {
+        (THIN_OUTER_RADIUS, THIN_INNER_RADIUS, THIN_STROKE_WIDTH)
+    } else {
+        (DUAL_OUTER_RADIUS, DUAL_INNER_RADIUS, DUAL_STROKE_WIDTH)
+    };
+    let mut svg = String::from(SVG_START);
+    push_ring(&mut svg, outer_radius, width, ratio, track, fill);
+    if let Some(inner_ratio) = inner_ratio {
+        push_ring(&mut svg, inner_radius, width, inner_ratio, track, inner_fill);
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+const SVG_START: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 16 16">"#;
+
+/// Append a ring of the given radius and stroke width, filled clockwise from the top by `ratio`.
+fn push_ring(svg: &mut String, radius: f32, width: f32, ratio: f32, track: Color, fill: Color) {
+    let ratio = ratio.clamp(0.0, 1.0);
+    svg.push_str(&format!(
+        r#"<circle cx="{c}" cy="{c}" r="{r}" fill="none" stroke="{track}" stroke-opacity="{opacity}" stroke-width="{w}"/>"#,
+        c = CENTER,
+        r = radius,
+        w = width,
+        track = hex(track),
+        opacity = track.a,
+    ));
+    if ratio >= 1.0 {
+        // An arc can't start and end at the same point, so draw a full circle instead
+        svg.push_str(&format!(
+            r#"<circle cx="{c}" cy="{c}" r="{r}" fill="none" stroke="{fill}" stroke-width="{w}"/>"#,
+            c = CENTER,
+            r = radius,
+            w = width,
+            fill = hex(fill),
+        ));
+    } else if ratio > 0.0 {
+        let angle = ratio * std::f32::consts::TAU;
+        let x = CENTER + radius * angle.sin();
+        let y = CENTER - radius * angle.cos();
+        let large_arc = if ratio > 0.5 { 1 } else { 0 };
+        svg.push_str(&format!(
+            r#"<path d="M {c} {top} A {r} {r} 0 {large_arc} 1 {x:.3} {y:.3}" fill="none" stroke="{fill}" stroke-width="{w}" stroke-linecap="round"/>"#,
+            c = CENTER,
+            top = CENTER - radius,
+            r = radius,
+            w = width,
+            fill = hex(fill),
+        ));
+    }
+}
+
+/// Format a color as `#rrggbb`, ignoring its alpha channel.
+fn hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Generate an SVG image of a bar graph around a horizontal axis, with one bar for every value.
+///
+/// The values go from -1 to 1, where positive ones are drawn upwards with `rising` and negative
+/// ones downwards with `falling`. A missing value is a break in the graph, which leaves out the
+/// axis as well. The image is one unit wide per bar, so it is meant to be stretched to the space
+/// it is given.
+pub fn rate_bars_svg(values: &[Option<f32>], rising: Color, falling: Color, axis: Color) -> String {
+    let width = values.len().max(1);
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" preserveAspectRatio="none">"#,
+        height = 2.0 * RATE_HALF_HEIGHT,
+    );
+    for (i, value) in values.iter().enumerate() {
+        let Some(value) = value else {
+            continue
+        };
+        // Each bar brings its own piece of the axis, so that a break shows as a gap in it
+        svg.push_str(&format!(
+            r#"<rect x="{i}" y="{y}" width="1" height="{h}" fill="{fill}" fill-opacity="{opacity}"/>"#,
+            y = RATE_HALF_HEIGHT - RATE_AXIS_WIDTH / 2.0,
+            h = RATE_AXIS_WIDTH,
+            fill = hex(axis),
+            opacity = axis.a,
+        ));
+        let value = value.clamp(-1.0, 1.0);
+        let height = value.abs() * RATE_HALF_HEIGHT;
+        if height == 0.0 {
+            continue
+        }
+        svg.push_str(&format!(
+            r#"<rect x="{x}" y="{y:.3}" width="{w}" height="{height:.3}" fill="{fill}"/>"#,
+            x = i as f32 + (1.0 - RATE_BAR_WIDTH) / 2.0,
+            y = if value > 0.0 { RATE_HALF_HEIGHT - height } else { RATE_HALF_HEIGHT },
+            w = RATE_BAR_WIDTH,
+            fill = hex(if value > 0.0 { rising } else { falling }),
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Generate an SVG image of a small filled circle, for the most compact display mode.
+pub fn dot_svg(fill: Color) -> String {
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 16 16">"#,
+            r#"<circle cx="{c}" cy="{c}" r="{r}" fill="{fill}"/>"#,
+            "</svg>",
+        ),
+        c = CENTER,
+        r = DOT_RADIUS,
+        fill = hex(fill),
+    )
+}
@@ -0,0 +1,493 @@
+// A bounded buffer of recent memory usage samples
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ID;
+
+/// The amount of samples that is kept, both in memory and on disk, unless configured otherwise.
+pub const HISTORY_CAPACITY: usize = 300;
+
+/// The most samples that a history ever keeps, whatever capacity it is given, so that a monitor of
+/// memory usage can't end up hoarding memory itself.
+pub const MAX_HISTORY_CAPACITY: usize = 100_000;
+
+/// Statistics over every sample in the history, in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryStats {
+    pub min: u64,
+    pub max: u64,
+    pub mean: u64,
+}
+
+/// A line fitted through the history.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LinearFit {
+    /// The change per second, in bytes
+    slope: f64,
+    /// Where the line is at the time of the newest sample, in bytes
+    latest: f64,
+}
+
+/// The amount of used memory at some point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Sample {
+    /// When the sample was taken, in milliseconds since the Unix epoch
+    at: u64,
+    used: u64,
+}
+
+/// The amount of used memory recorded on each tick, oldest first.
+///
+/// Every sample carries the time at which it was taken, so that the history stays accurate when
+/// the update interval changes. Wall clock time is used, rather than a monotonic clock, so that
+/// samples still mean something after they have been loaded from disk.
+#[derive(Debug)]
+pub struct History {
+    samples: VecDeque<Sample>,
+    capacity: usize,
+    /// How old, in milliseconds, the oldest sample may be compared to the newest one
+    max_age: Option<u64>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            capacity: HISTORY_CAPACITY,
+            max_age: None,
+        }
+    }
+}
+
+impl History {
+
+    /// An empty history that keeps at most `capacity` samples.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut history = Self::default();
+        history.set_capacity(capacity);
+        history
+    }
+
+    /// Record a new sample taken now, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, used: u64) {
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64);
+        self.push_sample(Sample { at, used });
+    }
+
+    fn push_sample(&mut self, sample: Sample) {
+        // The capacity is already bounded, but this is the one place that every sample goes through
+        while self.samples.len() >= self.capacity.min(MAX_HISTORY_CAPACITY) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.drop_expired();
+    }
+
+    /// Change how many samples are kept, dropping the oldest ones if there are too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        // Keep at least one sample so that the latest usage is always known
+        self.capacity = capacity.clamp(1, MAX_HISTORY_CAPACITY);
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Change how old, in milliseconds, samples may get before they are dropped, or keep them
+    /// until the capacity is reached if `None`.
+    pub fn set_max_age(&mut self, max_age: Option<u64>) {
+        self.max_age = max_age;
+        self.drop_expired();
+    }
+
+    /// Drop the samples that are older than the maximum age, as measured from the newest sample.
+    fn drop_expired(&mut self) {
+        let (Some(max_age), Some(newest)) = (self.max_age, self.samples.back()) else {
+            return
+        };
+        let oldest_allowed = newest.at.saturating_sub(max_age);
+        while self.samples.front().is_some_and(|sample| sample.at < oldest_allowed) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Put the samples of a history that was recorded earlier before these, such as one that is
+    /// loaded from disk after this one started recording.
+    ///
+    /// Samples of `earlier` that aren't older than the oldest sample here are left out, and the
+    /// oldest samples are dropped if there are too many altogether.
+    pub fn prepend(&mut self, earlier: History) {
+        let oldest = self.samples.front().map_or(u64::MAX, |sample| sample.at);
+        let mut samples: VecDeque<Sample> = earlier.samples.into_iter()
+            .filter(|sample| sample.at < oldest)
+            .collect();
+        samples.append(&mut self.samples);
+        self.samples = samples;
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+        self.drop_expired();
+    }
+
+    /// Lower every sample above `max` to it, for when less memory is available than before.
+    pub fn clamp(&mut self, max: u64) {
+        for sample in &mut self.samples {
+            sample.used = sample.used.min(max);
+        }
+    }
+
+    /// The newest `limit` samples as the time at which they were taken, in milliseconds since the
+    /// Unix epoch, and the used memory, oldest first.
+    pub fn samples(&self, limit: usize) -> Vec<(u64, u64)> {
+        self.samples.iter()
+            .skip(self.samples.len().saturating_sub(limit))
+            .map(|sample| (sample.at, sample.used))
+            .collect()
+    }
+
+    /// The second-to-last and the last sample, if there are at least two.
+    pub fn last_change(&self) -> Option<(u64, u64)> {
+        let mut recent = self.samples.iter().rev();
+        let (latest, previous) = (recent.next()?.used, recent.next()?.used);
+        Some((previous, latest))
+    }
+
+    /// The lowest, highest and average sample, if there are at least two samples to compare.
+    pub fn stats(&self) -> Option<HistoryStats> {
+        if self.samples.len() < 2 {
+            return None
+        }
+        let sum = self.samples.iter().map(|sample| u128::from(sample.used)).sum::<u128>();
+        Some(HistoryStats {
+            min: self.samples.iter().map(|sample| sample.used).min()?,
+            max: self.samples.iter().map(|sample| sample.used).max()?,
+            mean: (sum / self.samples.len() as u128) as u64,
+        })
+    }
+
+    /// How fast usage changed between every two consecutive samples, in bytes per second, oldest
+    /// first.
+    ///
+    /// Samples that were taken at the same time say nothing about the rate, so they are skipped.
+    /// Samples that are more than `max_gap` milliseconds apart, as around a suspend, are a break
+    /// in the history rather than a slow change, and come out as `None`.
+    pub fn rates(&self, max_gap: Option<u64>) -> Vec<Option<f64>> {
+        self.samples.iter()
+            .zip(self.samples.iter().skip(1))
+            .filter(|(before, after)| after.at > before.at)
+            .map(|(before, after)| {
+                let elapsed = after.at - before.at;
+                if max_gap.is_some_and(|max_gap| elapsed > max_gap) {
+                    return None
+                }
+                let seconds = elapsed as f64 / 1000.0;
+                Some((after.used as f64 - before.used as f64) / seconds)
+            })
+            .collect()
+    }
+
+    /// The average change per second over the whole history, in bytes, as fitted with least
+    /// squares, or `None` if there are fewer than `min_samples` samples or they were all taken at
+    /// the same time.
+    pub fn slope(&self, min_samples: usize) -> Option<f64> {
+        self.fit(min_samples).map(|fit| fit.slope)
+    }
+
+    /// The used memory that the least squares line through the history predicts for `horizon`
+    /// seconds after the newest sample, or `None` under the same conditions as [`History::slope`].
+    ///
+    /// A line that falls below zero predicts that nothing is used.
+    pub fn forecast(&self, min_samples: usize, horizon: f64) -> Option<u64> {
+        let fit = self.fit(min_samples)?;
+        Some((fit.latest + fit.slope * horizon).max(0.0) as u64)
+    }
+
+    /// Fit a line through the history with least squares.
+    fn fit(&self, min_samples: usize) -> Option<LinearFit> {
+        let n = self.samples.len();
+        if n < min_samples.max(2) {
+            return None
+        }
+        // Measure from the first sample, so that the large timestamps don't cost precision
+        let first = self.samples.front()?.at;
+        let seconds = |sample: &Sample| sample.at.saturating_sub(first) as f64 / 1000.0;
+        let mean_x = self.samples.iter().map(seconds).sum::<f64>() / n as f64;
+        let mean_y = self.samples.iter().map(|sample| sample.used as f64).sum::<f64>() / n as f64;
+        let mut covariance = 0.0;
+        let mut variance = 0.0;
+        for sample in &self.samples {
+            let dx = seconds(sample) - mean_x;
+            covariance += dx * (sample.used as f64 - mean_y);
+            variance += dx * dx;
+        }
+        if variance == 0.0 {
+            return None
+        }
+        let slope = covariance / variance;
+        let newest = seconds(self.samples.back()?);
+        Some(LinearFit {
+            slope,
+            latest: mean_y + slope * (newest - mean_x),
+        })
+    }
+
+    /// Load the samples that were saved by a previous run of the applet.
+    ///
+    /// A missing or corrupt file results in an empty history, as does one that was written
+    /// before samples had timestamps. Only the latest `capacity` samples are kept.
+    pub fn load(path: &Path, capacity: usize) -> Self {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    tracing::warn!("Failed to read usage history from {}: {}", path.display(), error);
+                }
+                return Self::with_capacity(capacity)
+            }
+        };
+        let mut history = Self::with_capacity(capacity);
+        for line in contents.lines() {
+            match parse_sample(line) {
+                Ok(sample) => history.push_sample(sample),
+                Err(error) => {
+                    tracing::warn!("Discarding corrupt usage history in {}: {}", path.display(), error);
+                    return Self::with_capacity(capacity)
+                }
+            }
+        }
+        history
+    }
+
+    /// Write the samples to disk, one per line, as the timestamp followed by the amount of used
+    /// memory.
+    ///
+    /// The file is replaced atomically so that a crash halfway through cannot corrupt it.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut contents = String::new();
+        for sample in &self.samples {
+            contents.push_str(&format!("{} {}\n", sample.at, sample.used));
+        }
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, contents)?;
+        fs::rename(tmp_path, path)
+    }
+
+}
+
+/// Parse a line that was written by [`History::save`].
+fn parse_sample(line: &str) -> Result<Sample, String> {
+    let mut fields = line.split_whitespace();
+    let (Some(at), Some(used), None) = (fields.next(), fields.next(), fields.next()) else {
+        return Err(format!("expected a timestamp and a sample, got '{line}'"))
+    };
+    Ok(Sample {
+        at: at.parse().map_err(|error| format!("invalid timestamp '{at}': {error}"))?,
+        used: used.parse().map_err(|error| format!("invalid sample '{used}': {error}"))?,
+    })
+}
+
+/// The file in which the history is persisted across restarts.
+pub fn history_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(ID).join("history"))
+}
+
+/// The file in which the used memory is kept when the applet exits, to compare with on the next
+/// start.
+pub fn last_session_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(ID).join("last-session"))
+}
+
+/// Read the used memory that the previous session ended with, or `None` if there was no previous
+/// session or its file can't be read.
+pub fn load_last_session(path: &Path) -> Option<u64> {
+    match fs::read_to_string(path) {
+        Ok(contents) => match contents.trim().parse() {
+            Ok(used) => Some(used),
+            Err(error) => {
+                tracing::warn!("Ignoring corrupt last session in {}: {}", path.display(), error);
+                None
+            }
+        },
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => {
+            tracing::warn!("Failed to read last session from {}: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+/// Write the used memory that this session ends with.
+pub fn save_last_session(path: &Path, used: u64) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{used}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A history that keeps `capacity` samples and has seen each of `samples`, as the time in
+    /// milliseconds and the used memory, in order.
+    fn history(capacity: usize, samples: &[(u64, u64)]) -> History {
+        let mut history = History::with_capacity(capacity);
+        for &(at, used) in samples {
+            history.push_sample(Sample { at, used });
+        }
+        history
+    }
+
+    #[test]
+    fn capacity_drops_the_oldest_samples() {
+        let mut history = history(3, &[(0, 1), (1000, 2), (2000, 3), (3000, 4)]);
+        assert_eq!(history.samples(usize::MAX), [(1000, 2), (2000, 3), (3000, 4)]);
+
+        history.set_capacity(2);
+        assert_eq!(history.samples(usize::MAX), [(2000, 3), (3000, 4)]);
+        history.set_capacity(0);
+        assert_eq!(history.samples(usize::MAX), [(3000, 4)]);
+        assert_eq!(History::default().capacity, HISTORY_CAPACITY);
+    }
+
+    #[test]
+    fn earlier_samples_are_prepended_up_to_the_capacity() {
+        let mut recent = history(3, &[(3000, 4), (4000, 5)]);
+        recent.prepend(history(10, &[(1000, 2), (2000, 3), (3000, 9)]));
+        // The overlapping sample is taken from the recent history, and the oldest one doesn't fit
+        assert_eq!(recent.samples(usize::MAX), [(2000, 3), (3000, 4), (4000, 5)]);
+
+        let mut empty = history(3, &[]);
+        empty.prepend(history(3, &[(1000, 2)]));
+        assert_eq!(empty.samples(usize::MAX), [(1000, 2)]);
+    }
+
+    #[test]
+    fn capacity_is_bounded() {
+        let mut history = History::with_capacity(usize::MAX);
+        for i in 0..MAX_HISTORY_CAPACITY as u64 + 10 {
+            history.push_sample(Sample { at: i, used: i });
+        }
+        let samples = history.samples(usize::MAX);
+        assert_eq!(samples.len(), MAX_HISTORY_CAPACITY);
+        assert_eq!(samples.first(), Some(&(10, 10)));
+
+        // Raising the capacity later is bounded just the same
+        history.set_capacity(MAX_HISTORY_CAPACITY * 2);
+        history.push_sample(Sample { at: u64::MAX, used: 0 });
+        assert_eq!(history.samples(usize::MAX).len(), MAX_HISTORY_CAPACITY);
+        assert_eq!(history.samples(2), [(MAX_HISTORY_CAPACITY as u64 + 9, MAX_HISTORY_CAPACITY as u64 + 9), (u64::MAX, 0)]);
+    }
+
+    #[test]
+    fn max_age_is_measured_from_the_newest_sample() {
+        let mut history = history(10, &[(0, 1), (1000, 2), (2000, 3)]);
+        history.set_max_age(Some(1000));
+        assert_eq!(history.samples(usize::MAX), [(1000, 2), (2000, 3)]);
+        history.push_sample(Sample { at: 5000, used: 4 });
+        assert_eq!(history.samples(usize::MAX), [(5000, 4)]);
+    }
+
+    #[test]
+    fn clamp_lowers_samples_above_the_maximum() {
+        let mut history = history(10, &[(0, 5), (1000, 12), (2000, 8)]);
+        history.clamp(10);
+        assert_eq!(history.samples(usize::MAX), [(0, 5), (1000, 10), (2000, 8)]);
+    }
+
+    #[test]
+    fn stats_need_two_samples() {
+        assert_eq!(history(10, &[(0, 5)]).stats(), None);
+        assert_eq!(
+            history(10, &[(0, 5), (1000, 1), (2000, 9)]).stats(),
+            Some(HistoryStats { min: 1, max: 9, mean: 5 }),
+        );
+        assert_eq!(history(10, &[(0, 5), (1000, 1)]).last_change(), Some((5, 1)));
+    }
+
+    #[test]
+    fn slope_is_per_second() {
+        let line = history(10, &[(0, 100), (1000, 200), (2000, 300)]);
+        assert_eq!(line.slope(2), Some(100.0));
+        assert_eq!(line.slope(4), None);
+        // An interval that changed halfway doesn't bend the line
+        assert_eq!(history(10, &[(0, 100), (500, 150), (2500, 350)]).slope(2), Some(100.0));
+        // Samples at the same time have no slope
+        assert_eq!(history(10, &[(0, 100), (0, 200)]).slope(2), None);
+    }
+
+    #[test]
+    fn rates_are_per_second_between_samples() {
+        let sampled = history(10, &[(0, 100), (500, 200), (500, 900), (2500, 100)]);
+        // The samples at the same time are skipped
+        assert_eq!(sampled.rates(None), [Some(200.0), Some(-400.0)]);
+        assert!(history(10, &[(0, 100)]).rates(None).is_empty());
+    }
+
+    #[test]
+    fn rates_break_where_samples_are_too_far_apart() {
+        let sampled = history(10, &[(0, 0), (1000, 100), (3000, 300), (3000 + 2001, 0)]);
+        // Exactly the maximum gap still counts as a change
+        assert_eq!(sampled.rates(Some(2000)), [Some(100.0), Some(100.0), None]);
+        assert_eq!(sampled.rates(Some(2001)), [Some(100.0), Some(100.0), Some(-300.0 * 1000.0 / 2001.0)]);
+        assert_eq!(sampled.rates(Some(1999)), [Some(100.0), None, None]);
+        assert_eq!(sampled.rates(None), sampled.rates(Some(u64::MAX)));
+    }
+
+    #[test]
+    fn forecast_extends_the_line_from_the_newest_sample() {
+        let line = history(10, &[(0, 100), (1000, 200), (2000, 300)]);
+        assert_eq!(line.forecast(2, 30.0), Some(3300));
+        assert_eq!(line.forecast(4, 30.0), None);
+        // Noise around the line starts the forecast on the line rather than at the newest sample
+        let noisy = history(10, &[(0, 100), (1000, 300), (2000, 200), (3000, 400)]);
+        assert_eq!(noisy.slope(2), Some(80.0));
+        assert_eq!(noisy.forecast(2, 10.0), Some(1170));
+        // A falling line stops at nothing used
+        assert_eq!(history(10, &[(0, 300), (1000, 200), (2000, 100)]).forecast(2, 60.0), Some(0));
+    }
+
+    #[test]
+    fn history_round_trips_through_disk() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("history");
+        let saved = history(10, &[(0, 1), (1000, 2), (2000, 3)]);
+        saved.save(&path).unwrap();
+
+        assert_eq!(History::load(&path, 10).samples, saved.samples);
+        assert_eq!(History::load(&path, 2).samples(usize::MAX), [(1000, 2), (2000, 3)]);
+    }
+
+    #[test]
+    fn corrupt_or_missing_history_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("history");
+        assert!(History::load(&path, 10).samples.is_empty());
+
+        fs::write(&path, "0 1\n1000 x\n").unwrap();
+        assert!(History::load(&path, 10).samples.is_empty());
+        // A history from before samples had timestamps
+        fs::write(&path, "1\n2\n").unwrap();
+        assert!(History::load(&path, 10).samples.is_empty());
+    }
+
+    #[test]
+    fn last_session_round_trips_through_disk() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cache").join("last-session");
+        assert_eq!(load_last_session(&path), None);
+
+        save_last_session(&path, 8 << 30).unwrap();
+        assert_eq!(load_last_session(&path), Some(8 << 30));
+        fs::write(&path, "lots\n").unwrap();
+        assert_eq!(load_last_session(&path), None);
+    }
+}
@@ -1,4 +1,15 @@
 
+mod alerts;
+mod dbus;
+mod gauge;
+mod history;
+mod meminfo;
+mod metrics;
+mod pressure;
+mod shortcut;
+mod signal;
+mod usage_log;
+
 // Mandatory COSMIC imports
 use cosmic::app::Core;
 use cosmic::applet::cosmic_panel_config::PanelAnchor;
@@ -6,8 +17,10 @@ use cosmic::cosmic_config::cosmic_config_derive::CosmicConfigEntry;
 use cosmic::cosmic_config; // Necessary for CosmicConfigEntry derivation to work
 use cosmic::cosmic_config::{Config, CosmicConfigEntry};
 use cosmic::iced::advanced::widget;
-use cosmic::iced::futures::SinkExt;
+use cosmic::iced::futures::{SinkExt, Stream};
 use cosmic::iced::Alignment::Center;
+use cosmic::iced::{Color, Length};
+use cosmic::iced::keyboard::{self, key::Named, Key, Modifiers};
 use cosmic::iced_futures::stream;
 use cosmic::iced::Subscription;
 use cosmic::iced::{
@@ -15,25 +28,222 @@ use cosmic::iced::{
     Limits,
 };
 use cosmic::iced_runtime::core::window;
-use cosmic::iced_widget::column;
+use cosmic::iced_widget::{column, stack, svg};
 use cosmic::widget::dropdown::popup_dropdown;
 use cosmic::widget::segmented_button::{Entity, SingleSelectModel};
 use cosmic::{surface, Element};
 use cosmic::app::Task;
 
 // Widgets we're going to use
-use cosmic::widget::{autosize, button, checkbox, text_input, container, icon, segmented_button, segmented_control, settings, spin_button};
+use cosmic::widget::{autosize, button, checkbox, mouse_area, text, text_input, container, icon, scrollable, segmented_button, segmented_control, settings, spin_button, tooltip};
 
 use once_cell::sync::Lazy;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use serde::{Deserialize, Serialize};
-use sysinfo::System;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
 use tokio::{sync::watch, time};
 
+use alerts::ThresholdAlert;
+use history::{History, HISTORY_CAPACITY, MAX_HISTORY_CAPACITY};
+use meminfo::{EccErrors, MeminfoField, NodeMemory};
+use metrics::{BackgroundSource, CommandSource, MemorySample, MetricsSource, ProcMeminfoSource, SessionSource, SysinfoSource};
+use shortcut::Shortcut;
+use usage_log::UsageLog;
+
 // Every COSMIC Application and Applet MUST have an ID
 const ID: &str = "be.samvervaeck.CosmicAppletRAM";
 
 const DEFAULT_UPDATE_INTERVAL: u64 = 1000;
 
+/// The range of update intervals that can be configured, in milliseconds.
+const MIN_UPDATE_INTERVAL: u64 = 100;
+const MAX_UPDATE_INTERVAL: u64 = 3_600_000;
+
+/// Update intervals that can be picked with a single click, in milliseconds.
+const INTERVAL_PRESETS: [(&str, u64); 3] = [
+    ("Power Saver", 5_000),
+    ("Balanced", 1_000),
+    ("Responsive", 250),
+];
+
+/// The slowest update interval that can be configured for the popup, in milliseconds.
+const MAX_POPUP_UPDATE_INTERVAL: u64 = 10_000;
+
+/// The range of decimal digits that counters can be formatted with.
+///
+/// An `f64` holds about 16 significant digits, so more would only show noise.
+const MIN_PRECISION: u32 = 0;
+const MAX_PRECISION: u32 = 16;
+const PRECISION_STEP: u32 = 1;
+
+/// How much PageUp and PageDown change the precision.
+const PRECISION_PAGE_STEP: u32 = 4;
+
+/// How much the arrow keys and PageUp and PageDown change the update interval, in milliseconds.
+const INTERVAL_STEP: u64 = 100;
+const INTERVAL_PAGE_STEP: u64 = 1000;
+
+const DEFAULT_SYSTEM_MONITOR_COMMAND: &str = "gnome-system-monitor";
+
+const DEFAULT_ICON_NAME: &str = "display-symbolic";
+
+/// The full-color icon, which is bundled so that it doesn't depend on the icon theme.
+const FULL_COLOR_ICON: &[u8] = include_bytes!("../data/icons/scalable/be.samvervaeck.CosmicAppletRAM.svg");
+
+/// How many processes are listed in the popup when showing the top processes.
+const TOP_PROCESS_COUNT: usize = 5;
+
+/// The usual bounds of the width of the popup, in logical pixels.
+const POPUP_MIN_WIDTH: f32 = 300.0;
+const POPUP_MAX_WIDTH: f32 = 372.0;
+
+/// The widest the popup gets when it is sized to its contents.
+const POPUP_FIT_MAX_WIDTH: f32 = 640.0;
+
+/// The average width of a character of the interface font, and the width that a settings row takes
+/// up besides its text, for estimating how wide the popup has to be.
+const POPUP_CHAR_WIDTH: f32 = 7.5;
+const POPUP_ROW_CHROME: f32 = 64.0;
+
+/// The height of the list of every memory statistic, which scrolls since it has dozens of them.
+const MEMINFO_LIST_HEIGHT: f32 = 240.0;
+
+/// The height of the graph of how fast memory is allocated and freed.
+const RATE_GRAPH_HEIGHT: f32 = 48.0;
+
+/// The largest step that percentages can be rounded to.
+const MAX_PERCENT_STEP: u32 = 25;
+
+/// The largest smoothing factor that can be configured, in percent.
+///
+/// At 100% the displayed value would never change, so stop a bit short of that.
+const MAX_SMOOTHING_FACTOR: u32 = 95;
+
+/// The most samples that the moving average can be taken over.
+const MAX_MOVING_AVERAGE_WINDOW: u32 = 60;
+
+/// How long to wait before trying once more to open the popup when the panel window isn't there
+/// yet, as can happen right after login.
+const POPUP_RETRY_DELAY: time::Duration = time::Duration::from_millis(500);
+
+/// How long the tooltip mentions that the popup could not be opened.
+const POPUP_UNAVAILABLE_TIMEOUT: time::Duration = time::Duration::from_secs(30);
+
+/// How long a refresh is considered fresh enough to not refresh again when the popup opens.
+const MIN_REFRESH_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+/// The furthest the popup can be moved from its usual position in either direction, in pixels.
+///
+/// The applet doesn't know how large the screen is, so this only rules out offsets that can't be
+/// on any screen.
+const MAX_POPUP_OFFSET: i32 = 4096;
+
+/// The languages that are written from right to left, as ISO 639 codes.
+const RTL_LANGUAGES: [&str; 10] = ["ar", "arc", "ckb", "dv", "fa", "he", "ps", "sd", "ug", "ur"];
+
+/// The smallest icon size that the panel can suggest for a percentage to be shown inside the gauge.
+const GAUGE_LABEL_MIN_ICON_SIZE: u16 = 24;
+
+/// Below this icon size, the rings of the gauge that also shows swap are drawn thinner.
+const DUAL_GAUGE_THIN_ICON_SIZE: u16 = 24;
+
+/// The size of the percentage inside the gauge relative to the icon size, so that three digits
+/// fit within the ring.
+const GAUGE_LABEL_SCALE: f32 = 0.35;
+
+/// The height of the usage bar in the popup.
+const BAR_HEIGHT: f32 = 6.0;
+
+/// How opaque the background of the panel button is tinted when usage is high, relative to the
+/// opacity of the panel itself.
+const BACKGROUND_TINT_OPACITY: f32 = 0.35;
+
+/// The resolution of the usage bar, which is split into this many equal portions.
+const BAR_PORTIONS: u16 = 1000;
+
+/// The fields whose strings are redacted from shared settings, unless the user includes them.
+///
+/// `source` is an enum, of which only the command of a remote source is redacted.
+const SNIPPET_REDACTED_FIELDS: [&str; 6] = [
+    "system_monitor_command",
+    "critical_action_command",
+    "critical_sound_file",
+    "usage_log_path",
+    "process_target",
+    "source",
+];
+
+/// The environment variable that sets the level at which recoverable failures are logged.
+const RECOVERABLE_LEVEL_VAR: &str = "COSMIC_APPLET_RAM_RECOVERABLE_LOG_LEVEL";
+
+/// The name of the file, in the home directory, that settings are exported to unless the user
+/// picks another one.
+const SETTINGS_FILE_NAME: &str = "cosmic-applet-ram.json";
+
+/// Shown next to the usage on the panel while it isn't live.
+const PAUSED_GLYPH: &str = " ⏸";
+
+/// In how many steps the icon fills up when reduced motion is enabled.
+const REDUCED_MOTION_STEPS: u32 = 4;
+
+/// For how many ticks the usage has to go the other way before the trend arrow turns around, when
+/// it only shows turning points.
+const TURN_CONFIRM_TICKS: u32 = 3;
+
+/// How many samples the history needs before memory exhaustion is estimated from it.
+const OOM_ESTIMATE_MIN_SAMPLES: usize = 10;
+
+/// The shortest gap between samples, in update intervals, that the rate graph can be configured
+/// to break at, since samples a single interval apart are simply consecutive.
+const MIN_GRAPH_GAP_INTERVALS: u32 = 2;
+
+/// The longest gap between samples, in update intervals, that the rate graph can be configured
+/// to bridge before it shows a break.
+const MAX_GRAPH_GAP_INTERVALS: u32 = 60;
+
+/// The largest budget, in GiB, that usage can be compared against from the popup.
+const MAX_DISPLAY_CAP_GIB: u64 = 4096;
+
+/// The range of seconds that the forecast can look ahead.
+const MIN_FORECAST_HORIZON: u32 = 5;
+const MAX_FORECAST_HORIZON: u32 = 600;
+
+/// The slowest growth, in bytes per second, that is extrapolated to memory exhaustion; anything
+/// slower is considered stable, since it's more likely to be noise.
+const OOM_ESTIMATE_MIN_SLOPE: f64 = 64.0 * 1024.0;
+
+/// How long after launching the system monitor further requests to launch it are ignored.
+const LAUNCH_GUARD_INTERVAL: time::Duration = time::Duration::from_secs(2);
+
+/// How long the text on the panel stays in each color while it blinks.
+const BLINK_INTERVAL: time::Duration = time::Duration::from_millis(800);
+
+/// How often the watchdog checks that the metrics are still being refreshed.
+const WATCHDOG_INTERVAL: time::Duration = time::Duration::from_secs(5);
+
+/// After how many update intervals without a refresh the metrics are considered stalled.
+const STALL_INTERVALS: u32 = 5;
+
+/// How often the usage history is written to disk when persistence is enabled.
+const HISTORY_SAVE_INTERVAL: time::Duration = time::Duration::from_secs(60);
+
+/// How long after the start the popup compares usage with how the previous session ended, after
+/// which the comparison says more about this session than about the previous one.
+const LAST_SESSION_DISPLAY_TIME: time::Duration = time::Duration::from_secs(5 * 60);
+
+/// The longest label that can be shown before or after the usage on the panel, in characters.
+const MAX_LABEL_LENGTH: usize = 16;
+
+/// The suggested icon size, in pixels, at or below which the panel is too thin for the full
+/// readout and the panel button switches to a compact one.
+const COMPACT_ICON_SIZE: u16 = 16;
+
+/// The most decimals that are shown on the panel in the compact readout.
+const COMPACT_PRECISION: u32 = 1;
+
 /*
 *  Every COSMIC model must be a struct data type.
 *  Mandatory fields for a COSMIC Applet are core and popup.
@@ -47,13 +257,153 @@ const DEFAULT_UPDATE_INTERVAL: u64 = 1000;
 struct Window {
     core: Core,
     popup: Option<window::Id>,
+    /// Only used for listing processes; memory statistics are read from `source`
     sys: sysinfo::System,
-    used: u64,
-    total: u64,
+    source: Box<dyn MetricsSource>,
+    /// Why the latest sample could not be taken, if the source failed
+    source_error: Option<String>,
+    /// Whether the source produced a sample yet, which a remote source takes a while to do
+    has_sample: bool,
+    /// The largest total the source reported since it was created, which usage can be displayed
+    /// relative to
+    peak_total: u64,
+    memory: MemorySample,
+    smoothed_used: Option<f64>,
+    /// The last samples of used memory, newest last, for the moving average
+    recent_used: VecDeque<u64>,
+    /// The used and total memory that the panel keeps showing while the pointer hovers over it
+    hover_frozen: Option<(u64, u64)>,
+    /// The total as last formatted for the panel, along with the total and the layout it was
+    /// formatted for
+    total_text: RefCell<Option<(u64, PanelLayout, String)>>,
+    refreshed_at: time::Instant,
+    /// When the source last produced a sample without failing, if it ever did
+    updated_at: Option<time::Instant>,
+    /// Whether the watchdog found that the metrics stopped being refreshed
+    stalled: bool,
+    /// Whether blinking text currently shows the critical color
+    blink_on: bool,
+    /// Whether the locale is written from right to left, which mirrors the panel contents
+    rtl: bool,
+    /// The last configured icon that could actually be found in the icon theme
+    icon_name: String,
+    /// Shown in the popup when the configured icon could not be found
+    icon_warning: Option<String>,
+    /// The icon name as it is being typed, which is only applied once submitted
+    icon_name_text: String,
+    /// Why the first memory statistics made no sense, shown in the popup
+    degraded: Option<String>,
+    uptime: u64,
+    history: History,
+    history_saved_at: time::Instant,
+    /// The used memory that the previous session ended with, if it was recorded
+    last_session_used: Option<u64>,
+    last_session_saved_at: time::Instant,
+    started_at: time::Instant,
+    /// When the last sample was added to the history
+    recorded_at: time::Instant,
+    numa_nodes: Vec<NodeMemory>,
+    numa_error_logged: bool,
+    /// The memory errors that ECC detected, if the hardware reports them
+    ecc_errors: Option<EccErrors>,
+    ecc_error_logged: bool,
+    /// The summary of the installed memory modules, which is read once since they can't change
+    /// while the system runs; `None` until read, and the inner `None` if they can't be read
+    modules_summary: Option<Option<String>>,
+    top_processes: Vec<ProcessUsage>,
+    /// The memory of every pinned process, in the order of the config, or `None` if it isn't running
+    pinned_process_memory: Vec<Option<u64>>,
+    /// The name that is being typed to pin another process
+    pinned_process_text: String,
+    /// Whether the list of every memory statistic is expanded in the popup
+    meminfo_expanded: bool,
+    /// Every memory statistic, which is only read while the list is expanded
+    meminfo_fields: Vec<MeminfoField>,
+    meminfo_error_logged: bool,
+    /// The memory that the monitored process uses, summed over every match, or `None` if it isn't
+    /// running
+    process_memory: Option<u64>,
+    /// The processes that matched the name of the monitored process in the last scan of every
+    /// process, which is only scanned again once one of them is gone
+    process_pids: Vec<Pid>,
+    /// The monitored process as it is being typed, which is only applied once submitted
+    process_target_text: String,
+    /// The amount of used memory the user marked as a reference point
+    baseline: Option<u64>,
+    /// The start and the end of a stretch of time over which the user measures the change in usage
+    marker_a: Option<Marker>,
+    marker_b: Option<Marker>,
+    /// The amount of used memory when the popup was opened
+    used_at_open: Option<u64>,
+    /// When the system monitor was last launched, to ignore clicks that would launch it again
+    launched_at: Option<time::Instant>,
+    /// Starts the configured commands, which is [`spawn_command`] except in tests
+    spawn: fn(&str) -> std::io::Result<()>,
+    /// Creates the metrics source when it is changed or restarted, which is [`create_source`]
+    /// except in tests
+    create_source: fn(&Source, u64) -> Box<dyn MetricsSource>,
+    /// The direction of the usage, as followed for [`TrendMode::Turns`]
+    turns: TurnTracker,
+    /// The file that settings are exported to and imported from, as typed in the popup
+    settings_file_text: String,
+    /// The outcome of the last export or import, shown in the popup
+    settings_file_status: Option<String>,
+    /// The setting in the popup that the arrow keys, PageUp, PageDown, Home and End change, which
+    /// is the one of those that was changed last
+    stepping: Option<SteppedSetting>,
+    /// What is wrong with each of the configured commands, checked whenever they change so that
+    /// mistakes show up before the commands are ever run
+    command_errors: CommandErrors,
+    /// The command to run when usage is critical as it is being typed, which is only applied once
+    /// submitted, so that nothing half-typed is ever run
+    critical_action_command_text: String,
+    /// The critical sound file as it is being typed, which is only applied once submitted, so that
+    /// half-typed paths aren't saved and played
+    critical_sound_file_text: String,
+    /// Leave commands and paths in the settings that are copied for sharing
+    snippet_includes_commands: bool,
+    /// The directory that the configuration is stored in, if it could be resolved
+    config_dir: Option<PathBuf>,
+    /// The newest version of the configuration that was written by a newer release, if any
+    newer_config_version: Option<u64>,
+    /// Settings in the configuration directory that this release doesn't know about
+    unknown_config_fields: Vec<String>,
+    /// Keep the popup open when the user clicks outside of it
+    pinned: bool,
+    /// When the popup could not be opened the last time it was asked for, because the applet had
+    /// no panel window to attach it to
+    popup_unavailable_at: Option<time::Instant>,
+    /// Whether another attempt to open the popup is scheduled, during which clicks are ignored
+    popup_retry_pending: bool,
+    /// The parsed popup shortcut, if one is configured and valid
+    shortcut: Option<Shortcut>,
+    /// The popup shortcut as it is being typed, which is only applied once submitted
+    popup_shortcut_text: String,
+    /// The panel text color as it is being typed, which is only applied once submitted
+    text_color_text: String,
+    /// The prefix labels as they are being typed, separated by commas, which are only applied once
+    /// submitted
+    prefix_labels_text: String,
+    /// Where samples are logged, if a log file is configured and writing to it hasn't failed
+    usage_log: Option<UsageLog>,
+    /// The usage log path as it is being typed, which is only applied once submitted
+    usage_log_path_text: String,
+    swap_alert: ThresholdAlert,
+    critical_alert: ThresholdAlert,
+    sound_alert: ThresholdAlert,
+    /// Whether playing the critical sound failed, after which it isn't tried again
+    sound_unavailable: bool,
     standard_model: segmented_button::SingleSelectModel,
     entity_si: Entity,
     entity_iec: Entity,
-    update_interval_tx: watch::Sender<u64>,
+    unit_model: segmented_button::SingleSelectModel,
+    entity_byte: Entity,
+    entity_bit: Entity,
+    tick_settings_tx: watch::Sender<TickSettings>,
+    /// Publishes the usage history whenever it changes, for the D-Bus service to answer queries
+    /// from
+    history_tx: watch::Sender<Vec<(u64, u64)>>,
+    time_subscription_generation: u64,
     live_config: CosmicAppletRamConfig,
     config: Config,
     // Exclusively UI state
@@ -65,62 +415,743 @@ const VERSION: u64 = 1;
 #[derive(Debug, Eq, PartialEq, Deserialize, Serialize, Clone, CosmicConfigEntry)]
 #[version = 1]
 struct CosmicAppletRamConfig {
+    source: Source,
     precision: u32,
+    /// Add decimals to values that would otherwise round to zero, such as with a fixed prefix
+    expand_small_values: bool,
+    /// Write decimals after a comma rather than a point, as in `8,2 GiB`
+    decimal_comma: bool,
+    rounding: Rounding,
     prefix: Prefix,
+    /// Offer the prefixes from peta up in the prefix dropdown
+    advanced_units: bool,
+    /// The smallest prefix that counts are shown with, so that small values get decimals too
+    smallest_prefix: Prefix,
+    /// Labels that replace the prefixes, in the order of [`PREFIXES`]; empty entries keep the
+    /// default
+    prefix_labels: Vec<String>,
+    auto_hysteresis: u32,
+    /// Let `Prefix::Auto` pick one prefix from the total memory and use it for every value
+    lock_prefix_to_total: bool,
+    /// Format swap on the panel with the exact prefix of the RAM next to it, rather than letting
+    /// `Prefix::Auto` pick one for swap by itself
+    swap_matches_ram: bool,
+    /// Let `Prefix::Auto` step away from the prefix it picked when the value would otherwise come
+    /// out below 1 or, for a shared prefix, at 1000 or more
+    auto_nice_values: bool,
+    /// Leave the unit off the used memory on the panel when it is the same as that of the total
+    unit_on_total_only: bool,
+    smoothing_mode: SmoothingMode,
+    smoothing_factor: u32,
+    /// The amount of samples that the moving average is taken over
+    moving_average_window: u32,
     show_total: bool,
+    /// Format the total on the panel only when it or the settings change, rather than on every
+    /// tick, since it is rarely anything but the installed memory
+    static_total: bool,
+    show_total_vertical: bool,
+    /// Keep the icon next to the text on vertical panels, where the two are stacked
+    show_icon_vertical: bool,
+    denominator: Denominator,
+    used_definition: UsedDefinition,
+    monitor_target: MonitorTarget,
+    /// The name or PID of a process whose memory is monitored instead of the whole system's
+    process_target: Option<String>,
+    /// Show RAM and swap added together on the panel, unless only swap is monitored
+    combined_view: bool,
+    /// Show how much memory is still free instead of how much is used
+    show_headroom: bool,
+    /// Let clicking the usage on the panel switch between used and free memory, and clicking the
+    /// total switch what usage is relative to
+    clickable_segments: bool,
+    /// Draw the numbers on the panel in a monospaced font, so that the label keeps its width as
+    /// the digits change
+    monospace_digits: bool,
+    /// Show an arrow next to the usage when it went up or down since the previous tick
+    show_trend: bool,
+    /// Change in MiB below which no trend arrow is shown, to keep tiny fluctuations from flickering
+    trend_epsilon: u32,
+    trend_mode: TrendMode,
+    show_uptime: bool,
+    /// Describe the usage in plain words in the tooltip, which screen readers read out, instead of
+    /// in numbers
+    usage_in_words: bool,
+    /// Also describe the usage in plain words in the popup
+    usage_in_words_popup: bool,
+    show_history_stats: bool,
+    /// Show a graph in the popup of how fast memory was allocated and freed over the history
+    show_rate_graph: bool,
+    /// Average every bar of the rate graph with its neighbours, so that single spikes stand out less
+    smooth_rate_graph: bool,
+    /// How many update intervals samples may be apart before the rate graph shows a break, as after
+    /// a suspend, or 0 to never break it
+    graph_gap_intervals: u32,
+    /// Show the used memory formatted with both standards in the popup
+    show_both_standards: bool,
+    /// Show in the popup how long it takes until memory is full, if usage keeps growing as it does
+    show_oom_estimate: bool,
+    /// Show in the popup how much memory is likely used a while from now, if usage keeps going the
+    /// way it does
+    show_forecast: bool,
+    /// How far ahead the forecast looks, in seconds
+    forecast_horizon: u32,
+    /// Show in the popup how long ago memory statistics were last read
+    show_last_update: bool,
+    /// Remember the used memory when the applet exits, and show in the popup how the usage after
+    /// the next start compares to it
+    compare_last_session: bool,
+    show_numa: bool,
+    /// Show how many memory errors ECC detected in the popup, on hardware that reports them
+    show_ecc: bool,
+    /// Show the installed memory modules in the popup, if the firmware tables can be read
+    show_modules: bool,
+    /// Show a bar in the popup that is filled by the used or by the free memory
+    usage_bar: UsageBar,
+    show_top_processes: bool,
+    /// Names of processes whose memory is always listed in the popup, with every process of the
+    /// same name added together
+    pinned_processes: Vec<String>,
+    /// Percentage of swap in use above which a notification is shown, or 0 to disable it
+    swap_notify_threshold: u32,
+    refresh_on_open: bool,
+    freeze_on_hover: bool,
+    primary_action: PrimaryAction,
+    system_monitor_command: String,
+    /// Key combination that toggles the popup while the applet has keyboard focus, such as `Super+R`
+    popup_shortcut: String,
+    display_mode: DisplayMode,
+    /// Text shown on the panel before the usage
+    label_prefix: String,
+    /// Text shown on the panel after the usage
+    label_suffix: String,
+    /// Text shown on the panel instead of the usage until the first sample arrives
+    placeholder: String,
+    /// A metric that is shown on the panel after the usage
+    secondary_metric: MetricSlot,
+    /// Text shown between the usage and the secondary metric
+    metric_separator: String,
+    /// Color of the text on the panel, as `#rrggbb`, `#rrggbbaa` or the name of a theme color, or
+    /// empty for the theme's foreground color
+    text_color: String,
+    /// Color the text on the panel like the critical band when usage is critical, whatever the
+    /// text color is
+    critical_text_color: bool,
+    /// Blink the text on the panel between its usual color and the critical one while usage is
+    /// critical, unless motion is reduced
+    critical_blink: bool,
+    icon_name: String,
+    /// Show the bundled full-color icon instead of the symbolic icon named by `icon_name`
+    icon_style: IconStyle,
+    icon_gradient: bool,
+    gauge_icon: bool,
+    /// Add an inner ring to the gauge that fills up as swap is used
+    gauge_swap_ring: bool,
+    /// Show the percentage of memory in use inside the gauge, if the panel is large enough
+    gauge_label: bool,
+    /// Percentages are shown rounded to a multiple of this, so that they change less often
+    percent_step: u32,
+    /// Cap displayed percentages at 100%, rather than showing by how much usage exceeds the total
+    /// as with a memory budget or a cgroup over its limit
+    clamp_percent: bool,
+    /// Only let the gauge and the icon tint change in coarse steps, instead of following every
+    /// small change in usage
+    reduced_motion: bool,
+    /// Leave out the padding around the icon, for panels with little room
+    tight_layout: bool,
+    /// Tint the background of the panel button while usage is high or critical
+    tint_background: bool,
+    /// Percentage of memory in use from which usage is considered high
+    warning_threshold: u32,
+    /// Percentage of memory in use from which usage is considered critical
+    critical_threshold: u32,
+    /// The thresholds for swap, which is worth worrying about much sooner than RAM
+    swap_thresholds: Thresholds,
+    /// The thresholds for the monitored process, as a percentage of the total memory
+    process_thresholds: Thresholds,
+    /// Command that is run when usage becomes critical, or empty to not run anything
+    critical_action_command: String,
+    /// Play a sound when usage becomes critical
+    critical_sound: bool,
+    /// The sound that is played when usage becomes critical, or empty for the system alert sound
+    critical_sound_file: String,
+    /// Also update as soon as the kernel reports memory pressure, rather than only on every tick
+    pressure_events: bool,
+    persist_history: bool,
+    /// How much usage history is kept
+    history_window: HistoryWindow,
+    /// CSV file to which every sample is appended, or empty to not log samples
+    usage_log_path: String,
+    /// Size in MiB after which the usage log is rotated
+    usage_log_max_size: u32,
+    /// A budget in bytes that usage is compared against instead of the total memory
+    display_cap_bytes: Option<u64>,
     standard: Standard,
+    /// The base of the prefixes, if it should differ from the one of the standard
+    prefix_base: Option<Base>,
+    /// Whether prefixes are labeled with an `i`, if it should differ from the standard
+    prefix_style: Option<PrefixStyle>,
+    /// Format byte counts exactly like `free -h`, ignoring the other formatting options
+    like_free: bool,
+    unit: Unit,
     update_interval: u64,
+    /// The update interval while the popup is open, in milliseconds, or 0 to keep the panel's
+    popup_update_interval: u64,
+    /// Horizontal distance in pixels by which the popup is moved from its usual position
+    popup_offset_x: i32,
+    /// Vertical distance in pixels by which the popup is moved from its usual position
+    popup_offset_y: i32,
+    /// Try once more to open the popup a moment later when the panel window isn't ready yet
+    retry_popup: bool,
+    /// Size the popup to its widest row when it opens, rather than to a fixed width
+    fit_popup_width: bool,
+    /// Tick on multiples of the update interval since the Unix epoch, such as exactly on the second
+    align_to_clock: bool,
+    missed_ticks: MissedTicks,
+    /// Show a short introduction to the most important settings at the top of the popup, until
+    /// the user dismisses it
+    first_run: bool,
 }
 
 impl Default for CosmicAppletRamConfig {
     fn default() -> Self {
         Self {
+            source: Source::Local,
             precision: 0,
+            expand_small_values: false,
+            decimal_comma: false,
+            rounding: Rounding::Nearest,
             prefix: Prefix::Auto,
+            advanced_units: false,
+            smallest_prefix: Prefix::None,
+            prefix_labels: Vec::new(),
+            auto_hysteresis: 100,
+            lock_prefix_to_total: false,
+            swap_matches_ram: false,
+            auto_nice_values: false,
+            unit_on_total_only: false,
+            smoothing_mode: SmoothingMode::default(),
+            smoothing_factor: 0,
+            moving_average_window: 5,
             show_total: true,
+            static_total: false,
+            show_total_vertical: true,
+            show_icon_vertical: true,
+            denominator: Denominator::Total,
+            used_definition: UsedDefinition::Sysinfo,
+            monitor_target: MonitorTarget::default(),
+            process_target: None,
+            combined_view: false,
+            show_headroom: false,
+            clickable_segments: false,
+            monospace_digits: false,
+            show_trend: false,
+            trend_epsilon: 16,
+            trend_mode: TrendMode::default(),
+            show_uptime: false,
+            usage_in_words: false,
+            usage_in_words_popup: false,
+            show_history_stats: false,
+            show_rate_graph: false,
+            smooth_rate_graph: false,
+            graph_gap_intervals: 5,
+            show_both_standards: false,
+            show_oom_estimate: false,
+            show_forecast: false,
+            forecast_horizon: 30,
+            show_last_update: false,
+            compare_last_session: false,
+            show_numa: false,
+            show_ecc: false,
+            show_modules: false,
+            usage_bar: UsageBar::default(),
+            show_top_processes: false,
+            pinned_processes: Vec::new(),
+            swap_notify_threshold: 0,
+            refresh_on_open: true,
+            freeze_on_hover: false,
+            primary_action: PrimaryAction::TogglePopup,
+            system_monitor_command: String::from(DEFAULT_SYSTEM_MONITOR_COMMAND),
+            popup_shortcut: String::new(),
+            display_mode: DisplayMode::Full,
+            label_prefix: String::new(),
+            label_suffix: String::new(),
+            placeholder: String::from("—"),
+            secondary_metric: MetricSlot::default(),
+            metric_separator: String::from(" • "),
+            text_color: String::new(),
+            critical_text_color: false,
+            critical_blink: false,
+            icon_name: String::from(DEFAULT_ICON_NAME),
+            icon_style: IconStyle::default(),
+            icon_gradient: false,
+            gauge_icon: false,
+            gauge_swap_ring: false,
+            gauge_label: false,
+            percent_step: 1,
+            clamp_percent: true,
+            reduced_motion: false,
+            tight_layout: false,
+            tint_background: false,
+            warning_threshold: 70,
+            critical_threshold: 90,
+            swap_thresholds: Thresholds { warning: 50, critical: 80 },
+            process_thresholds: Thresholds { warning: 50, critical: 75 },
+            critical_action_command: String::new(),
+            critical_sound: false,
+            critical_sound_file: String::new(),
+            pressure_events: false,
+            persist_history: false,
+            history_window: HistoryWindow::default(),
+            usage_log_path: String::new(),
+            usage_log_max_size: 10,
+            display_cap_bytes: None,
             standard: Standard::Iec,
+            prefix_base: None,
+            prefix_style: None,
+            like_free: false,
+            unit: Unit::Byte,
             update_interval: DEFAULT_UPDATE_INTERVAL,
+            popup_update_interval: 0,
+            popup_offset_x: 0,
+            popup_offset_y: 0,
+            fit_popup_width: false,
+            align_to_clock: false,
+            missed_ticks: MissedTicks::default(),
+            retry_popup: true,
+            first_run: true,
+        }
+    }
+}
+
+impl CosmicAppletRamConfig {
+
+    /// Bring every value that could have been hand-edited into something the applet can use.
+    ///
+    /// Numbers are clamped to the ranges that the popup allows, labels are shortened, and a
+    /// warning threshold above the critical threshold is lowered to match it.
+    fn validated(mut self) -> Self {
+        self.precision = clamp_precision(self.precision);
+        self.update_interval = self.update_interval.clamp(MIN_UPDATE_INTERVAL, MAX_UPDATE_INTERVAL);
+        if self.popup_update_interval != 0 {
+            self.popup_update_interval = self.popup_update_interval.clamp(MIN_UPDATE_INTERVAL, MAX_POPUP_UPDATE_INTERVAL);
+        }
+        self.smoothing_factor = self.smoothing_factor.min(MAX_SMOOTHING_FACTOR);
+        self.moving_average_window = self.moving_average_window.clamp(1, MAX_MOVING_AVERAGE_WINDOW);
+        self.percent_step = self.percent_step.clamp(1, MAX_PERCENT_STEP);
+        self.forecast_horizon = self.forecast_horizon.clamp(MIN_FORECAST_HORIZON, MAX_FORECAST_HORIZON);
+        self.graph_gap_intervals = clamp_graph_gap_intervals(self.graph_gap_intervals);
+        self.auto_hysteresis = self.auto_hysteresis.clamp(100, 200);
+        self.swap_notify_threshold = self.swap_notify_threshold.min(100);
+        self.critical_threshold = self.critical_threshold.min(100);
+        self.warning_threshold = self.warning_threshold.min(self.critical_threshold);
+        self.swap_thresholds = self.swap_thresholds.validated();
+        self.process_thresholds = self.process_thresholds.validated();
+        self.popup_offset_x = self.popup_offset_x.clamp(-MAX_POPUP_OFFSET, MAX_POPUP_OFFSET);
+        self.popup_offset_y = self.popup_offset_y.clamp(-MAX_POPUP_OFFSET, MAX_POPUP_OFFSET);
+        self.label_prefix = truncate_label(self.label_prefix);
+        self.label_suffix = truncate_label(self.label_suffix);
+        self.placeholder = truncate_label(self.placeholder);
+        self.metric_separator = truncate_label(self.metric_separator);
+        self.prefix_labels.truncate(PREFIXES.len());
+        // A budget of nothing would make every percentage infinite
+        self.display_cap_bytes = self.display_cap_bytes.filter(|&cap| cap > 0);
+        self
+    }
+
+    /// The thresholds that the given metric is classified by.
+    ///
+    /// Those of RAM predate the others, and are kept as separate fields so that existing
+    /// configurations keep them.
+    fn thresholds(&self, metric: ThresholdMetric) -> Thresholds {
+        match metric {
+            ThresholdMetric::Ram => Thresholds {
+                warning: self.warning_threshold,
+                critical: self.critical_threshold,
+            },
+            ThresholdMetric::Swap => self.swap_thresholds,
+            ThresholdMetric::Process => self.process_thresholds,
         }
     }
+
 }
 
 #[derive(Clone, Debug)]
 enum Message {
     Tick, // Triggered on a user-defined interval
-    TogglePopup, // Mandatory for open and close the applet
+    Watchdog, // Triggered periodically, independently of Tick, to check that the metrics are still refreshed
+    TogglePopup, // Mandatory for open and close the applet; also sent over D-Bus
+    RetryPopup, // Try again to open the popup, now that the panel window may be ready
+    LaunchSystemMonitor, // Start the user's system monitor
+    CopyUsage, // Copy the usage summary to the clipboard
+    Hover(bool), // The pointer entered or left the panel button
+    SetBaseline, // Remember the current usage so that later usage can be compared to it
+    ClearBaseline, // Forget the usage that was remembered by SetBaseline
+    MarkA, // Remember the current usage as the start of a measurement
+    MarkB, // Remember the current usage as the end of the measurement started by MarkA
+    ClearMarkers, // Forget the usage that was remembered by MarkA and MarkB
+    ToggleMeminfo, // Expand or collapse the list of every memory statistic
+    DismissFirstRun, // The user is done with the introduction at the top of the popup
+    RestartSource, // Start the metrics source over, for example to reconnect to a remote host
+    UpdateSettingsFile(String), // The user changed the file that settings are exported to or imported from
+    ExportSettings, // Write the configuration to the settings file
+    ImportSettings, // Read the configuration from the settings file, and apply and save it
+    OpenConfigDir, // Open the directory that holds the configuration in the file manager
+    CopySettings, // Copy the settings that differ from the defaults to the clipboard, for sharing
+    UpdateSnippetIncludesCommands(bool), // The user toggled whether copied settings include commands
+    UpdatePinned(bool), // The user toggled whether the popup stays open on outside clicks
     PopupClosed(window::Id), // Mandatory for the applet to know if it's been closed
     UpdateStandard(Standard), // The user changed the standard in which byte counts are presented
+    UpdatePrefixBase(Option<Base>), // The user changed the base of the prefixes, apart from the standard
+    UpdatePrefixStyle(Option<PrefixStyle>), // The user changed how prefixes are labeled, apart from the standard
+    UpdateLikeFree(bool), // The user toggled whether byte counts are formatted like `free -h`
+    UpdateUnit(Unit), // The user changed whether to count in bytes or in bits
     UpdatePrecision(u32), // The user adjusted the precision of the byte counts
+    UpdateDecimalComma(bool), // The user toggled whether decimals are written after a comma
+    UpdateExpandSmallValues(bool), // The user toggled whether tiny values get extra decimals
+    UpdateRounding(Rounding), // The user changed how byte counts are rounded
     UpdatePrefix(Prefix), // The user changed the prefix with which byte counts are presented
+    UpdateAdvancedUnits(bool), // The user toggled whether the prefixes from peta up are offered
+    UpdateSmallestPrefix(Prefix), // The user changed the smallest prefix that byte counts are shown with
+    UpdatePrefixLabelsText(String), // The user typed the labels that replace the prefixes
+    UpdatePrefixLabels(String), // The user submitted the labels that replace the prefixes
+    UpdateAutoHysteresis(u32), // The user changed how eagerly the automatic prefix is promoted
+    UpdateLockPrefixToTotal(bool), // The user toggled whether all values share the prefix of the total
+    UpdateSwapMatchesRam(bool), // The user toggled whether swap on the panel uses the prefix of the RAM
+    UpdateAutoNiceValues(bool), // The user toggled whether the automatic prefix avoids awkward values
+    UpdateUnitOnTotalOnly(bool), // The user toggled whether only the total on the panel carries a unit
+    UpdateSmoothingMode(SmoothingMode), // The user changed how the displayed usage is smoothed
+    UpdateSmoothingFactor(u32), // The user changed how much the displayed usage is smoothed
+    UpdateMovingAverageWindow(u32), // The user changed over how many samples the usage is averaged
     UpdateInterval(String), // The user changed the interval with which the data is updated
+    UpdateAlignToClock(bool), // The user toggled whether ticks line up with the wall clock
+    UpdatePopupInterval(u64), // The user changed the update interval while the popup is open
+    UpdateFitPopupWidth(bool), // The user toggled whether the popup is as wide as its contents
+    UpdatePopupOffsetX(i32), // The user changed the horizontal distance by which the popup is moved
+    UpdatePopupOffsetY(i32), // The user changed the vertical distance by which the popup is moved
+    UpdateMissedTicks(MissedTicks), // The user changed how ticks that were missed are made up for
     UpdateShowTotal(bool), // The user toggled whether to show total RAM
+    UpdateStaticTotal(bool), // The user toggled whether the total is only formatted when it changes
+    UpdateShowTotalVertical(bool), // The user toggled whether to show total RAM on vertical panels
+    UpdateShowIconVertical(bool), // The user toggled whether to show the icon on vertical panels
+    UpdateDenominator(Denominator), // The user changed what usage is displayed relative to
+    UpdateUsedDefinition(UsedDefinition), // The user changed what memory counts as used
+    UpdateMonitorTarget(MonitorTarget), // The user changed whether RAM, swap or both are monitored
+    UpdateProcessTargetText(String), // The user typed which process is monitored
+    UpdateProcessTarget(String), // The user submitted which process is monitored
+    UpdateCombinedView(bool), // The user toggled whether the panel shows RAM and swap together
+    UpdateShowHeadroom(bool), // The user toggled whether the panel shows free rather than used memory
+    UpdateClickableSegments(bool), // The user toggled whether the usage and the total on the panel respond to clicks
+    UpdateMonospaceDigits(bool), // The user toggled whether the numbers on the panel use a monospaced font
+    SegmentClicked(Segment), // The user clicked a piece of text on the panel
+    UpdateShowTrend(bool), // The user toggled whether to show an arrow when usage changes
+    UpdateTrendEpsilon(u32), // The user changed the smallest change for which an arrow is shown
+    UpdateTrendMode(TrendMode), // The user changed when the trend arrow changes direction
+    UpdateShowUptime(bool), // The user toggled whether to show the system uptime in the popup
+    UpdateUsageInWords(bool), // The user toggled whether the tooltip describes the usage in words
+    UpdateUsageInWordsPopup(bool), // The user toggled whether the popup describes the usage in words
+    UpdateShowHistoryStats(bool), // The user toggled whether to show the lowest, average and highest usage
+    UpdateShowRateGraph(bool), // The user toggled whether to show how fast memory was allocated and freed
+    UpdateSmoothRateGraph(bool), // The user toggled whether the graph of the allocation rate is smoothed
+    UpdateGraphGapIntervals(u32), // The user changed after how many intervals without samples the graph breaks
+    UpdateShowBothStandards(bool), // The user toggled whether to show the usage in both SI and IEC units
+    UpdateShowOomEstimate(bool), // The user toggled whether to show when memory would run out
+    UpdateShowForecast(bool), // The user toggled whether to show a forecast of the usage
+    UpdateForecastHorizon(u32), // The user changed how many seconds ahead the forecast looks
+    UpdateShowLastUpdate(bool), // The user toggled whether to show how long ago statistics were read
+    UpdateCompareLastSession(bool), // The user toggled whether to compare the usage with the previous session
+    UpdateShowNuma(bool), // The user toggled whether to show per-node usage in the popup
+    UpdateShowEcc(bool), // The user toggled whether to show memory error counts in the popup
+    UpdateShowModules(bool), // The user toggled whether to show the installed memory modules in the popup
+    UpdateUsageBar(UsageBar), // The user changed which bar, if any, is shown in the popup
+    UpdateShowTopProcesses(bool), // The user toggled whether to list the largest processes in the popup
+    UpdatePinnedProcessText(String), // The user typed the name of a process to pin
+    PinProcess, // The user pinned the process whose name was typed
+    UnpinProcess(usize), // The user removed the pinned process at the given position
+    UpdateSwapNotifyThreshold(u32), // The user changed the swap usage at which to send a notification
+    UpdateRefreshOnOpen(bool), // The user toggled whether opening the popup refreshes the metrics
+    UpdateFreezeOnHover(bool), // The user toggled whether hovering pauses the panel's numbers
+    UpdatePrimaryAction(PrimaryAction), // The user changed what clicking the panel button does
+    UpdateSystemMonitorCommand(String), // The user changed the command that starts the system monitor
+    UpdatePopupShortcutText(String), // The user typed a key combination that toggles the popup
+    UpdatePopupShortcut(String), // The user submitted the key combination that toggles the popup
+    UpdateUsageLogPathText(String), // The user typed the file to which samples are logged
+    UpdateUsageLogPath(String), // The user submitted the file to which samples are logged
+    KeyPressed(Key, Modifiers), // A key was pressed while the applet had keyboard focus
+    UpdateDisplayMode(DisplayMode), // The user changed what is shown on the panel
+    UpdateLabelPrefix(String), // The user changed the text shown before the usage
+    UpdateLabelSuffix(String), // The user changed the text shown after the usage
+    UpdateSecondaryMetric(SlotMetric), // The user changed which metric is shown after the usage
+    UpdateSecondaryStyle(SlotStyle), // The user changed how the secondary metric is shown
+    UpdateMetricSeparator(String), // The user changed the text between the usage and the secondary metric
+    UpdateTextColorText(String), // The user typed the color of the text on the panel
+    UpdateTextColor(String), // The user submitted the color of the text on the panel
+    UpdateCriticalTextColor(bool), // The user toggled whether critical usage overrides the text color
+    UpdateCriticalBlink(bool), // The user toggled whether the text blinks while usage is critical
+    Blink, // Switch the blinking text to its other color
+    UpdateIconNameText(String), // The user typed the name of the icon shown on the panel
+    UpdateIconName(String), // The user submitted the name of the icon shown on the panel
+    UpdateIconStyle(IconStyle), // The user picked between the symbolic and the full-color icon
+    UpdateIconGradient(bool), // The user toggled whether to color the icon by usage
+    UpdateGaugeIcon(bool), // The user toggled whether to replace the icon with a usage gauge
+    UpdateGaugeSwapRing(bool), // The user toggled whether the gauge shows swap in an inner ring
+    UpdateGaugeLabel(bool), // The user toggled whether to show the percentage inside the gauge
+    UpdatePercentStep(u32), // The user changed the step to which percentages are rounded
+    UpdateClampPercent(bool), // The user toggled whether percentages are capped at 100
+    UpdateDisplayCap(Option<u64>), // The user changed the budget that usage is compared against
+    UpdateReducedMotion(bool), // The user toggled whether the icon only changes in coarse steps
+    UpdateTightLayout(bool), // The user toggled whether the icon is shown without padding
+    UpdateTintBackground(bool), // The user toggled whether the panel background is tinted by the usage
+    UpdateWarningThreshold(u32), // The user changed the usage from which it is considered high
+    UpdateCriticalThreshold(u32), // The user changed the usage from which it is considered critical
+    UpdateSwapThresholds(Thresholds), // The user changed the thresholds for swap usage
+    UpdateProcessThresholds(Thresholds), // The user changed the thresholds for the monitored process
+    UpdateCriticalActionCommandText(String), // The user typed the command that is run when usage is critical
+    UpdateCriticalActionCommand(String), // The user submitted the command that is run when usage is critical
+    UpdateCriticalSound(bool), // The user toggled whether a sound is played when usage is critical
+    UpdateCriticalSoundFileText(String), // The user typed the sound that is played when usage is critical
+    UpdateCriticalSoundFile(String), // The user submitted the sound that is played when usage is critical
+    SoundPlayed(Option<String>), // The critical sound finished playing, or failed to with the given error
+    UpdatePressureEvents(bool), // The user toggled whether to update when the kernel reports memory pressure
+    PressureEvent, // The kernel reported memory pressure
     ConfigChanged(CosmicAppletRamConfig), // The configuration values were somehow changed
+    ReloadConfig, // Read the configuration from disk again, as asked for with SIGHUP
     Surface(surface::Action), // Actions that should be re-routed to COSMIC
 }
 
 trait ResultExt {
+    /// Log the error, if any, at the error level.
     fn log<S: AsRef<str>>(self, msg: S);
+    /// Log the error, if any, at the given level.
+    fn log_at<S: AsRef<str>>(self, level: tracing::Level, msg: S);
+    /// Log the error, if any, at [`RECOVERABLE_LEVEL`], for failures that the applet gets over on
+    /// its own, such as failing to save the configuration.
+    fn log_recoverable<S: AsRef<str>>(self, msg: S);
 }
 
 impl <T, E: std::fmt::Display> ResultExt for std::result::Result<T, E> {
     fn log<S: AsRef<str>>(self, msg: S) {
-        if let Err(error) = self {
-            tracing::error!("{}: {}", msg.as_ref(), error);
+        self.log_at(tracing::Level::ERROR, msg);
+    }
+
+    fn log_at<S: AsRef<str>>(self, level: tracing::Level, msg: S) {
+        let Err(error) = self else {
+            return
+        };
+        // The macros need to know the level at compile time
+        let msg = msg.as_ref();
+        if level == tracing::Level::ERROR {
+            tracing::error!("{}: {}", msg, error);
+        } else if level == tracing::Level::WARN {
+            tracing::warn!("{}: {}", msg, error);
+        } else if level == tracing::Level::INFO {
+            tracing::info!("{}: {}", msg, error);
+        } else if level == tracing::Level::DEBUG {
+            tracing::debug!("{}: {}", msg, error);
+        } else {
+            tracing::trace!("{}: {}", msg, error);
         }
     }
+
+    fn log_recoverable<S: AsRef<str>>(self, msg: S) {
+        self.log_at(*RECOVERABLE_LEVEL, msg);
+    }
 }
 
+/// The level at which failures that the applet recovers from are logged, which is a warning unless
+/// [`RECOVERABLE_LEVEL_VAR`] names another one, such as `debug`.
+static RECOVERABLE_LEVEL: Lazy<tracing::Level> = Lazy::new(|| {
+    std::env::var(RECOVERABLE_LEVEL_VAR).ok()
+        .and_then(|level| level.trim().parse().ok())
+        .unwrap_or(tracing::Level::WARN)
+});
 
+/// The id of the autosize container around the panel button.
+///
+/// Widget ids only need to be unique within a process, and the panel runs a separate process for
+/// every panel and output that the applet is on, so instances never share one.
 static AUTOSIZE_MAIN_ID: Lazy<widget::Id> = Lazy::new(|| widget::Id::new("autosize-main"));
 
 impl Window {
 
+    /// Create the applet state around a metrics source and a history that were already set up,
+    /// and take a first sample from the source.
+    ///
+    /// Everything that is read from the session, such as the icon theme, is left to
+    /// [`cosmic::Application::init`], so that tests can build a window without it.
+    fn new(core: Core, config: Config, live_config: CosmicAppletRamConfig, source: Box<dyn MetricsSource>, history: History) -> Self {
+
+        let mut standard_model = SingleSelectModel::default();
+        let entity_si = standard_model
+            .insert()
+            .text("SI")
+            .data(Standard::Si)
+            .id();
+        let entity_iec = standard_model
+            .insert()
+            .data(Standard::Iec)
+            .text("IEC")
+            .id();
+
+        let mut unit_model = SingleSelectModel::default();
+        let entity_byte = unit_model
+            .insert()
+            .text("Bytes")
+            .data(Unit::Byte)
+            .id();
+        let entity_bit = unit_model
+            .insert()
+            .text("Bits")
+            .data(Unit::Bit)
+            .id();
+
+        let history_tx = watch::Sender::new(history.samples(dbus::MAX_HISTORY_SAMPLES));
+
+        let mut window = Window {
+            core, // Set the incoming core
+            popup: None, // No popup should be open on startup
+            sys: System::new(),
+            source,
+            source_error: None,
+            has_sample: false,
+            peak_total: 0,
+            memory: MemorySample::default(),
+            smoothed_used: None,
+            recent_used: VecDeque::new(),
+            hover_frozen: None,
+            total_text: RefCell::new(None),
+            refreshed_at: time::Instant::now(),
+            updated_at: None,
+            stalled: false,
+            blink_on: false,
+            rtl: locale_is_rtl(),
+            icon_name: String::from(DEFAULT_ICON_NAME),
+            icon_warning: None,
+            icon_name_text: live_config.icon_name.clone(),
+            degraded: None,
+            uptime: System::uptime(),
+            history,
+            history_saved_at: time::Instant::now(),
+            last_session_used: None,
+            last_session_saved_at: time::Instant::now(),
+            started_at: time::Instant::now(),
+            recorded_at: time::Instant::now(),
+            numa_nodes: Vec::new(),
+            numa_error_logged: false,
+            ecc_errors: None,
+            ecc_error_logged: false,
+            modules_summary: None,
+            top_processes: Vec::new(),
+            pinned_process_memory: Vec::new(),
+            pinned_process_text: String::new(),
+            popup_shortcut_text: String::new(),
+            text_color_text: live_config.text_color.clone(),
+            prefix_labels_text: live_config.prefix_labels.join(", "),
+            meminfo_expanded: false,
+            meminfo_fields: Vec::new(),
+            meminfo_error_logged: false,
+            process_memory: None,
+            process_pids: Vec::new(),
+            process_target_text: live_config.process_target.clone().unwrap_or_default(),
+            baseline: None,
+            marker_a: None,
+            marker_b: None,
+            used_at_open: None,
+            launched_at: None,
+            spawn: spawn_command,
+            create_source,
+            turns: TurnTracker::default(),
+            settings_file_text: dirs::home_dir()
+                .map(|home| home.join(SETTINGS_FILE_NAME).display().to_string())
+                .unwrap_or_default(),
+            settings_file_status: None,
+            stepping: None,
+            command_errors: CommandErrors::check(&live_config),
+            critical_action_command_text: live_config.critical_action_command.clone(),
+            critical_sound_file_text: live_config.critical_sound_file.clone(),
+            snippet_includes_commands: false,
+            config_dir: config_dir(),
+            newer_config_version: None,
+            unknown_config_fields: Vec::new(),
+            pinned: false,
+            popup_unavailable_at: None,
+            popup_retry_pending: false,
+            shortcut: None,
+            usage_log: None,
+            usage_log_path_text: String::new(),
+            swap_alert: ThresholdAlert::default(),
+            critical_alert: ThresholdAlert::default(),
+            sound_alert: ThresholdAlert::default(),
+            sound_unavailable: false,
+            standard_model,
+            entity_si,
+            entity_iec,
+            unit_model,
+            entity_byte,
+            entity_bit,
+            tick_settings_tx: watch::Sender::new(TickSettings {
+                msec: live_config.update_interval,
+                missed: live_config.missed_ticks,
+            }),
+            history_tx,
+            time_subscription_generation: 0,
+            update_interval_text: live_config.update_interval.to_string(),
+            live_config,
+            config,
+        };
+
+        // Force the segmented controls to select their initial value
+        window.ui_set_standard(window.live_config.standard);
+        window.ui_set_unit(window.live_config.unit);
+        window.ui_set_popup_shortcut(window.live_config.popup_shortcut.clone());
+        window.ui_set_usage_log_path(window.live_config.usage_log_path.clone());
+
+        // Immediately load statistics when the application loads
+        window.refresh_metrics();
+        window
+    }
+
     /// Low-level utility to change the amount of milliseconds between each tick.
     ///
     /// This method does not save configuration.
     fn set_ticks(&mut self, msec: u64) {
-        // Don't panic if the update could not be processed
-        let _ = self.update_interval_tx.send(msec);
+        self.source.set_interval(msec);
+        let settings = TickSettings {
+            msec,
+            missed: self.live_config.missed_ticks,
+        };
+        if let Err(error) = self.tick_settings_tx.send(settings) {
+            // The receiver is only dropped if the time subscription somehow stopped
+            tracing::error!("Failed to change the update interval, restarting timer: {}", error);
+            // `send` doesn't store the value when there are no receivers left
+            self.tick_settings_tx.send_replace(settings);
+            // A new ID makes iced start a fresh subscription with a new receiver
+            self.time_subscription_generation += 1;
+        }
+    }
+
+    /// Changes where memory statistics are read from.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_source(&mut self, source: Source) {
+        self.source = (self.create_source)(&source, self.live_config.update_interval);
+        self.live_config.source = source;
+        self.command_errors = CommandErrors::check(&self.live_config);
+        // What the previous source sampled says nothing about the new one
+        self.has_sample = false;
+        self.peak_total = 0;
+        self.updated_at = None;
+        self.refresh_metrics();
+    }
+
+    /// Replace the metrics source by a new one of the same kind, along with the state of sysinfo.
+    ///
+    /// Dropping a remote source stops its background thread once the command that it is running
+    /// returns, so a command that hangs doesn't keep the new source from starting.
+    fn restart_source(&mut self) {
+        tracing::info!("Restarting the metrics source");
+        self.sys = System::new();
+        self.source_error = None;
+        self.ui_set_source(self.live_config.source.clone());
     }
 
     /// Changes the standard with which counters are formatted.
@@ -136,6 +1167,40 @@ impl Window {
         self.live_config.standard = standard;
     }
 
+    /// Changes the base of the prefixes, or lets it follow the standard if `None`.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_prefix_base(&mut self, base: Option<Base>) {
+        self.live_config.prefix_base = base;
+    }
+
+    /// Changes how prefixes are labeled, or lets that follow the standard if `None`.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_prefix_style(&mut self, style: Option<PrefixStyle>) {
+        self.live_config.prefix_style = style;
+    }
+
+    /// Changes whether byte counts are formatted like `free -h`.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_like_free(&mut self, enable: bool) {
+        self.live_config.like_free = enable;
+    }
+
+    /// Changes the unit in which counters are displayed.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_unit(&mut self, unit: Unit) {
+        self.unit_model.activate(
+            match unit {
+                Unit::Byte => self.entity_byte,
+                Unit::Bit => self.entity_bit,
+            }
+        );
+        self.live_config.unit = unit;
+    }
+
     /// Changes the interval at which the UI updates to the given value.
     ///
     /// This method overrides whatever value was present in the text input. The text input will be
@@ -145,512 +1210,9036 @@ impl Window {
     fn ui_set_update_interval(&mut self, msec: u64) {
         self.live_config.update_interval = msec;
         self.update_interval_text = msec.to_string();
-        self.set_ticks(msec);
+        self.apply_interval();
+        // A window given in minutes holds a different amount of samples at another interval
+        self.history.set_capacity(self.live_config.history_window.capacity(msec));
+        self.publish_history();
     }
 
-    /// Changes the prefix with which counters are displayed.
+    /// Changes how much usage history is kept.
     ///
     /// This method does not save configuration.
-    fn ui_set_prefix(&mut self, prefix: Prefix) {
-        self.live_config.prefix = prefix;
+    fn ui_set_history_window(&mut self, window: HistoryWindow) {
+        self.live_config.history_window = window;
+        self.history.set_capacity(window.capacity(self.live_config.update_interval));
+        self.history.set_max_age(window.max_age());
+        self.publish_history();
     }
 
-    /// Changes the precision with which counters are formatted.
+    /// Changes whether ticks are aligned to the wall clock.
     ///
     /// This method does not save configuration.
-    fn ui_set_precision(&mut self, precision: u32) {
-        self.live_config.precision = precision;
+    fn ui_set_align_to_clock(&mut self, enable: bool) {
+        self.live_config.align_to_clock = enable;
     }
 
-    /// Change whether to display the total installed amount of RAM.
+    /// Changes how ticks that were missed are made up for.
     ///
     /// This method does not save configuration.
-    fn ui_set_show_total(&mut self, enable: bool) {
-        self.live_config.show_total = enable;
+    fn ui_set_missed_ticks(&mut self, missed: MissedTicks) {
+        self.live_config.missed_ticks = missed;
+        self.set_ticks(self.effective_interval());
     }
 
-    /// Refresh the metrics that are rendered to the screen.
-    fn refresh_metrics(&mut self) {
-        self.sys.refresh_memory();
-        self.used = self.sys.used_memory();
-        self.total = self.sys.total_memory();
+    /// Changes the update interval while the popup is open, where 0 keeps the panel's.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_popup_update_interval(&mut self, msec: u64) {
+        self.live_config.popup_update_interval = msec;
+        self.apply_interval();
     }
 
-}
-
-impl cosmic::Application for Window {
-    /*
-    *  Executors are a mandatory thing for both COSMIC Applications and Applets.
-    *  They're basically what allows for multi-threaded async operations for things that
-    *  may take too long and block the thread the GUI is running on. This is also where
-    *  Tasks take place.
-    */
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = (); // Argument passed to init()
-    type Message = Message; // These are setting the application messages to our Message enum
-    const APP_ID: &'static str = ID; // This is where we set our const above to the actual ID
-
-    // Setup the immutable core functionality.
-    fn core(&self) -> &Core {
-        &self.core
+    /// Changes whether the popup is sized to its contents, which takes effect when it next opens.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_fit_popup_width(&mut self, enable: bool) {
+        self.live_config.fit_popup_width = enable;
     }
 
-    // Set up the mutable core functionality.
-    fn core_mut(&mut self) -> &mut Core {
-        &mut self.core
+    /// Changes the horizontal distance by which the popup is moved, which takes effect when it next
+    /// opens.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_popup_offset_x(&mut self, offset: i32) {
+        self.live_config.popup_offset_x = offset.clamp(-MAX_POPUP_OFFSET, MAX_POPUP_OFFSET);
     }
 
-    // Initialize the applet
-    /*
-    *  The parameters are the Core and flags (again not sure what to do with these).
-    *  The function returns our model struct initialized and an Option<Task>, in this case
-    *  there is no command so it returns a None value with the type of Task in its place.
-    */
-    fn init(core: Core, _flags: Self::Flags) -> (Self, Task<Self::Message>) {
+    /// Changes the vertical distance by which the popup is moved, which takes effect when it next
+    /// opens.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_popup_offset_y(&mut self, offset: i32) {
+        self.live_config.popup_offset_y = offset.clamp(-MAX_POPUP_OFFSET, MAX_POPUP_OFFSET);
+    }
 
-        let mut standard_model = SingleSelectModel::default();
-        let entity_si = standard_model
-            .insert()
-            .text("SI")
-            .data(Standard::Si)
-            .id();
-        let entity_iec = standard_model
-            .insert()
-            .data(Standard::Iec)
-            .text("IEC")
-            .id();
+    /// The rows of the popup whose contents vary the most in width, as pairs of a label and a
+    /// value, for sizing the popup to them.
+    fn wide_popup_rows(&self) -> Vec<(String, String)> {
+        let mut rows: Vec<(String, String)> = self.top_processes.iter()
+            .map(|process| (process.name.clone(), self.format(process.memory)))
+            .collect();
+        if let Some(Some(summary)) = &self.modules_summary {
+            rows.push((String::from("Modules"), summary.clone()));
+        }
+        if let Some(error) = &self.source_error {
+            rows.push((String::from("Source"), format!("Failing: {error}")));
+        }
+        if let Some(dir) = &self.config_dir {
+            rows.push((String::from("Configuration"), dir.display().to_string()));
+        }
+        rows
+    }
 
-        let config = Config::new(ID, VERSION).expect("failed to load config for RAM usage applet");
+    /// The interval at which the applet currently updates, in milliseconds.
+    ///
+    /// While the popup is open this is the popup's interval, unless that is slower than the
+    /// panel's, since opening the popup shouldn't make the numbers any more stale.
+    fn effective_interval(&self) -> u64 {
+        let panel = self.live_config.update_interval;
+        match self.live_config.popup_update_interval {
+            popup if popup != 0 && self.popup.is_some() => popup.min(panel),
+            _ => panel,
+        }
+    }
 
-        let live_config = CosmicAppletRamConfig::get_entry(&config).unwrap_or_default();
+    /// Switch the timer to the effective interval, if it isn't ticking at that one already.
+    fn apply_interval(&mut self) {
+        let msec = self.effective_interval();
+        if self.tick_settings_tx.borrow().msec != msec {
+            self.set_ticks(msec);
+        }
+    }
 
-        let mut window = Window {
-            core, // Set the incoming core
-            popup: None, // No popup should be open on startup
-            sys: System::new(),
-            used: 0,
-            total: 0,
-            standard_model,
-            entity_si,
-            entity_iec,
-            update_interval_tx: watch::Sender::new(live_config.update_interval),
-            update_interval_text: live_config.update_interval.to_string(),
-            live_config,
-            config,
-        };
+    /// Whether the history is due another sample.
+    ///
+    /// The history keeps the pace of the panel's interval while the popup updates faster, since
+    /// its capacity is worked out from the panel's interval.
+    fn sample_due(&self) -> bool {
+        let interval = time::Duration::from_millis(self.live_config.update_interval);
+        // Half a tick of slack keeps jitter from making every other sample wait an extra tick
+        let slack = time::Duration::from_millis(self.effective_interval()) / 2;
+        self.recorded_at.elapsed() + slack >= interval
+    }
 
-        // Force the segmented control to select its initial value
-        window.ui_set_standard(window.live_config.standard);
+    /// Changes the prefix with which counters are displayed.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_prefix(&mut self, prefix: Prefix) {
+        self.live_config.prefix = prefix;
+    }
 
-        // Immediately load statistics when the application loads
-        window.refresh_metrics();
+    /// Changes whether the prefix dropdown offers the prefixes from peta up.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_advanced_units(&mut self, enable: bool) {
+        self.live_config.advanced_units = enable;
+    }
 
-        (window, Task::none())
+    /// Changes the smallest prefix with which counters are displayed.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_smallest_prefix(&mut self, prefix: Prefix) {
+        self.live_config.smallest_prefix = prefix;
     }
 
-    // Create what happens when the applet is closed
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        // Pass the PopupClosed message to the update function
-        Some(Message::PopupClosed(id))
+    /// Changes the labels that replace the prefixes, in the order of [`PREFIXES`].
+    ///
+    /// The text input will be changed to reflect the given labels. This method does not save
+    /// configuration.
+    fn ui_set_prefix_labels(&mut self, mut labels: Vec<String>) {
+        labels.truncate(PREFIXES.len());
+        self.prefix_labels_text = labels.join(", ");
+        self.live_config.prefix_labels = labels;
     }
 
-    fn subscription(&self) -> cosmic::iced::Subscription<Self::Message> {
-        fn time_subscription(mut msec_watch: watch::Receiver<u64>) -> Subscription<Message> {
-            Subscription::run_with_id(
-                "time-sub",
-                stream::channel(1, |mut output| async move {
-                    // Mark this receiver's state as changed so that it always receives an initial
-                    // update during the loop below
-                    // This allows us to avoid duplicating code from the loop
-                    msec_watch.mark_changed();
-                    let mut msec = 1000;
-                    let mut timer = time::interval(time::Duration::from_millis(msec));
-                    timer.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
-
-                    loop {
-                        tokio::select! {
-                            _ = timer.tick() => {
-                                output.send(Message::Tick).await.log("Failed sending tick request to applet");
-                            },
-                            // Update timer if the user toggles show_seconds
-                            Ok(()) = msec_watch.changed() => {
-                                msec = *msec_watch.borrow_and_update();
-                                let period = time::Duration::from_millis(msec);
-                                let start = time::Instant::now() + period;
-                                timer = time::interval_at(start, period);
-                                timer.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
-                            }
-                        }
-                    }
-                }),
-            )
-        }
-        let show_seconds_rx = self.update_interval_tx.subscribe();
-        Subscription::batch(vec![
-            self.core
-                .watch_config(Self::APP_ID)
-                .map(|u| {
-                    for err in u.errors {
-                        tracing::error!(?err, "Error watching config");
-                    }
-                    Message::ConfigChanged(u.config)
-                }),
-            time_subscription(show_seconds_rx),
-        ])
+    /// Changes how far past a boundary a counter must be before the automatic prefix is promoted.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_auto_hysteresis(&mut self, percent: u32) {
+        self.live_config.auto_hysteresis = percent;
+    }
+
+    /// Changes whether the automatic prefix is chosen once from the total memory.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_lock_prefix_to_total(&mut self, enable: bool) {
+        self.live_config.lock_prefix_to_total = enable;
+    }
+
+    /// Changes whether swap on the panel is formatted with the prefix of the RAM.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_swap_matches_ram(&mut self, enable: bool) {
+        self.live_config.swap_matches_ram = enable;
+    }
+
+    /// Changes whether the automatic prefix keeps the integer part of values between 1 and 999.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_auto_nice_values(&mut self, enable: bool) {
+        self.live_config.auto_nice_values = enable;
+    }
+
+    /// Changes whether the used memory on the panel is shown without a unit when it shares the
+    /// unit of the total.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_unit_on_total_only(&mut self, enable: bool) {
+        self.live_config.unit_on_total_only = enable;
+    }
+
+    /// Changes how much of the previously displayed usage is retained on each tick.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_smoothing_factor(&mut self, percent: u32) {
+        self.live_config.smoothing_factor = percent;
+    }
+
+    /// Changes whether the displayed usage is smoothed with an exponential or a moving average.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_smoothing_mode(&mut self, mode: SmoothingMode) {
+        self.live_config.smoothing_mode = mode;
+    }
+
+    /// Changes over how many samples the moving average is taken.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_moving_average_window(&mut self, samples: u32) {
+        self.live_config.moving_average_window = samples;
+        while self.recent_used.len() > samples as usize {
+            self.recent_used.pop_front();
+        }
+    }
+
+    /// Whether the displayed usage differs from the latest sample because it is smoothed.
+    fn smoothing_enabled(&self) -> bool {
+        match self.live_config.smoothing_mode {
+            SmoothingMode::Exponential => self.live_config.smoothing_factor > 0,
+            SmoothingMode::MovingAverage => self.live_config.moving_average_window > 1,
+        }
+    }
+
+    /// Changes the precision with which counters are formatted.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_precision(&mut self, precision: u32) {
+        self.live_config.precision = clamp_precision(precision);
+    }
+
+    /// Changes whether decimals are written after a comma.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_decimal_comma(&mut self, enable: bool) {
+        self.live_config.decimal_comma = enable;
+    }
+
+    /// Changes whether values that would round to zero are shown with more decimals.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_expand_small_values(&mut self, enable: bool) {
+        self.live_config.expand_small_values = enable;
+    }
+
+    /// Changes how byte counts are rounded to the configured precision.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_rounding(&mut self, rounding: Rounding) {
+        self.live_config.rounding = rounding;
+    }
+
+    /// Change whether to display the total installed amount of RAM.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_total(&mut self, enable: bool) {
+        self.live_config.show_total = enable;
+    }
+
+    /// Change whether the total on the panel is formatted only when it changes.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_static_total(&mut self, enable: bool) {
+        self.live_config.static_total = enable;
+    }
+
+    /// Change whether to display the total installed amount of RAM when the panel is vertical.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_total_vertical(&mut self, enable: bool) {
+        self.live_config.show_total_vertical = enable;
+    }
+
+    /// Change whether to display the icon when the panel is vertical.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_icon_vertical(&mut self, enable: bool) {
+        self.live_config.show_icon_vertical = enable;
+    }
+
+    /// Changes what the used memory is displayed relative to.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_denominator(&mut self, denominator: Denominator) {
+        self.live_config.denominator = denominator;
+    }
+
+    /// Changes what memory counts as used.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_used_definition(&mut self, definition: UsedDefinition) {
+        self.live_config.used_definition = definition;
+        self.refresh_metrics();
+    }
+
+    /// Change whether RAM, swap or both are monitored.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_monitor_target(&mut self, target: MonitorTarget) {
+        self.live_config.monitor_target = target;
+        // Whatever was frozen is of the other kind of memory
+        if self.hover_frozen.is_some() {
+            self.hover_frozen = Some((self.live_used(), self.live_total()));
+        }
+    }
+
+    /// Change which process is monitored, if any.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_process_target(&mut self, target: Option<String>) {
+        self.process_target_text = target.clone().unwrap_or_default();
+        self.live_config.process_target = target;
+        self.process_pids.clear();
+        self.refresh_process_memory();
+        if self.hover_frozen.is_some() {
+            self.hover_frozen = Some((self.live_used(), self.live_total()));
+        }
+    }
+
+    /// Change whether to display the free rather than the used memory.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_headroom(&mut self, enable: bool) {
+        self.live_config.show_headroom = enable;
+    }
+
+    /// Change whether the usage and the total on the panel respond to clicks.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_clickable_segments(&mut self, enable: bool) {
+        self.live_config.clickable_segments = enable;
+    }
+
+    /// Change whether the numbers on the panel are drawn in a monospaced font.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_monospace_digits(&mut self, enable: bool) {
+        self.live_config.monospace_digits = enable;
+    }
+
+    /// Whether a piece of the panel label is drawn in a monospaced font.
+    ///
+    /// Every number is, while labels, separators and arrows keep the panel font.
+    fn monospaced(&self, segment: Segment) -> bool {
+        self.live_config.monospace_digits && segment != Segment::Plain
+    }
+
+    /// Change whether to display RAM and swap added together on the panel.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_combined_view(&mut self, enable: bool) {
+        self.live_config.combined_view = enable;
+    }
+
+    /// Change whether to display an arrow when the usage went up or down.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_trend(&mut self, enable: bool) {
+        self.live_config.show_trend = enable;
+    }
+
+    /// Changes the smallest change in usage, in MiB, for which an arrow is shown.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_trend_epsilon(&mut self, mib: u32) {
+        self.live_config.trend_epsilon = mib;
+    }
+
+    /// Changes whether the trend arrow follows every tick or only turning points.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_trend_mode(&mut self, mode: TrendMode) {
+        self.live_config.trend_mode = mode;
+    }
+
+    /// Which way the usage is heading, as the trend arrow shows it.
+    fn shown_trend(&self) -> Trend {
+        match self.live_config.trend_mode {
+            TrendMode::EveryUpdate => self.trend(),
+            TrendMode::Turns => self.turns.confirmed,
+        }
+    }
+
+    /// Which way the usage went since the previous tick.
+    fn trend(&self) -> Trend {
+        match self.history.last_change() {
+            Some((before, after)) => Trend::between(before, after, u64::from(self.live_config.trend_epsilon) * 1024 * 1024),
+            None => Trend::Steady,
+        }
+    }
+
+    /// Change whether to display the time since the system booted in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_uptime(&mut self, enable: bool) {
+        self.live_config.show_uptime = enable;
+    }
+
+    /// Change whether the tooltip describes the usage in plain words.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_usage_in_words(&mut self, enable: bool) {
+        self.live_config.usage_in_words = enable;
+    }
+
+    /// Change whether the popup describes the usage in plain words.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_usage_in_words_popup(&mut self, enable: bool) {
+        self.live_config.usage_in_words_popup = enable;
+    }
+
+    /// Change whether to display statistics over the usage history in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_history_stats(&mut self, enable: bool) {
+        self.live_config.show_history_stats = enable;
+    }
+
+    /// Change whether to display the graph of the allocation rate in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_rate_graph(&mut self, enable: bool) {
+        self.live_config.show_rate_graph = enable;
+    }
+
+    /// Change whether the graph of the allocation rate is smoothed.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_smooth_rate_graph(&mut self, enable: bool) {
+        self.live_config.smooth_rate_graph = enable;
+    }
+
+    /// Change after how many update intervals without samples the rate graph shows a break.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_graph_gap_intervals(&mut self, intervals: u32) {
+        self.live_config.graph_gap_intervals = clamp_graph_gap_intervals(intervals);
+    }
+
+    /// Change whether to display the used memory in both standards in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_both_standards(&mut self, enable: bool) {
+        self.live_config.show_both_standards = enable;
+    }
+
+    /// Change whether to display an estimate of when memory runs out in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_oom_estimate(&mut self, enable: bool) {
+        self.live_config.show_oom_estimate = enable;
+    }
+
+    /// Change whether to display a forecast of the usage in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_forecast(&mut self, enable: bool) {
+        self.live_config.show_forecast = enable;
+    }
+
+    /// Change how many seconds ahead the forecast looks.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_forecast_horizon(&mut self, seconds: u32) {
+        self.live_config.forecast_horizon = seconds;
+    }
+
+    /// Change whether to display how long ago memory statistics were last read in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_last_update(&mut self, enable: bool) {
+        self.live_config.show_last_update = enable;
+    }
+
+    /// Change whether to compare the usage with how the previous session ended.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_compare_last_session(&mut self, enable: bool) {
+        self.live_config.compare_last_session = enable;
+    }
+
+    /// Forecast the used memory `forecast_horizon` seconds from now, by extending the line through
+    /// the history, or `None` if there are too few samples to go by.
+    ///
+    /// No more memory can be used than there is, so the forecast stops at the total.
+    fn forecast(&self) -> Option<u64> {
+        let horizon = f64::from(self.live_config.forecast_horizon);
+        let used = self.history.forecast(OOM_ESTIMATE_MIN_SAMPLES, horizon)?;
+        Some(used.min(self.memory.total))
+    }
+
+    /// Estimate how long it takes, in seconds, until all memory is used when usage keeps growing
+    /// at the rate of the history.
+    ///
+    /// `Some(None)` means that usage isn't growing fast enough to tell, and `None` that there
+    /// aren't enough samples yet.
+    fn oom_estimate(&self) -> Option<Option<u64>> {
+        let per_second = self.history.slope(OOM_ESTIMATE_MIN_SAMPLES)?;
+        if per_second < OOM_ESTIMATE_MIN_SLOPE {
+            return Some(None)
+        }
+        let remaining = self.memory.total.saturating_sub(self.memory.used) as f64;
+        Some(Some((remaining / per_second) as u64))
+    }
+
+    /// Change whether to display the usage of each NUMA node in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_numa(&mut self, enable: bool) {
+        self.live_config.show_numa = enable;
+        self.refresh_numa_nodes();
+    }
+
+    /// Change whether to display the memory errors that ECC detected in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_ecc(&mut self, enable: bool) {
+        self.live_config.show_ecc = enable;
+        self.refresh_ecc_errors();
+    }
+
+    /// Change whether to display the installed memory modules in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_modules(&mut self, enable: bool) {
+        self.live_config.show_modules = enable;
+        self.read_modules();
+    }
+
+    /// Read the installed memory modules, unless they were read before or aren't shown.
+    fn read_modules(&mut self) {
+        if !self.live_config.show_modules || self.modules_summary.is_some() {
+            return
+        }
+        self.modules_summary = Some(match meminfo::read_memory_modules() {
+            Ok(modules) if modules.is_empty() => None,
+            Ok(modules) => Some(meminfo::summarize_modules(&modules)),
+            Err(error) => {
+                // Only root can usually read the firmware tables, so this is the common case
+                tracing::info!("Failed to read the installed memory modules: {}", error);
+                None
+            }
+        });
+    }
+
+    /// Change which bar, if any, is shown in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_usage_bar(&mut self, bar: UsageBar) {
+        self.live_config.usage_bar = bar;
+    }
+
+    /// Change whether to list the processes that use the most memory in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_show_top_processes(&mut self, enable: bool) {
+        self.live_config.show_top_processes = enable;
+        self.refresh_processes();
+    }
+
+    /// Change which processes are always listed in the popup.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_pinned_processes(&mut self, names: Vec<String>) {
+        self.live_config.pinned_processes = names;
+        self.refresh_processes();
+    }
+
+    /// Changes the percentage of swap usage above which a notification is shown.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_swap_notify_threshold(&mut self, percent: u32) {
+        self.live_config.swap_notify_threshold = percent;
+    }
+
+    /// Change whether opening the popup immediately refreshes the metrics.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_refresh_on_open(&mut self, enable: bool) {
+        self.live_config.refresh_on_open = enable;
+    }
+
+    /// Change whether the numbers on the panel stop changing while the pointer hovers over them.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_freeze_on_hover(&mut self, enable: bool) {
+        self.live_config.freeze_on_hover = enable;
+        if !enable {
+            self.hover_frozen = None;
+        }
+    }
+
+    /// Changes what happens when the panel button is clicked.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_primary_action(&mut self, action: PrimaryAction) {
+        self.live_config.primary_action = action;
+    }
+
+    /// Changes the command that is run to open the system monitor.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_system_monitor_command(&mut self, command: String) {
+        self.live_config.system_monitor_command = command;
+        self.command_errors = CommandErrors::check(&self.live_config);
+    }
+
+    /// Changes the file to which every sample is appended.
+    ///
+    /// The text input will be changed to reflect the given path. This method does not save
+    /// configuration.
+    fn ui_set_usage_log_path(&mut self, path: String) {
+        self.usage_log_path_text = path.clone();
+        self.usage_log = if path.trim().is_empty() {
+            None
+        } else {
+            let max_size = u64::from(self.live_config.usage_log_max_size.max(1)) * 1024 * 1024;
+            Some(UsageLog::new(PathBuf::from(path.trim()), max_size))
+        };
+        self.live_config.usage_log_path = path;
+    }
+
+    /// Changes the size in MiB at which the usage log is rotated.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_usage_log_max_size(&mut self, size: u32) {
+        self.live_config.usage_log_max_size = size;
+        // The size limit is passed along when the log is created
+        self.ui_set_usage_log_path(self.live_config.usage_log_path.clone());
+    }
+
+    /// Changes the key combination that toggles the popup.
+    ///
+    /// The text input will be changed to reflect the given combination. An invalid combination
+    /// disables the shortcut. This method does not save configuration.
+    fn ui_set_popup_shortcut(&mut self, combo: String) {
+        self.popup_shortcut_text = combo.clone();
+        self.shortcut = if combo.trim().is_empty() {
+            None
+        } else {
+            match Shortcut::parse(&combo) {
+                Ok(shortcut) => Some(shortcut),
+                Err(error) => {
+                    tracing::warn!("Ignoring invalid popup shortcut: {}", error);
+                    None
+                }
+            }
+        };
+        self.live_config.popup_shortcut = combo;
+    }
+
+    /// Changes what is rendered on the panel.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_display_mode(&mut self, mode: DisplayMode) {
+        self.live_config.display_mode = mode;
+    }
+
+    /// Changes the text shown on the panel before the usage.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_label_prefix(&mut self, label: String) {
+        self.live_config.label_prefix = truncate_label(label);
+    }
+
+    /// Changes the text shown on the panel after the usage.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_label_suffix(&mut self, label: String) {
+        self.live_config.label_suffix = truncate_label(label);
+    }
+
+    /// Changes the text shown on the panel instead of the usage until the first sample arrives.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_placeholder(&mut self, placeholder: String) {
+        self.live_config.placeholder = truncate_label(placeholder);
+    }
+
+    /// Changes the metric that is shown on the panel after the usage.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_secondary_metric(&mut self, slot: MetricSlot) {
+        self.live_config.secondary_metric = slot;
+    }
+
+    /// Changes the text shown between the usage and the secondary metric.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_metric_separator(&mut self, separator: String) {
+        self.live_config.metric_separator = truncate_label(separator);
+    }
+
+    /// Changes the color of the text on the panel.
+    ///
+    /// The text input will be changed to reflect the given color. A color that can't be parsed
+    /// falls back to the theme's foreground color. This method does not save configuration.
+    fn ui_set_text_color(&mut self, color: String) {
+        self.text_color_text = color.clone();
+        if parse_text_color(&color, &cosmic::theme::active()).is_none() && !color.trim().is_empty() {
+            tracing::warn!("Ignoring invalid panel text color '{}'", color);
+        }
+        self.live_config.text_color = color;
+    }
+
+    /// Changes whether critical usage colors the text on the panel regardless of its color.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_critical_text_color(&mut self, enable: bool) {
+        self.live_config.critical_text_color = enable;
+    }
+
+    /// Changes whether the text on the panel blinks while usage is critical.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_critical_blink(&mut self, enable: bool) {
+        self.live_config.critical_blink = enable;
+    }
+
+    /// Changes the icon that is shown on the panel.
+    ///
+    /// The text input will be changed to reflect the given name. This method does not save
+    /// configuration.
+    fn ui_set_icon_name(&mut self, name: String) {
+        self.icon_name_text = name.clone();
+        self.live_config.icon_name = name;
+        self.resolve_icon();
+    }
+
+    /// Look up the configured icon in the icon theme.
+    ///
+    /// If it can't be found, the last icon that could is kept and a warning is shown in the popup.
+    /// The warning is only logged once for as long as the same name can't be found.
+    fn resolve_icon(&mut self) {
+        let name = &self.live_config.icon_name;
+        if icon::from_name(name.as_str()).path().is_some() {
+            self.icon_name = name.clone();
+            self.icon_warning = None;
+        } else {
+            let warning = format!("Icon '{name}' not found, keeping '{}'", self.icon_name);
+            if self.icon_warning.as_ref() != Some(&warning) {
+                tracing::warn!("{}", warning);
+            }
+            self.icon_warning = Some(warning);
+        }
+    }
+
+    /// Change whether the symbolic or the full-color icon is shown.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_icon_style(&mut self, style: IconStyle) {
+        self.live_config.icon_style = style;
+    }
+
+    /// Change whether the icon is tinted according to how much memory is in use.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_icon_gradient(&mut self, enable: bool) {
+        self.live_config.icon_gradient = enable;
+    }
+
+    /// Change whether the icon is replaced by a ring that fills up as memory is used.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_gauge_icon(&mut self, enable: bool) {
+        self.live_config.gauge_icon = enable;
+    }
+
+    /// Change whether the gauge has an inner ring for swap.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_gauge_swap_ring(&mut self, enable: bool) {
+        self.live_config.gauge_swap_ring = enable;
+    }
+
+    /// Change whether the percentage of memory in use is shown inside the gauge.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_gauge_label(&mut self, enable: bool) {
+        self.live_config.gauge_label = enable;
+    }
+
+    /// Changes the step to which displayed percentages are rounded.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_percent_step(&mut self, step: u32) {
+        self.live_config.percent_step = step;
+    }
+
+    /// Changes whether displayed percentages are capped at 100.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_clamp_percent(&mut self, enable: bool) {
+        self.live_config.clamp_percent = enable;
+    }
+
+    /// Changes the budget in bytes that usage is compared against instead of the total memory.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_display_cap_bytes(&mut self, cap: Option<u64>) {
+        // As in `validated`, a budget of nothing is no budget at all
+        self.live_config.display_cap_bytes = cap.filter(|&cap| cap > 0);
+    }
+
+    /// A percentage as it is displayed, capped at 100 if the user wants that and rounded to the
+    /// configured step.
+    fn display_percent(&self, percent: f64) -> f64 {
+        snap_percent(self.clamped_percent(percent), self.live_config.percent_step)
+    }
+
+    /// A percentage capped at 100 if the user wants that.
+    fn clamped_percent(&self, percent: f64) -> f64 {
+        if self.live_config.clamp_percent { percent.min(100.0) } else { percent }
+    }
+
+    /// Change whether the icon only follows usage in coarse steps.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_reduced_motion(&mut self, enable: bool) {
+        self.live_config.reduced_motion = enable;
+    }
+
+    /// Change whether the icon is shown without the padding that the panel suggests.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_tight_layout(&mut self, enable: bool) {
+        self.live_config.tight_layout = enable;
+    }
+
+    /// Change whether the background of the panel button is tinted by the usage.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_tint_background(&mut self, enable: bool) {
+        self.live_config.tint_background = enable;
+    }
+
+    /// Changes the percentage of memory usage from which usage is considered high.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_warning_threshold(&mut self, percent: u32) {
+        self.live_config.warning_threshold = percent;
+    }
+
+    /// Changes the percentage of memory usage from which usage is considered critical.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_critical_threshold(&mut self, percent: u32) {
+        self.live_config.critical_threshold = percent;
+    }
+
+    /// Changes the thresholds for swap usage.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_swap_thresholds(&mut self, thresholds: Thresholds) {
+        self.live_config.swap_thresholds = thresholds;
+    }
+
+    /// Changes the thresholds for the memory usage of the monitored process.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_process_thresholds(&mut self, thresholds: Thresholds) {
+        self.live_config.process_thresholds = thresholds;
+    }
+
+    /// Changes the command that is run when memory usage becomes critical.
+    ///
+    /// The text input will be changed to reflect the given command. This method does not save
+    /// configuration.
+    fn ui_set_critical_action_command(&mut self, command: String) {
+        self.critical_action_command_text = command.clone();
+        self.live_config.critical_action_command = command;
+        self.command_errors = CommandErrors::check(&self.live_config);
+    }
+
+    /// Changes whether a sound is played when memory usage becomes critical.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_critical_sound(&mut self, enable: bool) {
+        self.live_config.critical_sound = enable;
+        // Give the sound another chance, for example after installing a player
+        self.sound_unavailable = false;
+    }
+
+    /// Changes the sound that is played when memory usage becomes critical.
+    ///
+    /// The text input will be changed to reflect the given path. This method does not save
+    /// configuration.
+    fn ui_set_critical_sound_file(&mut self, path: String) {
+        self.critical_sound_file_text = path.clone();
+        self.live_config.critical_sound_file = path;
+        self.sound_unavailable = false;
+    }
+
+    /// Changes whether the kernel's memory pressure events trigger an update.
+    ///
+    /// This method does not save configuration.
+    fn ui_set_pressure_events(&mut self, enable: bool) {
+        self.live_config.pressure_events = enable;
+    }
+
+    /// The color of the text on the panel, or `None` for the theme's foreground color.
+    fn panel_text_color(&self) -> Option<Color> {
+        let theme = cosmic::theme::active();
+        if self.blinking() {
+            return if self.blink_on {
+                Some(UsageBand::Critical.color(&theme))
+            } else {
+                parse_text_color(&self.live_config.text_color, &theme)
+            }
+        }
+        if self.live_config.critical_text_color && self.usage_band() == UsageBand::Critical {
+            return Some(UsageBand::Critical.color(&theme))
+        }
+        parse_text_color(&self.live_config.text_color, &theme)
+    }
+
+    /// Whether the text on the panel should currently blink, which is only while usage is critical.
+    fn blinking(&self) -> bool {
+        self.live_config.critical_blink
+            && !self.live_config.reduced_motion
+            && self.usage_band() == UsageBand::Critical
+    }
+
+    /// How alarming the displayed memory usage is, according to the thresholds of whatever is
+    /// monitored.
+    fn usage_band(&self) -> UsageBand {
+        UsageBand::classify(self.usage_ratio() * 100.0, self.live_config.thresholds(self.monitored_metric()))
+    }
+
+    /// Which thresholds apply to the usage that is displayed.
+    fn monitored_metric(&self) -> ThresholdMetric {
+        if self.live_config.process_target.is_some() {
+            ThresholdMetric::Process
+        } else if self.live_config.monitor_target == MonitorTarget::Swap {
+            ThresholdMetric::Swap
+        } else {
+            ThresholdMetric::Ram
+        }
+    }
+
+    /// How alarming the swap usage is, or `None` without swap.
+    fn swap_band(&self) -> Option<UsageBand> {
+        let total = self.memory.total_swap;
+        if total == 0 {
+            return None
+        }
+        let percent = self.memory.used_swap as f32 / total as f32 * 100.0;
+        Some(UsageBand::classify(percent, self.live_config.thresholds(ThresholdMetric::Swap)))
+    }
+
+    /// The amount of used memory that is displayed on the panel.
+    ///
+    /// This is frozen while the pointer hovers over the panel if the user enabled it.
+    fn displayed_used(&self) -> u64 {
+        if let Some((used, _)) = self.hover_frozen {
+            return used
+        }
+        self.live_used()
+    }
+
+    /// The amount of used memory as of the last refresh, smoothed if the user enabled it.
+    ///
+    /// This is the memory of the monitored process if there is one, or else the used swap if only
+    /// swap is monitored.
+    fn live_used(&self) -> u64 {
+        if self.live_config.process_target.is_some() {
+            return self.process_memory.unwrap_or(0)
+        }
+        if self.live_config.monitor_target == MonitorTarget::Swap {
+            return self.memory.used_swap
+        }
+        if !self.smoothing_enabled() {
+            return self.memory.used
+        }
+        match self.live_config.smoothing_mode {
+            SmoothingMode::Exponential => self.smoothed_used.map_or(self.memory.used, |smoothed| smoothed.round() as u64),
+            // Until the window has filled up, this averages whatever samples there are
+            SmoothingMode::MovingAverage if !self.recent_used.is_empty() => {
+                let sum = self.recent_used.iter().map(|&used| u128::from(used)).sum::<u128>();
+                (sum / self.recent_used.len() as u128) as u64
+            }
+            SmoothingMode::MovingAverage => self.memory.used,
+        }
+    }
+
+    /// The amount of memory that usage is compared against as of the last refresh.
+    ///
+    /// This is the total swap if only swap is monitored, unless a process is monitored. Otherwise
+    /// it is the user's budget, if they set one.
+    fn live_total(&self) -> u64 {
+        match self.live_config.monitor_target {
+            MonitorTarget::Swap if self.live_config.process_target.is_none() => self.memory.total_swap,
+            _ => self.ram_total(),
+        }
+    }
+
+    /// The amount of RAM that usage is compared against, which is the user's budget if they set
+    /// one.
+    fn ram_total(&self) -> u64 {
+        self.live_config.display_cap_bytes.unwrap_or_else(|| self.live_config.denominator.of(&self.memory, self.peak_total))
+    }
+
+    /// The total amount of memory that is displayed.
+    fn displayed_total(&self) -> u64 {
+        match self.hover_frozen {
+            Some((_, total)) => total,
+            None => self.live_total(),
+        }
+    }
+
+    /// Whether the system monitor was launched so recently that launching it again is ignored.
+    fn launching(&self) -> bool {
+        self.launched_at.is_some_and(|launched_at| launched_at.elapsed() < LAUNCH_GUARD_INTERVAL)
+    }
+
+    /// Whether the displayed numbers follow the latest refresh.
+    ///
+    /// They don't while they are frozen under the pointer, or while the watchdog finds that the
+    /// metrics stopped being refreshed.
+    fn is_live(&self) -> bool {
+        self.hover_frozen.is_none() && !self.stalled
+    }
+
+    /// Whether only swap is monitored on a system that has none.
+    fn no_swap(&self) -> bool {
+        self.live_config.process_target.is_none()
+            && self.live_config.monitor_target == MonitorTarget::Swap
+            && self.memory.total_swap == 0
+    }
+
+    /// Whether a process is monitored that isn't running.
+    fn process_missing(&self) -> bool {
+        self.live_config.process_target.is_some() && self.process_memory.is_none()
+    }
+
+    /// The fraction of memory that is in use, between 0 and 1.
+    fn usage_ratio(&self) -> f32 {
+        let total = self.displayed_total();
+        if total == 0 {
+            return 0.0
+        }
+        (self.displayed_used() as f32 / total as f32).clamp(0.0, 1.0)
+    }
+
+    /// The percentage of memory that is in use, which exceeds 100 when usage is over budget.
+    fn usage_percent(&self) -> f32 {
+        let total = self.displayed_total();
+        if total == 0 {
+            return 0.0
+        }
+        self.displayed_used() as f32 / total as f32 * 100.0
+    }
+
+    /// The fraction of memory in use as the icon shows it.
+    ///
+    /// With reduced motion, this is rounded to [`REDUCED_MOTION_STEPS`], so that the gauge and
+    /// the tint stay put unless usage changes considerably.
+    fn icon_ratio(&self) -> f32 {
+        self.motion_steps(self.usage_ratio())
+    }
+
+    /// The fraction of swap that is in use, as shown in the gauge, or `None` without swap.
+    fn swap_icon_ratio(&self) -> Option<f32> {
+        let total = self.memory.total_swap;
+        if total == 0 {
+            return None
+        }
+        Some(self.motion_steps((self.memory.used_swap as f32 / total as f32).clamp(0.0, 1.0)))
+    }
+
+    /// Round a fraction to the steps of reduced motion, if the user enabled it.
+    fn motion_steps(&self, ratio: f32) -> f32 {
+        if !self.live_config.reduced_motion {
+            return ratio
+        }
+        let steps = REDUCED_MOTION_STEPS as f32;
+        (ratio * steps).round() / steps
+    }
+
+    /// The used memory and the total with swap added to both, if the panel shows them combined.
+    fn combined_usage(&self) -> Option<(u64, u64)> {
+        let combined = self.live_config.combined_view
+            && self.live_config.monitor_target != MonitorTarget::Swap
+            && self.live_config.process_target.is_none();
+        // Saturate rather than wrap, although no real system comes anywhere close
+        combined.then(|| (
+            self.displayed_used().saturating_add(self.memory.used_swap),
+            self.displayed_total().saturating_add(self.memory.total_swap),
+        ))
+    }
+
+    /// The label and the text of the row in the popup that explains the combined panel usage.
+    fn combined_item(&self) -> Option<(&'static str, String)> {
+        let (used, total) = self.combined_usage()?;
+        Some(("RAM+Swap", format!("{} / {}", self.format(used), self.format(total))))
+    }
+
+    /// The pieces of text that are shown next to the icon on the panel, in order.
+    ///
+    /// Each piece is a separate widget, so that they line up one below the other on a vertical
+    /// panel. Nothing is shown next to the icon unless the display mode is [`DisplayMode::Full`],
+    /// [`DisplayMode::FreeAndPercent`] or [`DisplayMode::Ratio`].
+    fn panel_label(&self, layout: PanelLayout) -> Vec<(Segment, String)> {
+        let mut pieces = Vec::new();
+        if !matches!(self.live_config.display_mode, DisplayMode::Full | DisplayMode::FreeAndPercent | DisplayMode::Ratio) {
+            return pieces
+        }
+        // A single piece of text takes the place of the used memory, the total and swap
+        let composed = matches!(self.live_config.display_mode, DisplayMode::FreeAndPercent | DisplayMode::Ratio);
+        let (used, total) = self.combined_usage().unwrap_or_else(|| (self.displayed_used(), self.displayed_total()));
+        if !self.live_config.label_prefix.is_empty() {
+            pieces.push((Segment::Plain, self.live_config.label_prefix.clone()));
+        }
+        if self.source_error.is_some() {
+            pieces.push((Segment::Plain, String::from("unreachable")));
+        } else if !self.has_sample {
+            pieces.push((Segment::Plain, self.live_config.placeholder.clone()));
+        } else if self.no_swap() {
+            pieces.push((Segment::Plain, String::from("no swap")));
+        } else if self.process_missing() {
+            pieces.push((Segment::Plain, String::from("not running")));
+        } else if self.live_config.display_mode == DisplayMode::Ratio {
+            pieces.push((Segment::Number, terse_ratio(used, total, &self.panel_format(layout))));
+        } else if composed {
+            // Snapping rounds to a whole number, which would only leave zeros for the decimals, so
+            // the percentage is only snapped to steps that are coarser than that
+            let percent = Some(total)
+                .filter(|&total| total > 0)
+                .map(|total| used as f64 / total as f64 * 100.0)
+                .map(|percent| if self.live_config.percent_step > 1 {
+                    self.display_percent(percent)
+                } else {
+                    self.clamped_percent(percent)
+                });
+            let format = self.panel_format(layout);
+            pieces.extend(free_and_percent(&format_bytes(total.saturating_sub(used), &format), percent, &format));
+        } else {
+            let headroom = self.live_config.show_headroom;
+            let shown = if headroom { total.saturating_sub(used) } else { used };
+            let mut usage = self.format_panel_used(shown, total, layout);
+            if headroom {
+                usage.push_str(" free");
+            }
+            pieces.push((Segment::Used, usage));
+            let trend = match self.shown_trend() {
+                // Free memory goes the other way
+                Trend::Rising if headroom => Trend::Falling,
+                Trend::Falling if headroom => Trend::Rising,
+                trend => trend,
+            };
+            if let Some(arrow) = trend.arrow().filter(|_| self.live_config.show_trend) {
+                pieces.push((Segment::Plain, String::from(arrow)));
+            }
+            if !self.is_live() {
+                pieces.push((Segment::Plain, String::from(PAUSED_GLYPH)));
+            }
+        }
+        let has_usage = self.source_error.is_none() && self.has_sample;
+        if layout.show_total && has_usage && !composed && !self.no_swap() && !self.process_missing() {
+            pieces.push((Segment::Plain, String::from(" / ")));
+            pieces.push((Segment::Total, self.total_text(total, layout)));
+        }
+        let show_swap = self.live_config.monitor_target == MonitorTarget::Both
+            && self.live_config.process_target.is_none()
+            && !self.live_config.combined_view
+            && self.memory.total_swap > 0;
+        if show_swap && has_usage && !composed {
+            let format = self.swap_format(layout, used, total);
+            pieces.push((Segment::Plain, String::from(" | ")));
+            pieces.push((Segment::Number, format_bytes(self.memory.used_swap, &format)));
+            if layout.show_total {
+                pieces.push((Segment::Plain, String::from(" / ")));
+                pieces.push((Segment::Number, format_bytes(self.memory.total_swap, &format)));
+            }
+        }
+        if let Some((name, value)) = self.slot_label(&self.live_config.secondary_metric, layout).filter(|_| has_usage) {
+            pieces.push((Segment::Plain, self.live_config.metric_separator.clone()));
+            pieces.push((Segment::Plain, format!("{name} ")));
+            pieces.push((Segment::Number, value));
+        }
+        if !self.live_config.label_suffix.is_empty() {
+            pieces.push((Segment::Plain, self.live_config.label_suffix.clone()));
+        }
+        pieces
+    }
+
+    /// The name and the value of a metric slot on the panel, such as `Swap` and `10%`, or `None`
+    /// if it is disabled.
+    ///
+    /// Slots always show the latest refresh of their metric, without smoothing.
+    fn slot_label(&self, slot: &MetricSlot, layout: PanelLayout) -> Option<(&'static str, String)> {
+        let (name, used, total) = match slot.metric {
+            SlotMetric::Disabled => return None,
+            SlotMetric::Ram => ("RAM", self.memory.used, self.ram_total()),
+            SlotMetric::Swap => ("Swap", self.memory.used_swap, self.memory.total_swap),
+        };
+        let value = match slot.style {
+            _ if slot.metric == SlotMetric::Swap && total == 0 => String::from("none"),
+            SlotStyle::Amount => {
+                let mut format = self.panel_format(layout);
+                if let Some(prefix) = slot.prefix {
+                    format.prefix = prefix;
+                }
+                // The slot may measure something else than the total that the prefix is locked to
+                format.auto_reference = None;
+                format_bytes(used, &format)
+            }
+            SlotStyle::Percentage if total == 0 => String::from("0%"),
+            SlotStyle::Percentage => format!(
+                "{:.0}%",
+                self.display_percent(used as f64 / total as f64 * 100.0),
+            ),
+        };
+        Some((name, value))
+    }
+
+    /// How byte counts are formatted according to the user's preferences.
+    fn byte_format(&self) -> ByteFormat<'_> {
+        let mut format = ByteFormat::from_config(&self.live_config);
+        if self.live_config.lock_prefix_to_total {
+            format.auto_reference = Some(self.displayed_total());
+        }
+        format
+    }
+
+    /// Format a byte count according to the user's preferences.
+    fn format(&self, count: u64) -> String {
+        format_bytes(count, &self.byte_format())
+    }
+
+    /// Format a duration in milliseconds like [`format_duration_ms`], with the decimal separator
+    /// that the user prefers.
+    fn format_duration(&self, msec: u64) -> String {
+        with_decimal_separator(format_duration_ms(msec), &self.byte_format())
+    }
+
+    /// Format the total for the panel button, reusing the previous text while the total and the
+    /// layout stay the same if the user asked for that.
+    fn total_text(&self, total: u64, layout: PanelLayout) -> String {
+        if !self.live_config.static_total {
+            return self.format_panel(total, layout)
+        }
+        let mut cache = self.total_text.borrow_mut();
+        match &*cache {
+            Some((cached_total, cached_layout, text)) if *cached_total == total && *cached_layout == layout => text.clone(),
+            _ => {
+                let text = self.format_panel(total, layout);
+                *cache = Some((total, layout, text.clone()));
+                text
+            }
+        }
+    }
+
+    /// Format a byte count for the panel button, which may call for a more compact readout than
+    /// the user's preferences.
+    fn format_panel(&self, count: u64, layout: PanelLayout) -> String {
+        format_bytes(count, &self.panel_format(layout))
+    }
+
+    /// Format the used (or free) memory for the panel button, leaving out the unit if it is
+    /// followed by a total in the same unit and the user asked for that.
+    fn format_panel_used(&self, used: u64, total: u64, layout: PanelLayout) -> String {
+        if layout.show_total && self.live_config.unit_on_total_only {
+            let format = self.panel_format(layout);
+            let (used_number, used_unit) = format_bytes_parts(used, &format);
+            let (_, total_unit) = format_bytes_parts(total, &format);
+            // Leaving out the unit is only unambiguous when both values share it
+            if used_unit == total_unit {
+                return used_number
+            }
+        }
+        self.format_panel(used, layout)
+    }
+
+    /// How byte counts are formatted on the panel button with the given layout.
+    fn panel_format(&self, layout: PanelLayout) -> ByteFormat<'_> {
+        let mut format = self.byte_format();
+        if layout.compact {
+            format.precision = format.precision.min(COMPACT_PRECISION);
+            format.compact = true;
+        }
+        format
+    }
+
+    /// How swap is formatted on the panel next to RAM with the given usage and total.
+    ///
+    /// Normally `Prefix::Auto` picks a prefix for swap by itself, unless the prefix is locked to
+    /// the total. To match, swap takes the prefix that the RAM usage came out with, and neither
+    /// the prefix nor the precision is changed to keep small swap values from rounding away.
+    fn swap_format(&self, layout: PanelLayout, used: u64, total: u64) -> ByteFormat<'_> {
+        let mut format = self.panel_format(layout);
+        if self.live_config.swap_matches_ram {
+            let shown = if self.live_config.show_headroom { total.saturating_sub(used) } else { used };
+            format.auto_reference = Some(format.auto_reference.unwrap_or(shown));
+            format.auto_nice = false;
+            format.expand_small_values = false;
+        }
+        format
+    }
+
+    /// Format the difference between two byte counts with an explicit sign, such as `+320 MiB`.
+    fn format_change(&self, before: u64, after: u64) -> String {
+        if after >= before {
+            format!("+{}", self.format(after - before))
+        } else {
+            format!("-{}", self.format(before - after))
+        }
+    }
+
+    /// The usage in plain words, such as `About eight gigabytes used, roughly half full`, or `None`
+    /// while there is no usage to describe.
+    fn usage_words(&self) -> Option<String> {
+        if self.source_error.is_some() || !self.has_sample || self.no_swap() || self.process_missing() {
+            return None
+        }
+        let words = describe_usage(self.displayed_used(), self.displayed_total());
+        Some(match self.live_config.monitor_target {
+            MonitorTarget::Swap => format!("Swap: {words}"),
+            _ => words,
+        })
+    }
+
+    /// Whether the popup recently could not be opened for the lack of a panel window.
+    fn popup_unavailable(&self) -> bool {
+        self.popup_unavailable_at.is_some_and(|at| at.elapsed() < POPUP_UNAVAILABLE_TIMEOUT)
+    }
+
+    /// The text of the tooltip of the panel button, which is also its accessible name.
+    fn tooltip_text(&self) -> String {
+        if self.launching() {
+            String::from("Launching…")
+        } else if self.popup_unavailable() {
+            format!("{} (the popup could not be opened)", self.usage_summary())
+        } else if let Some(words) = self.usage_words().filter(|_| self.live_config.usage_in_words) {
+            words
+        } else {
+            self.usage_summary()
+        }
+    }
+
+    /// The full usage summary, such as `8 GiB / 16 GiB`.
+    ///
+    /// This is shown in the panel tooltip regardless of what the panel itself displays.
+    fn usage_summary(&self) -> String {
+        if let Some(error) = &self.source_error {
+            return format!("Unreachable: {error}")
+        }
+        if !self.has_sample {
+            return String::from("Waiting for the first sample")
+        }
+        if self.no_swap() {
+            return String::from("No swap")
+        }
+        if let Some(target) = self.live_config.process_target.as_ref().filter(|_| self.process_missing()) {
+            return format!("{target} is not running")
+        }
+        let usage = if self.live_config.show_headroom {
+            format!(
+                "{} free / {}",
+                self.format(self.displayed_total().saturating_sub(self.displayed_used())),
+                self.format(self.displayed_total()),
+            )
+        } else {
+            format!("{} / {}", self.format(self.displayed_used()), self.format(self.displayed_total()))
+        };
+        match self.live_config.monitor_target {
+            MonitorTarget::Swap => format!("Swap used: {usage}"),
+            MonitorTarget::Both => format!(
+                "{usage}, swap {} / {}",
+                self.format(self.memory.used_swap),
+                self.format(self.memory.total_swap),
+            ),
+            // Without its cgroup, the session source measures the whole machine instead
+            MonitorTarget::Ram if self.live_config.source == Source::Session && self.source.fell_back() => {
+                format!("System used: {usage}")
+            }
+            MonitorTarget::Ram if self.live_config.source == Source::Session => format!("Session used: {usage}"),
+            MonitorTarget::Ram => usage,
+        }
+    }
+
+    /// A graph of how fast memory was allocated and freed between every two samples in the
+    /// history, with allocations going up and frees going down.
+    ///
+    /// The graph is scaled to the fastest change, which the label shows. Samples that are too far
+    /// apart, as around a suspend, leave a break in the graph rather than a misleading bar.
+    fn rate_graph(&self) -> Element<'_, Message> {
+        // Samples are recorded at the pace of the panel, even while the popup updates faster
+        let max_gap = Some(self.live_config.graph_gap_intervals)
+            .filter(|&intervals| intervals > 0)
+            .map(|intervals| u64::from(intervals) * self.live_config.update_interval);
+        let mut rates = self.history.rates(max_gap);
+        // Nothing but breaks, as right after a suspend, is no more to draw than no rates at all
+        if rates.iter().all(Option::is_none) {
+            return Element::from(settings::item("Allocation Rate", text("insufficient data")))
+        }
+        if self.live_config.smooth_rate_graph {
+            rates = smooth_rates(&rates);
+        }
+        let scale = rates.iter().flatten().fold(0.0, |max: f64, rate| max.max(rate.abs()));
+        let values = rates.iter()
+            .map(|rate| rate.map(|rate| if scale > 0.0 { (rate / scale) as f32 } else { 0.0 }))
+            .collect::<Vec<_>>();
+        let theme = cosmic::theme::active();
+        let mut axis: Color = theme.cosmic().on_bg_color().into();
+        axis.a = 0.3;
+        let graph = gauge::rate_bars_svg(
+            &values,
+            UsageBand::Critical.color(&theme),
+            UsageBand::Normal.color(&theme),
+            axis,
+        );
+        Element::from(column![
+            settings::item("Allocation Rate", text(format!("up to {}/s", self.format(scale as u64)))),
+            // Like the gauge, the graph is a vector image that is rasterized at the scale of the
+            // output it is on, so it stays sharp on HiDPI and when the applet moves to another one
+            svg(svg::Handle::from_memory(graph.into_bytes()))
+                .width(Length::Fill)
+                .height(Length::Fixed(RATE_GRAPH_HEIGHT))
+                .content_fit(cosmic::iced::ContentFit::Fill),
+        ])
+    }
+
+    /// Refresh the metrics that are only displayed in the popup.
+    fn refresh_popup_metrics(&mut self) {
+        self.refresh_uptime();
+        self.refresh_numa_nodes();
+        self.refresh_ecc_errors();
+        self.read_modules();
+        self.refresh_processes();
+        self.refresh_meminfo_fields();
+    }
+
+    /// Refresh every memory statistic, for the list in the popup.
+    ///
+    /// Without `/proc/meminfo`, as on other systems than Linux, only the statistics that are part
+    /// of a sample are listed. The same goes for a remote source, since the file would describe
+    /// this machine instead.
+    fn refresh_meminfo_fields(&mut self) {
+        if !self.meminfo_expanded {
+            return
+        }
+        if matches!(self.live_config.source, Source::Remote { .. }) {
+            self.meminfo_fields = meminfo::fields_from_sample(&self.memory);
+            return
+        }
+        match meminfo::read_meminfo_fields() {
+            Ok(fields) => self.meminfo_fields = fields,
+            Err(error) => {
+                if !self.meminfo_error_logged {
+                    tracing::warn!("Failed to read {}, listing only the basic statistics: {}", meminfo::MEMINFO_PATH, error);
+                    self.meminfo_error_logged = true;
+                }
+                self.meminfo_fields = meminfo::fields_from_sample(&self.memory);
+            }
+        }
+    }
+
+    /// Refresh the uptime that is displayed in the popup.
+    fn refresh_uptime(&mut self) {
+        self.uptime = System::uptime();
+    }
+
+    /// Refresh the top and the pinned processes in the popup.
+    ///
+    /// Only the memory of each process is refreshed, which is much cheaper than a full refresh, and
+    /// only once for both lists.
+    fn refresh_processes(&mut self) {
+        if self.live_config.show_top_processes || !self.live_config.pinned_processes.is_empty() {
+            self.sys.refresh_processes_specifics(
+                ProcessesToUpdate::All,
+                true,
+                ProcessRefreshKind::nothing().with_memory(),
+            );
+        }
+        self.collect_top_processes();
+        self.collect_pinned_processes();
+    }
+
+    /// Collect the processes that use the most memory, as of the last refresh of the processes.
+    ///
+    /// Processes are ranked by memory and then by PID, so that processes using the same amount of
+    /// memory don't swap places on every tick.
+    fn collect_top_processes(&mut self) {
+        if !self.live_config.show_top_processes {
+            self.top_processes.clear();
+            return
+        }
+        let mut processes: Vec<ProcessUsage> = self.sys.processes()
+            .iter()
+            .map(|(pid, process)| ProcessUsage {
+                pid: *pid,
+                name: process.name().to_string_lossy().into_owned(),
+                memory: process.memory(),
+            })
+            .collect();
+        processes.sort_by(|a, b| b.memory.cmp(&a.memory).then(a.pid.cmp(&b.pid)));
+        processes.truncate(TOP_PROCESS_COUNT);
+        self.top_processes = processes;
+    }
+
+    /// Collect the memory of the processes that are always listed in the popup, as of the last
+    /// refresh of the processes.
+    ///
+    /// Like a process target, names are matched case-insensitively and every match is added
+    /// together, so that programs made of several processes are shown as a whole.
+    fn collect_pinned_processes(&mut self) {
+        if self.live_config.pinned_processes.is_empty() {
+            self.pinned_process_memory.clear();
+            return
+        }
+        self.pinned_process_memory = self.live_config.pinned_processes.iter()
+            .map(|name| {
+                self.sys.processes()
+                    .values()
+                    .filter(|process| process.thread_kind().is_none())
+                    .filter(|process| process.name().to_string_lossy().eq_ignore_ascii_case(name.trim()))
+                    .map(|process| process.memory())
+                    .reduce(|a, b| a.saturating_add(b))
+            })
+            .collect();
+    }
+
+    /// Refresh the per-node statistics that are displayed in the popup.
+    ///
+    /// Failures are only logged once, since they are bound to repeat on every tick.
+    fn refresh_numa_nodes(&mut self) {
+        if !self.live_config.show_numa {
+            return
+        }
+        match meminfo::read_numa_nodes() {
+            Ok(nodes) => self.numa_nodes = nodes,
+            Err(error) => {
+                if !self.numa_error_logged {
+                    tracing::warn!("Failed to read NUMA node statistics: {}", error);
+                    self.numa_error_logged = true;
+                }
+                self.numa_nodes.clear();
+            }
+        }
+    }
+
+    /// Refresh the memory error counts that are displayed in the popup.
+    ///
+    /// Failures are only logged once, since they are bound to repeat on every tick.
+    fn refresh_ecc_errors(&mut self) {
+        if !self.live_config.show_ecc {
+            return
+        }
+        match meminfo::read_ecc_errors() {
+            Ok(errors) => self.ecc_errors = errors,
+            Err(error) => {
+                if !self.ecc_error_logged {
+                    tracing::warn!("Failed to read memory error counts: {}", error);
+                    self.ecc_error_logged = true;
+                }
+                self.ecc_errors = None;
+            }
+        }
+    }
+
+    /// Send notifications, play the critical sound and run the critical action, for every threshold
+    /// that was crossed since the previous check.
+    fn check_alerts(&mut self) -> Task<Message> {
+        let critical = self.usage_band() == UsageBand::Critical;
+        if self.critical_alert.update(critical) && !self.live_config.critical_action_command.trim().is_empty() {
+            tracing::info!("Memory usage is critical, running '{}'", self.live_config.critical_action_command);
+            (self.spawn)(&self.live_config.critical_action_command)
+                .log("Failed to run critical memory action");
+        }
+        Task::batch([self.critical_sound(critical), self.swap_notification()])
+    }
+
+    /// Play the critical sound if usage just became critical, on a thread of its own so that the
+    /// popup doesn't stutter.
+    fn critical_sound(&mut self, critical: bool) -> Task<Message> {
+        // The alert keeps track of the edge even while the sound is off
+        if !self.sound_alert.update(critical) || !self.live_config.critical_sound || self.sound_unavailable {
+            return Task::none()
+        }
+        let file = self.live_config.critical_sound_file.trim().to_string();
+        cosmic::iced::Task::future(async move {
+            cosmic::Action::App(Message::SoundPlayed(alerts::play_sound(file).await.err()))
+        })
+    }
+
+    /// Send a notification if swap usage just rose above the configured threshold.
+    fn swap_notification(&mut self) -> Task<Message> {
+        let threshold = self.live_config.swap_notify_threshold;
+        if threshold == 0 || self.memory.total_swap == 0 {
+            return Task::none()
+        }
+        let percent = self.memory.used_swap as f64 / self.memory.total_swap as f64 * 100.0;
+        if !self.swap_alert.update(percent >= threshold as f64) {
+            return Task::none()
+        }
+        let body = format!(
+            "{} of {} swap is in use",
+            self.format(self.memory.used_swap),
+            self.format(self.memory.total_swap),
+        );
+        cosmic::iced::Task::future(async move {
+            alerts::notify(String::from("High swap usage"), body)
+                .await
+                .log_recoverable("Failed to send swap notification");
+        })
+        .discard()
+    }
+
+    /// Append the current usage to the history, saving it to disk if enough time has passed.
+    fn record_sample(&mut self) {
+        self.recorded_at = time::Instant::now();
+        self.history.push(self.memory.used);
+        self.publish_history();
+        self.turns.update(self.trend());
+        if self.live_config.persist_history && self.history_saved_at.elapsed() >= HISTORY_SAVE_INTERVAL {
+            self.save_history();
+        }
+        // The applet may be killed without dropping the window, so what this session ends with is
+        // saved along the way as well
+        if self.live_config.compare_last_session && self.last_session_saved_at.elapsed() >= HISTORY_SAVE_INTERVAL {
+            self.save_last_session();
+        }
+        if let Some(Err(error)) = self.usage_log.as_mut().map(|usage_log| usage_log.append(&self.memory)) {
+            // Stop trying rather than logging the same error on every tick
+            tracing::error!("Failed to write usage log, disabling it until its path changes: {}", error);
+            self.usage_log = None;
+        }
+    }
+
+    /// Restart the metrics source and the timer if the metrics were not refreshed for
+    /// `STALL_INTERVALS` update intervals.
+    fn check_stalled(&mut self) {
+        let limit = time::Duration::from_millis(self.live_config.update_interval) * STALL_INTERVALS;
+        if !self.stalled && self.refreshed_at.elapsed() > limit {
+            tracing::warn!(
+                "Metrics were not refreshed for {}, restarting the metrics source and timer",
+                format_duration_ms(self.refreshed_at.elapsed().as_millis() as u64),
+            );
+            self.stalled = true;
+            self.source = (self.create_source)(&self.live_config.source, self.live_config.update_interval);
+            // A new ID makes iced start a fresh time subscription
+            self.time_subscription_generation += 1;
+        }
+    }
+
+    /// Changes whether the usage history is kept across restarts.
+    ///
+    /// Turning it on picks up the history that an earlier run saved, and saves the combined history
+    /// right away. This method does not save configuration.
+    fn ui_set_persist_history(&mut self, enable: bool) {
+        let enabled = enable && !self.live_config.persist_history;
+        self.live_config.persist_history = enable;
+        if enabled {
+            if let Some(path) = history::history_path() {
+                self.history.prepend(History::load(&path, MAX_HISTORY_CAPACITY));
+                self.publish_history();
+            }
+            self.save_history();
+        }
+    }
+
+    /// Write the used memory to disk right away, as the one that this session ended with so far.
+    fn save_last_session(&mut self) {
+        self.last_session_saved_at = time::Instant::now();
+        if let Some(path) = history::last_session_path().filter(|_| self.has_sample) {
+            history::save_last_session(&path, self.memory.used).log_recoverable("Failed to save the usage of this session");
+        }
+    }
+
+    /// The comparison of the current usage with how the previous session ended, as shown in the
+    /// popup, or `None` once it is [`LAST_SESSION_DISPLAY_TIME`] after the start.
+    fn last_session_text(&self) -> Option<String> {
+        if !self.live_config.compare_last_session || !self.has_sample || self.started_at.elapsed() >= LAST_SESSION_DISPLAY_TIME {
+            return None
+        }
+        Some(match self.last_session_used {
+            Some(used) => format!("{} vs last session", self.format_change(used, self.memory.used)),
+            None => String::from("no previous session"),
+        })
+    }
+
+    /// Hand the usage history to the D-Bus service, after it changed in any way.
+    fn publish_history(&self) {
+        self.history_tx.send_replace(self.history.samples(dbus::MAX_HISTORY_SAMPLES));
+    }
+
+    /// Write the usage history to disk right away.
+    fn save_history(&mut self) {
+        self.history_saved_at = time::Instant::now();
+        if let Some(path) = history::history_path() {
+            self.history.save(&path).log_recoverable("Failed to save usage history");
+        }
+    }
+
+    /// Create and "open" the popup.
+    ///
+    /// Without a panel window to attach the popup to, this tries again after [`POPUP_RETRY_DELAY`]
+    /// if `retry` is set and the user didn't turn retrying off.
+    fn open_popup(&mut self, retry: bool) -> Task<Message> {
+        let parent_win_id = match self.core.main_window_id() {
+            Some(id) => id,
+            None if retry && self.live_config.retry_popup => {
+                tracing::info!("Not opening the popup yet, because the applet has no panel window to attach it to");
+                self.popup_retry_pending = true;
+                return cosmic::iced::Task::future(async {
+                    time::sleep(POPUP_RETRY_DELAY).await;
+                    cosmic::Action::App(Message::RetryPopup)
+                })
+            }
+            // Early return if the widget window somehow wasn't present
+            None => {
+                tracing::warn!("Not opening the popup, because the applet has no panel window to attach it to");
+                self.popup_unavailable_at = Some(time::Instant::now());
+                return Task::none()
+            }
+        };
+        self.popup_unavailable_at = None;
+        let new_id = window::Id::unique();
+        self.popup.replace(new_id);
+
+        let mut popup_settings = self.core.applet.get_popup_settings(
+            parent_win_id,
+            new_id,
+            None,
+            None,
+            None
+        );
+
+        let (min_width, max_width) = if self.live_config.fit_popup_width {
+            let width = fit_popup_width(&self.wide_popup_rows());
+            (width, width)
+        } else {
+            (POPUP_MIN_WIDTH, POPUP_MAX_WIDTH)
+        };
+        popup_settings.positioner.size_limits = Limits::NONE
+            .max_width(max_width)
+            .min_width(min_width)
+            .min_height(200.0)
+            .max_height(1080.0);
+
+        // Nudge the popup away from where the panel would put it; the compositor still slides it
+        // back onto the screen if this pushes it off
+        popup_settings.positioner.offset = (self.live_config.popup_offset_x, self.live_config.popup_offset_y);
+
+        // Without a grab, the compositor doesn't dismiss the popup when the user clicks elsewhere
+        // A pinned popup is still destroyed together with the panel, which reaches us through
+        // `on_close_requested` like any other close
+        popup_settings.grab = !self.pinned;
+
+        get_popup(popup_settings)
+    }
+
+    /// Refresh the metrics that are rendered to the screen.
+    fn refresh_metrics(&mut self) {
+        let previous_total = self.memory.total;
+        let memory = self.source.sample();
+        // A source that is still busy has no new metrics, so this doesn't count as a refresh
+        if self.source.busy() {
+            return
+        }
+        self.memory = memory;
+        if self.live_config.used_definition == UsedDefinition::TotalMinusAvailable {
+            self.memory.used = self.memory.total.saturating_sub(self.memory.available);
+        }
+        // A total of zero means that there is no reading yet, rather than that memory is gone
+        if previous_total != 0 && self.memory.total != 0 && self.memory.total != previous_total {
+            self.total_changed(previous_total);
+        }
+        self.source_error = self.source.error();
+        self.peak_total = self.peak_total.max(self.memory.total);
+        // Sources hand out an empty sample until they have a real one
+        if self.memory.total > 0 {
+            self.has_sample = true;
+        }
+        self.refreshed_at = time::Instant::now();
+        if self.memory.total > 0 && self.source_error.is_none() {
+            self.updated_at = Some(self.refreshed_at);
+        }
+        self.stalled = false;
+        // Exponential moving average, which starts out at the first sample
+        let factor = self.live_config.smoothing_factor.min(MAX_SMOOTHING_FACTOR) as f64 / 100.0;
+        self.smoothed_used = Some(match self.smoothed_used {
+            Some(previous) => previous * factor + self.memory.used as f64 * (1.0 - factor),
+            None => self.memory.used as f64,
+        });
+        // Both averages are kept up to date, so that switching between them is seamless
+        let window = self.live_config.moving_average_window.max(1) as usize;
+        while self.recent_used.len() >= window {
+            self.recent_used.pop_front();
+        }
+        self.recent_used.push_back(self.memory.used);
+        self.refresh_process_memory();
+    }
+
+    /// Check that the statistics that were just read are plausible, and mark the applet as degraded
+    /// if they aren't.
+    ///
+    /// This catches a broken or unsupported backend right away, rather than leaving the user to make
+    /// sense of what it displays. A remote source is skipped, since it has nothing to show until
+    /// its command first finishes.
+    fn check_health(&mut self) {
+        if matches!(self.live_config.source, Source::Remote { .. }) {
+            return
+        }
+        self.degraded = health_problem(&self.memory);
+        if let Some(problem) = &self.degraded {
+            tracing::warn!("Memory statistics look wrong, the displayed usage is probably incorrect: {}", problem);
+        }
+    }
+
+    /// Adapt to the total amount of memory having changed since the previous refresh, as happens
+    /// when memory is hot-plugged or ballooned in a virtual machine.
+    ///
+    /// When the total shrinks, earlier usage that no longer fits is lowered to the new total, so
+    /// that the smoothed usage and the history never come out above 100%.
+    fn total_changed(&mut self, previous_total: u64) {
+        let total = self.memory.total;
+        tracing::info!("Total memory changed from {} to {} bytes", previous_total, total);
+        if total > previous_total {
+            return
+        }
+        self.smoothed_used = self.smoothed_used.map(|smoothed| smoothed.min(total as f64));
+        for used in &mut self.recent_used {
+            *used = (*used).min(total);
+        }
+        self.history.clamp(total);
+        self.publish_history();
+    }
+
+    /// Refresh the memory of the monitored process, if there is one.
+    ///
+    /// A target that is a number is taken as a PID, and only that process is refreshed. Otherwise
+    /// it is matched case-insensitively against the name of every process, and the memory of all
+    /// matches is summed, so that programs made of several processes are shown as a whole.
+    ///
+    /// Scanning every process is costly, so only the matches are refreshed until one of them is
+    /// gone. Processes that start matching in the meantime are picked up by the next scan.
+    fn refresh_process_memory(&mut self) {
+        let Some(target) = &self.live_config.process_target else {
+            self.process_memory = None;
+            return
+        };
+        let target = target.trim();
+        let refresh_kind = ProcessRefreshKind::nothing().with_memory();
+        self.process_memory = match target.parse::<u32>() {
+            Ok(pid) => {
+                let pid = Pid::from_u32(pid);
+                self.sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, refresh_kind);
+                self.sys.process(pid).map(|process| process.memory())
+            }
+            Err(_) => {
+                let matches = |process: &sysinfo::Process| {
+                    process.thread_kind().is_none() && process.name().to_string_lossy().eq_ignore_ascii_case(target)
+                };
+                // A PID that was reused by another process counts as gone as well
+                let all_alive = !self.process_pids.is_empty() && {
+                    self.sys.refresh_processes_specifics(ProcessesToUpdate::Some(&self.process_pids), true, refresh_kind);
+                    self.process_pids.iter().all(|&pid| self.sys.process(pid).is_some_and(matches))
+                };
+                if !all_alive {
+                    self.sys.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh_kind);
+                    self.process_pids = self.sys.processes()
+                        .iter()
+                        .filter(|&(_, process)| matches(process))
+                        .map(|(&pid, _)| pid)
+                        .collect();
+                }
+                self.process_pids.iter()
+                    .filter_map(|&pid| self.sys.process(pid))
+                    .map(|process| process.memory())
+                    .reduce(|a, b| a.saturating_add(b))
+            }
+        };
+    }
+
+}
+
+impl Drop for Window {
+
+    fn drop(&mut self) {
+        // Dropping the window also drops the interval sender, which ends the time subscription;
+        // flush what it would otherwise have saved on a later tick
+        if self.live_config.persist_history {
+            self.save_history();
+        }
+        if self.live_config.compare_last_session {
+            self.save_last_session();
+        }
+    }
+
+}
+
+impl cosmic::Application for Window {
+    /*
+    *  Executors are a mandatory thing for both COSMIC Applications and Applets.
+    *  They're basically what allows for multi-threaded async operations for things that
+    *  may take too long and block the thread the GUI is running on. This is also where
+    *  Tasks take place.
+    */
+    type Executor = cosmic::SingleThreadExecutor;
+    type Flags = (); // Argument passed to init()
+    type Message = Message; // These are setting the application messages to our Message enum
+    const APP_ID: &'static str = ID; // This is where we set our const above to the actual ID
+
+    // Setup the immutable core functionality.
+    fn core(&self) -> &Core {
+        &self.core
+    }
+
+    // Set up the mutable core functionality.
+    fn core_mut(&mut self) -> &mut Core {
+        &mut self.core
+    }
+
+    // Initialize the applet
+    /*
+    *  The parameters are the Core and flags (again not sure what to do with these).
+    *  The function returns our model struct initialized and an Option<Task>, in this case
+    *  there is no command so it returns a None value with the type of Task in its place.
+    */
+    fn init(core: Core, _flags: Self::Flags) -> (Self, Task<Self::Message>) {
+
+        let config = Config::new(ID, VERSION).expect("failed to load config for RAM usage applet");
+
+        let mut loaded_config = CosmicAppletRamConfig::get_entry(&config).unwrap_or_default();
+
+        // Whoever saved settings before the introduction existed is well past their first run
+        if loaded_config.first_run && config_dir().as_deref().is_some_and(predates_first_run) {
+            loaded_config
+                .set_first_run(&config, false)
+                .log_recoverable("Failed to save applet configuration");
+        }
+
+        // A hand-edited config can contain values that the popup can't represent; fix them on
+        // disk as well so that other readers of the config agree with what is displayed
+        let live_config = loaded_config.clone().validated();
+        if live_config != loaded_config {
+            tracing::warn!("Correcting invalid values in the applet configuration");
+            live_config.write_entry(&config).log_recoverable("Failed to save corrected applet configuration");
+        }
+
+        let history_capacity = live_config.history_window.capacity(live_config.update_interval);
+        let mut history = match history::history_path() {
+            Some(path) if live_config.persist_history => History::load(&path, history_capacity),
+            _ => History::with_capacity(history_capacity),
+        };
+        history.set_max_age(live_config.history_window.max_age());
+
+        let source = create_source(&live_config.source, live_config.update_interval);
+        let mut window = Window::new(core, config, live_config, source, history);
+        window.newer_config_version = newer_config_version();
+        window.last_session_used = history::last_session_path().and_then(|path| history::load_last_session(&path));
+        window.unknown_config_fields = window.config_dir.as_deref().map(unknown_config_fields).unwrap_or_default();
+        window.resolve_icon();
+        window.check_health();
+
+        (window, Task::none())
+    }
+
+    // Create what happens when the applet is closed
+    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
+        // Pass the PopupClosed message to the update function
+        Some(Message::PopupClosed(id))
+    }
+
+    fn subscription(&self) -> cosmic::iced::Subscription<Self::Message> {
+        fn time_subscription(settings_watch: watch::Receiver<TickSettings>, generation: u64, align: bool) -> Subscription<Message> {
+            Subscription::run_with_id(
+                // Including the alignment restarts the subscription when it is toggled
+                ("time-sub", generation, align),
+                tick_stream(settings_watch, align),
+            )
+        }
+        let tick_settings_rx = self.tick_settings_tx.subscribe();
+        Subscription::batch(vec![
+            self.core
+                .watch_config(Self::APP_ID)
+                .map(|u| {
+                    for err in u.errors {
+                        tracing::error!(?err, "Error watching config");
+                    }
+                    Message::ConfigChanged(u.config)
+                }),
+            time_subscription(tick_settings_rx, self.time_subscription_generation, self.live_config.align_to_clock),
+            // Runs separately from the time subscription, so that it notices when that one wedges
+            cosmic::iced::time::every(WATCHDOG_INTERVAL).map(|_| Message::Watchdog),
+            // Nothing needs to be woken up for blinking unless usage is critical
+            if self.blinking() {
+                cosmic::iced::time::every(BLINK_INTERVAL).map(|_| Message::Blink)
+            } else {
+                Subscription::none()
+            },
+            dbus::subscription(self.history_tx.subscribe()),
+            signal::subscription(),
+            if self.live_config.pressure_events {
+                pressure::subscription()
+            } else {
+                Subscription::none()
+            },
+            // Only listen for keys when there is a shortcut to look out for or a setting to step
+            if self.shortcut.is_some() || self.popup.is_some() {
+                keyboard::on_key_press(|key, modifiers| Some(Message::KeyPressed(key, modifiers)))
+            } else {
+                Subscription::none()
+            },
+        ])
     }
 
     // Here is the update function, it's the one that handles all of the messages that
     // are passed within the applet.
     fn update(&mut self, message: Self::Message) -> Task<Self::Message> {
+        // Anything but a tick may change how the total is formatted, while a tick that changes
+        // the total itself is caught by the cache
+        if !matches!(message, Message::Tick) {
+            self.total_text.take();
+        }
+
         // match on what message was sent
         match message {
             // Handle the TogglePopup message
             Message::TogglePopup => {
+                // Another click would only schedule another attempt
+                if self.popup_retry_pending {
+                    tracing::debug!("Ignoring request to toggle the popup while it is about to be opened");
+                    return Task::none()
+                }
                 // Close the popup
                 return if let Some(popup_id) = self.popup.take() {
+                    self.used_at_open = None;
+                    self.apply_interval();
                     destroy_popup(popup_id)
                 } else {
-                    // Create and "open" the popup
-                    let parent_win_id = match self.core.main_window_id() {
-                        Some(id) => id,
-                        // Early return if the widget window somehow wasn't present
-                        None => return Task::none(),
+                    // Make sure the popup doesn't open on numbers that are up to one interval old
+                    if self.live_config.refresh_on_open && self.refreshed_at.elapsed() >= MIN_REFRESH_INTERVAL {
+                        self.refresh_metrics();
+                        // Restart the timer so that the next tick is a full interval away
+                        self.set_ticks(self.live_config.update_interval);
+                    }
+
+                    // Some metrics are only refreshed while the popup is visible
+                    self.refresh_popup_metrics();
+                    self.used_at_open = Some(self.memory.used);
+
+                    let task = self.open_popup(true);
+                    // The popup may update at an interval of its own
+                    self.apply_interval();
+                    task
+                }
+            }
+            Message::RetryPopup => {
+                self.popup_retry_pending = false;
+                if self.popup.is_none() {
+                    let task = self.open_popup(false);
+                    self.apply_interval();
+                    return task
+                }
+            }
+            // Unset the popup field after it has been closed
+            Message::PopupClosed(popup_id) => {
+                if self.popup.as_ref() == Some(&popup_id) {
+                    self.popup = None;
+                    self.used_at_open = None;
+                    self.stepping = None;
+                    self.apply_interval();
+                }
+            }
+            Message::LaunchSystemMonitor => {
+                // A slow monitor gives no feedback for a while, which invites clicking again
+                if self.launching() {
+                    tracing::debug!("Ignoring repeated request to launch the system monitor");
+                    return Task::none()
+                }
+                self.launched_at = Some(time::Instant::now());
+                (self.spawn)(&self.live_config.system_monitor_command)
+                    .log("Failed to launch system monitor");
+            }
+            Message::CopyUsage => {
+                return cosmic::iced::clipboard::write(self.usage_summary());
+            }
+            Message::Hover(hovered) => {
+                // Metrics are still refreshed in the background; only what's displayed is frozen
+                self.hover_frozen = if hovered && self.live_config.freeze_on_hover {
+                    Some((self.live_used(), self.live_total()))
+                } else {
+                    None
+                };
+            }
+            Message::SetBaseline => {
+                self.baseline = Some(self.memory.used);
+            }
+            Message::ClearBaseline => {
+                self.baseline = None;
+            }
+            Message::MarkA => {
+                // A new start makes for a new measurement
+                self.marker_a = Some(Marker::now(self.memory.used));
+                self.marker_b = None;
+            }
+            Message::MarkB => {
+                self.marker_b = Some(Marker::now(self.memory.used));
+            }
+            Message::ClearMarkers => {
+                self.marker_a = None;
+                self.marker_b = None;
+            }
+            Message::RestartSource => {
+                self.restart_source();
+            }
+            Message::DismissFirstRun => {
+                self.live_config
+                    .set_first_run(&self.config, false)
+                    .log_recoverable("Failed to save applet configuration");
+            }
+            Message::ToggleMeminfo => {
+                self.meminfo_expanded = !self.meminfo_expanded;
+                if self.meminfo_expanded {
+                    self.refresh_meminfo_fields();
+                } else {
+                    self.meminfo_fields.clear();
+                }
+            }
+            Message::UpdateSettingsFile(text) => {
+                self.settings_file_text = text;
+            }
+            Message::ExportSettings => {
+                let path = PathBuf::from(self.settings_file_text.trim());
+                self.settings_file_status = Some(match export_settings(&self.live_config, &path) {
+                    Ok(()) => format!("Exported to {}", path.display()),
+                    Err(error) => {
+                        tracing::error!("Failed to export settings to {}: {}", path.display(), error);
+                        format!("Export failed: {error}")
+                    }
+                });
+            }
+            Message::ImportSettings => {
+                let path = PathBuf::from(self.settings_file_text.trim());
+                match import_settings(&path).and_then(|config| keep_redacted_fields(config, &self.live_config)) {
+                    Ok(mut config) => {
+                        // Settings from elsewhere don't bring the introduction back
+                        config.first_run = self.live_config.first_run;
+                        config.write_entry(&self.config).log("Failed to save imported applet configuration");
+                        self.settings_file_status = Some(format!(
+                            "Imported from {}, keeping the current commands and paths",
+                            path.display(),
+                        ));
+                        // Saving echoes the config back later, but that is skipped as unchanged
+                        return self.update(Message::ConfigChanged(config))
+                    }
+                    Err(error) => {
+                        tracing::error!("Failed to import settings from {}: {}", path.display(), error);
+                        self.settings_file_status = Some(format!("Import failed: {error}"));
+                    }
+                }
+            }
+            Message::CopySettings => {
+                match settings_snippet(&self.live_config, self.snippet_includes_commands) {
+                    Ok(snippet) => {
+                        self.settings_file_status = Some(String::from("Copied settings to the clipboard"));
+                        return cosmic::iced::clipboard::write(snippet)
+                    }
+                    Err(error) => {
+                        tracing::error!("Failed to copy settings: {}", error);
+                        self.settings_file_status = Some(format!("Copy failed: {error}"));
+                    }
+                }
+            }
+            Message::UpdateSnippetIncludesCommands(include) => {
+                self.snippet_includes_commands = include;
+            }
+            Message::OpenConfigDir => {
+                if let Some(dir) = &self.config_dir {
+                    open_path(dir).log("Failed to open the configuration directory");
+                }
+            }
+            Message::UpdatePinned(pinned) => {
+                self.pinned = pinned;
+                // Whether the popup closes on outside clicks is decided when it is created
+                if let Some(popup_id) = self.popup.take() {
+                    return Task::batch([destroy_popup(popup_id), self.open_popup(false)])
+                }
+            }
+            Message::Watchdog => {
+                self.check_stalled();
+            }
+            Message::Tick => {
+                self.refresh_metrics();
+                if self.sample_due() {
+                    self.record_sample();
+                }
+                if self.popup.is_some() {
+                    self.refresh_popup_metrics();
+                }
+                return self.check_alerts();
+            }
+            Message::UpdatePrecision(prec) => {
+                self.stepping = Some(SteppedSetting::Precision);
+                self.live_config
+                    .set_precision(&self.config, clamp_precision(prec))
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_precision(prec);
+                self.refresh_metrics();
+            }
+            Message::UpdateDecimalComma(enable) => {
+                self.live_config
+                    .set_decimal_comma(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_decimal_comma(enable);
+            }
+            Message::UpdateExpandSmallValues(enable) => {
+                self.live_config
+                    .set_expand_small_values(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_expand_small_values(enable);
+            }
+            Message::UpdateRounding(rounding) => {
+                self.live_config
+                    .set_rounding(&self.config, rounding)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_rounding(rounding);
+            }
+            Message::UpdateStandard(standard) => {
+                self.live_config
+                    .set_standard(&self.config, standard)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_standard(standard);
+                self.refresh_metrics();
+            }
+            Message::UpdatePrefixBase(base) => {
+                self.live_config
+                    .set_prefix_base(&self.config, base)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_prefix_base(base);
+            }
+            Message::UpdatePrefixStyle(style) => {
+                self.live_config
+                    .set_prefix_style(&self.config, style)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_prefix_style(style);
+            }
+            Message::UpdateLikeFree(enable) => {
+                self.live_config
+                    .set_like_free(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_like_free(enable);
+            }
+            Message::UpdateUnit(unit) => {
+                self.live_config
+                    .set_unit(&self.config, unit)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_unit(unit);
+                self.refresh_metrics();
+            }
+            Message::UpdateShowTotal(enable) => {
+                self.live_config
+                    .set_show_total(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_total(enable);
+                self.refresh_metrics();
+            }
+            Message::UpdateStaticTotal(enable) => {
+                self.live_config
+                    .set_static_total(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_static_total(enable);
+            }
+            Message::UpdateShowTotalVertical(enable) => {
+                self.live_config
+                    .set_show_total_vertical(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_total_vertical(enable);
+            }
+            Message::UpdateShowIconVertical(enable) => {
+                self.live_config
+                    .set_show_icon_vertical(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_icon_vertical(enable);
+            }
+            Message::UpdateDenominator(denominator) => {
+                self.live_config
+                    .set_denominator(&self.config, denominator)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_denominator(denominator);
+            }
+            Message::UpdateUsedDefinition(definition) => {
+                self.live_config
+                    .set_used_definition(&self.config, definition)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_used_definition(definition);
+            }
+            Message::UpdateMonitorTarget(target) => {
+                self.live_config
+                    .set_monitor_target(&self.config, target)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_monitor_target(target);
+            }
+            Message::UpdateProcessTargetText(text) => {
+                self.process_target_text = text;
+            }
+            Message::UpdateProcessTarget(target) => {
+                let target = Some(target).filter(|target| !target.trim().is_empty());
+                self.live_config
+                    .set_process_target(&self.config, target.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_process_target(target);
+            }
+            Message::UpdateCombinedView(enable) => {
+                self.live_config
+                    .set_combined_view(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_combined_view(enable);
+            }
+            Message::UpdateShowHeadroom(enable) => {
+                self.live_config
+                    .set_show_headroom(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_headroom(enable);
+            }
+            Message::UpdateClickableSegments(enable) => {
+                self.live_config
+                    .set_clickable_segments(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_clickable_segments(enable);
+            }
+            Message::UpdateMonospaceDigits(enable) => {
+                self.live_config
+                    .set_monospace_digits(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_monospace_digits(enable);
+            }
+            Message::SegmentClicked(segment) => {
+                // Each segment toggles a setting, which is saved as if it was changed in the popup
+                match segment {
+                    Segment::Plain | Segment::Number => {},
+                    Segment::Used => {
+                        return self.update(Message::UpdateShowHeadroom(!self.live_config.show_headroom))
+                    }
+                    Segment::Total => {
+                        return self.update(Message::UpdateDenominator(match self.live_config.denominator {
+                            Denominator::Total => Denominator::UsedAndAvailable,
+                            Denominator::UsedAndAvailable => Denominator::PeakTotal,
+                            Denominator::PeakTotal => Denominator::Total,
+                        }))
+                    }
+                }
+            }
+            Message::UpdateShowTrend(enable) => {
+                self.live_config
+                    .set_show_trend(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_trend(enable);
+            }
+            Message::UpdateTrendEpsilon(mib) => {
+                self.live_config
+                    .set_trend_epsilon(&self.config, mib)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_trend_epsilon(mib);
+            }
+            Message::UpdateTrendMode(mode) => {
+                self.live_config
+                    .set_trend_mode(&self.config, mode)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_trend_mode(mode);
+            }
+            Message::UpdateShowUptime(enable) => {
+                self.live_config
+                    .set_show_uptime(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_uptime(enable);
+            }
+            Message::UpdateUsageInWords(enable) => {
+                self.live_config
+                    .set_usage_in_words(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_usage_in_words(enable);
+            }
+            Message::UpdateUsageInWordsPopup(enable) => {
+                self.live_config
+                    .set_usage_in_words_popup(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_usage_in_words_popup(enable);
+            }
+            Message::UpdateShowHistoryStats(enable) => {
+                self.live_config
+                    .set_show_history_stats(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_history_stats(enable);
+            }
+            Message::UpdateShowRateGraph(enable) => {
+                self.live_config
+                    .set_show_rate_graph(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_rate_graph(enable);
+            }
+            Message::UpdateSmoothRateGraph(enable) => {
+                self.live_config
+                    .set_smooth_rate_graph(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_smooth_rate_graph(enable);
+            }
+            Message::UpdateGraphGapIntervals(intervals) => {
+                // The spin button passes a single interval on its way between never and the
+                // minimum, which is skipped in the direction it is going
+                let intervals = match intervals {
+                    1 if self.live_config.graph_gap_intervals == 0 => MIN_GRAPH_GAP_INTERVALS,
+                    1 => 0,
+                    intervals => clamp_graph_gap_intervals(intervals),
+                };
+                self.live_config
+                    .set_graph_gap_intervals(&self.config, intervals)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_graph_gap_intervals(intervals);
+            }
+            Message::UpdateShowBothStandards(enable) => {
+                self.live_config
+                    .set_show_both_standards(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_both_standards(enable);
+            }
+            Message::UpdateShowOomEstimate(enable) => {
+                self.live_config
+                    .set_show_oom_estimate(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_oom_estimate(enable);
+            }
+            Message::UpdateShowForecast(enable) => {
+                self.live_config
+                    .set_show_forecast(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_forecast(enable);
+            }
+            Message::UpdateForecastHorizon(seconds) => {
+                self.live_config
+                    .set_forecast_horizon(&self.config, seconds)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_forecast_horizon(seconds);
+            }
+            Message::UpdateShowLastUpdate(enable) => {
+                self.live_config
+                    .set_show_last_update(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_last_update(enable);
+            }
+            Message::UpdateCompareLastSession(enable) => {
+                self.live_config
+                    .set_compare_last_session(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_compare_last_session(enable);
+            }
+            Message::UpdateShowNuma(enable) => {
+                self.live_config
+                    .set_show_numa(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_numa(enable);
+            }
+            Message::UpdateShowEcc(enable) => {
+                self.live_config
+                    .set_show_ecc(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_ecc(enable);
+            }
+            Message::UpdateShowModules(enable) => {
+                self.live_config
+                    .set_show_modules(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_modules(enable);
+            }
+            Message::UpdateUsageBar(bar) => {
+                self.live_config
+                    .set_usage_bar(&self.config, bar)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_usage_bar(bar);
+            }
+            Message::UpdateShowTopProcesses(enable) => {
+                self.live_config
+                    .set_show_top_processes(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_show_top_processes(enable);
+            }
+            Message::UpdatePinnedProcessText(text) => {
+                self.pinned_process_text = text;
+            }
+            Message::PinProcess => {
+                let name = self.pinned_process_text.trim();
+                let pinned = &self.live_config.pinned_processes;
+                if !name.is_empty() && !pinned.iter().any(|pinned| pinned.eq_ignore_ascii_case(name)) {
+                    let mut names = pinned.clone();
+                    names.push(String::from(name));
+                    self.live_config
+                        .set_pinned_processes(&self.config, names.clone())
+                        .log_recoverable("Failed to save applet configuration");
+                    self.ui_set_pinned_processes(names);
+                }
+                self.pinned_process_text.clear();
+            }
+            Message::UnpinProcess(index) => {
+                if index < self.live_config.pinned_processes.len() {
+                    let mut names = self.live_config.pinned_processes.clone();
+                    names.remove(index);
+                    self.live_config
+                        .set_pinned_processes(&self.config, names.clone())
+                        .log_recoverable("Failed to save applet configuration");
+                    self.ui_set_pinned_processes(names);
+                }
+            }
+            Message::UpdateSwapNotifyThreshold(percent) => {
+                self.live_config
+                    .set_swap_notify_threshold(&self.config, percent)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_swap_notify_threshold(percent);
+            }
+            Message::UpdateRefreshOnOpen(enable) => {
+                self.live_config
+                    .set_refresh_on_open(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_refresh_on_open(enable);
+            }
+            Message::UpdateFreezeOnHover(enable) => {
+                self.live_config
+                    .set_freeze_on_hover(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_freeze_on_hover(enable);
+            }
+            Message::UpdatePrimaryAction(action) => {
+                self.live_config
+                    .set_primary_action(&self.config, action)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_primary_action(action);
+            }
+            Message::UpdateUsageLogPathText(text) => {
+                self.usage_log_path_text = text;
+            }
+            Message::UpdateUsageLogPath(path) => {
+                self.live_config
+                    .set_usage_log_path(&self.config, path.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_usage_log_path(path);
+            }
+            Message::UpdatePopupShortcutText(text) => {
+                self.popup_shortcut_text = text;
+            }
+            Message::UpdatePopupShortcut(combo) => {
+                self.live_config
+                    .set_popup_shortcut(&self.config, combo.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_popup_shortcut(combo);
+            }
+            Message::KeyPressed(key, modifiers) => {
+                if self.shortcut.as_ref().is_some_and(|shortcut| shortcut.matches(&key, modifiers)) {
+                    return self.update(Message::TogglePopup)
+                }
+                // A focused text input keeps the keys to itself, so these never get in the way of
+                // editing text
+                if self.popup.is_none() || !modifiers.is_empty() {
+                    return Task::none()
+                }
+                match self.stepping {
+                    Some(SteppedSetting::Precision) => {
+                        let precision = u64::from(self.live_config.precision);
+                        let stepped = step_value(
+                            precision,
+                            &key,
+                            u64::from(PRECISION_STEP),
+                            u64::from(PRECISION_PAGE_STEP),
+                            u64::from(MIN_PRECISION),
+                            u64::from(MAX_PRECISION),
+                        );
+                        if let Some(stepped) = stepped.filter(|&stepped| stepped != precision) {
+                            return self.update(Message::UpdatePrecision(stepped as u32))
+                        }
+                    }
+                    Some(SteppedSetting::UpdateInterval) => {
+                        let msec = self.live_config.update_interval;
+                        let stepped = step_value(msec, &key, INTERVAL_STEP, INTERVAL_PAGE_STEP, MIN_UPDATE_INTERVAL, MAX_UPDATE_INTERVAL);
+                        if let Some(stepped) = stepped.filter(|&stepped| stepped != msec) {
+                            return self.update(Message::UpdateInterval(stepped.to_string()))
+                        }
+                    }
+                    None => {},
+                }
+            }
+            Message::UpdateSystemMonitorCommand(command) => {
+                self.live_config
+                    .set_system_monitor_command(&self.config, command.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_system_monitor_command(command);
+            }
+            Message::UpdateDisplayMode(mode) => {
+                self.live_config
+                    .set_display_mode(&self.config, mode)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_display_mode(mode);
+            }
+            Message::UpdateLabelPrefix(label) => {
+                let label = truncate_label(label);
+                self.live_config
+                    .set_label_prefix(&self.config, label.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_label_prefix(label);
+            }
+            Message::UpdateLabelSuffix(label) => {
+                let label = truncate_label(label);
+                self.live_config
+                    .set_label_suffix(&self.config, label.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_label_suffix(label);
+            }
+            Message::UpdateSecondaryMetric(metric) => {
+                let slot = MetricSlot { metric, ..self.live_config.secondary_metric };
+                self.live_config
+                    .set_secondary_metric(&self.config, slot)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_secondary_metric(slot);
+            }
+            Message::UpdateSecondaryStyle(style) => {
+                let slot = MetricSlot { style, ..self.live_config.secondary_metric };
+                self.live_config
+                    .set_secondary_metric(&self.config, slot)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_secondary_metric(slot);
+            }
+            Message::UpdateMetricSeparator(separator) => {
+                let separator = truncate_label(separator);
+                self.live_config
+                    .set_metric_separator(&self.config, separator.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_metric_separator(separator);
+            }
+            Message::UpdateTextColorText(text) => {
+                self.text_color_text = text;
+            }
+            Message::UpdateTextColor(color) => {
+                self.live_config
+                    .set_text_color(&self.config, color.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_text_color(color);
+            }
+            Message::UpdateCriticalTextColor(enable) => {
+                self.live_config
+                    .set_critical_text_color(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_critical_text_color(enable);
+            }
+            Message::UpdateCriticalBlink(enable) => {
+                self.live_config
+                    .set_critical_blink(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_critical_blink(enable);
+            }
+            Message::Blink => {
+                self.blink_on = !self.blink_on;
+            }
+            Message::UpdateIconNameText(text) => {
+                self.icon_name_text = text;
+            }
+            Message::UpdateIconName(name) => {
+                self.live_config
+                    .set_icon_name(&self.config, name.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_icon_name(name);
+            }
+            Message::UpdateIconStyle(style) => {
+                self.live_config
+                    .set_icon_style(&self.config, style)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_icon_style(style);
+            }
+            Message::UpdateIconGradient(enable) => {
+                self.live_config
+                    .set_icon_gradient(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_icon_gradient(enable);
+            }
+            Message::UpdateGaugeIcon(enable) => {
+                self.live_config
+                    .set_gauge_icon(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_gauge_icon(enable);
+            }
+            Message::UpdateGaugeSwapRing(enable) => {
+                self.live_config
+                    .set_gauge_swap_ring(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_gauge_swap_ring(enable);
+            }
+            Message::UpdateGaugeLabel(enable) => {
+                self.live_config
+                    .set_gauge_label(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_gauge_label(enable);
+            }
+            Message::UpdatePercentStep(step) => {
+                self.live_config
+                    .set_percent_step(&self.config, step)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_percent_step(step);
+            }
+            Message::UpdateClampPercent(enable) => {
+                self.live_config
+                    .set_clamp_percent(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_clamp_percent(enable);
+            }
+            Message::UpdateDisplayCap(cap) => {
+                self.live_config
+                    .set_display_cap_bytes(&self.config, cap.filter(|&cap| cap > 0))
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_display_cap_bytes(cap);
+            }
+            Message::UpdateReducedMotion(enable) => {
+                self.live_config
+                    .set_reduced_motion(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_reduced_motion(enable);
+            }
+            Message::UpdateTightLayout(enable) => {
+                self.live_config
+                    .set_tight_layout(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_tight_layout(enable);
+            }
+            Message::UpdateTintBackground(enable) => {
+                self.live_config
+                    .set_tint_background(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_tint_background(enable);
+            }
+            Message::UpdateWarningThreshold(percent) => {
+                self.live_config
+                    .set_warning_threshold(&self.config, percent)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_warning_threshold(percent);
+            }
+            Message::UpdateCriticalThreshold(percent) => {
+                self.live_config
+                    .set_critical_threshold(&self.config, percent)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_critical_threshold(percent);
+            }
+            Message::UpdateSwapThresholds(thresholds) => {
+                let thresholds = thresholds.validated();
+                self.live_config
+                    .set_swap_thresholds(&self.config, thresholds)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_swap_thresholds(thresholds);
+            }
+            Message::UpdateProcessThresholds(thresholds) => {
+                let thresholds = thresholds.validated();
+                self.live_config
+                    .set_process_thresholds(&self.config, thresholds)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_process_thresholds(thresholds);
+            }
+            Message::UpdateCriticalActionCommandText(text) => {
+                self.critical_action_command_text = text;
+            }
+            Message::UpdateCriticalActionCommand(command) => {
+                self.live_config
+                    .set_critical_action_command(&self.config, command.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_critical_action_command(command);
+            }
+            Message::UpdateCriticalSound(enable) => {
+                self.live_config
+                    .set_critical_sound(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_critical_sound(enable);
+            }
+            Message::UpdateCriticalSoundFileText(text) => {
+                self.critical_sound_file_text = text;
+            }
+            Message::UpdateCriticalSoundFile(path) => {
+                self.live_config
+                    .set_critical_sound_file(&self.config, path.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_critical_sound_file(path);
+            }
+            // Without a working player, every later attempt would fail the same way
+            Message::SoundPlayed(Some(error)) => {
+                if !self.sound_unavailable {
+                    tracing::warn!("Failed to play the critical memory sound, not trying again: {}", error);
+                    self.sound_unavailable = true;
+                }
+            }
+            Message::SoundPlayed(None) => {}
+            Message::UpdatePressureEvents(enable) => {
+                self.live_config
+                    .set_pressure_events(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_pressure_events(enable);
+            }
+            // Pressure tends to come right before usage turns critical, so catch it without waiting
+            // for the next tick
+            Message::PressureEvent => {
+                tracing::debug!("The kernel reported memory pressure");
+                self.refresh_metrics();
+                return self.check_alerts();
+            }
+            Message::UpdatePrefix(prefix) => {
+                self.live_config
+                    .set_prefix(&self.config, prefix)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_prefix(prefix);
+                self.refresh_metrics();
+            }
+            Message::UpdateAdvancedUnits(enable) => {
+                self.live_config
+                    .set_advanced_units(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_advanced_units(enable);
+            }
+            Message::UpdateSmallestPrefix(prefix) => {
+                self.live_config
+                    .set_smallest_prefix(&self.config, prefix)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_smallest_prefix(prefix);
+            }
+            Message::UpdatePrefixLabelsText(text) => {
+                self.prefix_labels_text = text;
+            }
+            Message::UpdatePrefixLabels(text) => {
+                let labels = parse_prefix_labels(&text);
+                self.live_config
+                    .set_prefix_labels(&self.config, labels.clone())
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_prefix_labels(labels);
+            }
+            Message::UpdateAutoHysteresis(percent) => {
+                self.live_config
+                    .set_auto_hysteresis(&self.config, percent)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_auto_hysteresis(percent);
+            }
+            Message::UpdateLockPrefixToTotal(enable) => {
+                self.live_config
+                    .set_lock_prefix_to_total(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_lock_prefix_to_total(enable);
+            }
+            Message::UpdateSwapMatchesRam(enable) => {
+                self.live_config
+                    .set_swap_matches_ram(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_swap_matches_ram(enable);
+            }
+            Message::UpdateAutoNiceValues(enable) => {
+                self.live_config
+                    .set_auto_nice_values(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_auto_nice_values(enable);
+            }
+            Message::UpdateUnitOnTotalOnly(enable) => {
+                self.live_config
+                    .set_unit_on_total_only(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_unit_on_total_only(enable);
+            }
+            Message::UpdateSmoothingFactor(percent) => {
+                self.live_config
+                    .set_smoothing_factor(&self.config, percent)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_smoothing_factor(percent);
+            }
+            Message::UpdateSmoothingMode(mode) => {
+                self.live_config
+                    .set_smoothing_mode(&self.config, mode)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_smoothing_mode(mode);
+            }
+            Message::UpdateMovingAverageWindow(samples) => {
+                self.live_config
+                    .set_moving_average_window(&self.config, samples)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_moving_average_window(samples);
+            }
+            Message::UpdateInterval(text) => {
+                self.stepping = Some(SteppedSetting::UpdateInterval);
+                if let Ok(msec) = text.parse::<u64>() {
+                    if (MIN_UPDATE_INTERVAL..=MAX_UPDATE_INTERVAL).contains(&msec) {
+                        self.live_config
+                            .set_update_interval(&self.config, msec)
+                            .log_recoverable("Failed to save applet configuration");
+                        self.apply_interval();
+                        self.history.set_capacity(self.live_config.history_window.capacity(msec));
+                        self.publish_history();
+                    }
+                }
+                self.update_interval_text = text;
+            }
+            Message::UpdateFitPopupWidth(enable) => {
+                self.live_config
+                    .set_fit_popup_width(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_fit_popup_width(enable);
+            }
+            Message::UpdatePopupOffsetX(offset) => {
+                self.live_config
+                    .set_popup_offset_x(&self.config, offset.clamp(-MAX_POPUP_OFFSET, MAX_POPUP_OFFSET))
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_popup_offset_x(offset);
+            }
+            Message::UpdatePopupOffsetY(offset) => {
+                self.live_config
+                    .set_popup_offset_y(&self.config, offset.clamp(-MAX_POPUP_OFFSET, MAX_POPUP_OFFSET))
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_popup_offset_y(offset);
+            }
+            Message::UpdatePopupInterval(msec) => {
+                self.live_config
+                    .set_popup_update_interval(&self.config, msec)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_popup_update_interval(msec);
+            }
+            Message::UpdateAlignToClock(enable) => {
+                self.live_config
+                    .set_align_to_clock(&self.config, enable)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_align_to_clock(enable);
+            }
+            Message::UpdateMissedTicks(missed) => {
+                self.live_config
+                    .set_missed_ticks(&self.config, missed)
+                    .log_recoverable("Failed to save applet configuration");
+                self.ui_set_missed_ticks(missed);
+            }
+            Message::Surface(a) => return cosmic::task::message(cosmic::Action::Cosmic(
+                cosmic::app::Action::Surface(a)
+            )),
+            Message::ReloadConfig => {
+                match CosmicAppletRamConfig::get_entry(&self.config) {
+                    Ok(config) => {
+                        tracing::info!("Reloading applet configuration");
+                        return self.update(Message::ConfigChanged(config))
+                    }
+                    Err((errors, config)) => {
+                        for err in errors {
+                            tracing::error!(?err, "Failed to reload part of the applet configuration");
+                        }
+                        // Like the config watcher, apply the fields that could be read, while the
+                        // others are left at their defaults
+                        tracing::info!("Reloading applet configuration");
+                        return self.update(Message::ConfigChanged(config))
+                    }
+                }
+            }
+            Message::ConfigChanged(config) => {
+                let config = config.validated();
+                log_config_changes(&self.live_config, &config);
+                if config.source != self.live_config.source {
+                    self.ui_set_source(config.source.clone());
+                }
+                if clamp_precision(config.precision) != self.live_config.precision {
+                    self.ui_set_precision(config.precision);
+                }
+                if config.decimal_comma != self.live_config.decimal_comma {
+                    self.ui_set_decimal_comma(config.decimal_comma);
+                }
+                if config.expand_small_values != self.live_config.expand_small_values {
+                    self.ui_set_expand_small_values(config.expand_small_values);
+                }
+                if config.rounding != self.live_config.rounding {
+                    self.ui_set_rounding(config.rounding);
+                }
+                if config.prefix != self.live_config.prefix {
+                    self.ui_set_prefix(config.prefix);
+                }
+                if config.advanced_units != self.live_config.advanced_units {
+                    self.ui_set_advanced_units(config.advanced_units);
+                }
+                if config.smallest_prefix != self.live_config.smallest_prefix {
+                    self.ui_set_smallest_prefix(config.smallest_prefix);
+                }
+                if config.auto_hysteresis != self.live_config.auto_hysteresis {
+                    self.ui_set_auto_hysteresis(config.auto_hysteresis);
+                }
+                if config.lock_prefix_to_total != self.live_config.lock_prefix_to_total {
+                    self.ui_set_lock_prefix_to_total(config.lock_prefix_to_total);
+                }
+                if config.swap_matches_ram != self.live_config.swap_matches_ram {
+                    self.ui_set_swap_matches_ram(config.swap_matches_ram);
+                }
+                if config.auto_nice_values != self.live_config.auto_nice_values {
+                    self.ui_set_auto_nice_values(config.auto_nice_values);
+                }
+                if config.unit_on_total_only != self.live_config.unit_on_total_only {
+                    self.ui_set_unit_on_total_only(config.unit_on_total_only);
+                }
+                if config.smoothing_factor != self.live_config.smoothing_factor {
+                    self.ui_set_smoothing_factor(config.smoothing_factor);
+                }
+                if config.smoothing_mode != self.live_config.smoothing_mode {
+                    self.ui_set_smoothing_mode(config.smoothing_mode);
+                }
+                if config.moving_average_window != self.live_config.moving_average_window {
+                    self.ui_set_moving_average_window(config.moving_average_window);
+                }
+                if config.show_total != self.live_config.show_total {
+                    self.ui_set_show_total(config.show_total);
+                }
+                if config.static_total != self.live_config.static_total {
+                    self.ui_set_static_total(config.static_total);
+                }
+                if config.show_total_vertical != self.live_config.show_total_vertical {
+                    self.ui_set_show_total_vertical(config.show_total_vertical);
+                }
+                if config.show_icon_vertical != self.live_config.show_icon_vertical {
+                    self.ui_set_show_icon_vertical(config.show_icon_vertical);
+                }
+                if config.denominator != self.live_config.denominator {
+                    self.ui_set_denominator(config.denominator);
+                }
+                if config.used_definition != self.live_config.used_definition {
+                    self.ui_set_used_definition(config.used_definition);
+                }
+                if config.monitor_target != self.live_config.monitor_target {
+                    self.ui_set_monitor_target(config.monitor_target);
+                }
+                if config.process_target != self.live_config.process_target {
+                    self.ui_set_process_target(config.process_target.clone());
+                }
+                if config.combined_view != self.live_config.combined_view {
+                    self.ui_set_combined_view(config.combined_view);
+                }
+                if config.show_headroom != self.live_config.show_headroom {
+                    self.ui_set_show_headroom(config.show_headroom);
+                }
+                if config.clickable_segments != self.live_config.clickable_segments {
+                    self.ui_set_clickable_segments(config.clickable_segments);
+                }
+                if config.monospace_digits != self.live_config.monospace_digits {
+                    self.ui_set_monospace_digits(config.monospace_digits);
+                }
+                if config.show_trend != self.live_config.show_trend {
+                    self.ui_set_show_trend(config.show_trend);
+                }
+                if config.trend_epsilon != self.live_config.trend_epsilon {
+                    self.ui_set_trend_epsilon(config.trend_epsilon);
+                }
+                if config.trend_mode != self.live_config.trend_mode {
+                    self.ui_set_trend_mode(config.trend_mode);
+                }
+                if config.show_uptime != self.live_config.show_uptime {
+                    self.ui_set_show_uptime(config.show_uptime);
+                }
+                if config.usage_in_words != self.live_config.usage_in_words {
+                    self.ui_set_usage_in_words(config.usage_in_words);
+                }
+                if config.usage_in_words_popup != self.live_config.usage_in_words_popup {
+                    self.ui_set_usage_in_words_popup(config.usage_in_words_popup);
+                }
+                if config.show_history_stats != self.live_config.show_history_stats {
+                    self.ui_set_show_history_stats(config.show_history_stats);
+                }
+                if config.show_rate_graph != self.live_config.show_rate_graph {
+                    self.ui_set_show_rate_graph(config.show_rate_graph);
+                }
+                if config.smooth_rate_graph != self.live_config.smooth_rate_graph {
+                    self.ui_set_smooth_rate_graph(config.smooth_rate_graph);
+                }
+                if config.graph_gap_intervals != self.live_config.graph_gap_intervals {
+                    self.ui_set_graph_gap_intervals(config.graph_gap_intervals);
+                }
+                if config.show_both_standards != self.live_config.show_both_standards {
+                    self.ui_set_show_both_standards(config.show_both_standards);
+                }
+                if config.show_oom_estimate != self.live_config.show_oom_estimate {
+                    self.ui_set_show_oom_estimate(config.show_oom_estimate);
+                }
+                if config.show_forecast != self.live_config.show_forecast {
+                    self.ui_set_show_forecast(config.show_forecast);
+                }
+                if config.forecast_horizon != self.live_config.forecast_horizon {
+                    self.ui_set_forecast_horizon(config.forecast_horizon);
+                }
+                if config.show_last_update != self.live_config.show_last_update {
+                    self.ui_set_show_last_update(config.show_last_update);
+                }
+                if config.compare_last_session != self.live_config.compare_last_session {
+                    self.ui_set_compare_last_session(config.compare_last_session);
+                }
+                if config.show_numa != self.live_config.show_numa {
+                    self.ui_set_show_numa(config.show_numa);
+                }
+                if config.show_ecc != self.live_config.show_ecc {
+                    self.ui_set_show_ecc(config.show_ecc);
+                }
+                if config.show_modules != self.live_config.show_modules {
+                    self.ui_set_show_modules(config.show_modules);
+                }
+                if config.usage_bar != self.live_config.usage_bar {
+                    self.ui_set_usage_bar(config.usage_bar);
+                }
+                if config.show_top_processes != self.live_config.show_top_processes {
+                    self.ui_set_show_top_processes(config.show_top_processes);
+                }
+                if config.pinned_processes != self.live_config.pinned_processes {
+                    self.ui_set_pinned_processes(config.pinned_processes.clone());
+                }
+                if config.swap_notify_threshold != self.live_config.swap_notify_threshold {
+                    self.ui_set_swap_notify_threshold(config.swap_notify_threshold);
+                }
+                if config.refresh_on_open != self.live_config.refresh_on_open {
+                    self.ui_set_refresh_on_open(config.refresh_on_open);
+                }
+                if config.freeze_on_hover != self.live_config.freeze_on_hover {
+                    self.ui_set_freeze_on_hover(config.freeze_on_hover);
+                }
+                if config.primary_action != self.live_config.primary_action {
+                    self.ui_set_primary_action(config.primary_action);
+                }
+                if config.system_monitor_command != self.live_config.system_monitor_command {
+                    self.ui_set_system_monitor_command(config.system_monitor_command);
+                }
+                if config.popup_shortcut != self.live_config.popup_shortcut {
+                    self.ui_set_popup_shortcut(config.popup_shortcut);
+                }
+                if config.display_mode != self.live_config.display_mode {
+                    self.ui_set_display_mode(config.display_mode);
+                }
+                if config.label_prefix != self.live_config.label_prefix {
+                    self.ui_set_label_prefix(config.label_prefix);
+                }
+                if config.label_suffix != self.live_config.label_suffix {
+                    self.ui_set_label_suffix(config.label_suffix);
+                }
+                if config.secondary_metric != self.live_config.secondary_metric {
+                    self.ui_set_secondary_metric(config.secondary_metric);
+                }
+                if config.metric_separator != self.live_config.metric_separator {
+                    self.ui_set_metric_separator(config.metric_separator);
+                }
+                if config.text_color != self.live_config.text_color {
+                    self.ui_set_text_color(config.text_color);
+                }
+                if config.critical_text_color != self.live_config.critical_text_color {
+                    self.ui_set_critical_text_color(config.critical_text_color);
+                }
+                if config.critical_blink != self.live_config.critical_blink {
+                    self.ui_set_critical_blink(config.critical_blink);
+                }
+                if config.icon_name != self.live_config.icon_name {
+                    self.ui_set_icon_name(config.icon_name);
+                }
+                if config.icon_style != self.live_config.icon_style {
+                    self.ui_set_icon_style(config.icon_style);
+                }
+                if config.icon_gradient != self.live_config.icon_gradient {
+                    self.ui_set_icon_gradient(config.icon_gradient);
+                }
+                if config.persist_history != self.live_config.persist_history {
+                    self.ui_set_persist_history(config.persist_history);
+                }
+                // The offsets are applied when the popup is opened next
+                if config.popup_offset_x != self.live_config.popup_offset_x {
+                    self.ui_set_popup_offset_x(config.popup_offset_x);
+                }
+                if config.popup_offset_y != self.live_config.popup_offset_y {
+                    self.ui_set_popup_offset_y(config.popup_offset_y);
+                }
+                if config.fit_popup_width != self.live_config.fit_popup_width {
+                    self.ui_set_fit_popup_width(config.fit_popup_width);
+                }
+                if config.history_window != self.live_config.history_window {
+                    self.ui_set_history_window(config.history_window);
+                }
+                if config.prefix_labels != self.live_config.prefix_labels {
+                    self.ui_set_prefix_labels(config.prefix_labels.clone());
+                }
+                if config.placeholder != self.live_config.placeholder {
+                    self.ui_set_placeholder(config.placeholder.clone());
+                }
+                if config.retry_popup != self.live_config.retry_popup {
+                    self.live_config.retry_popup = config.retry_popup;
+                }
+                // The introduction can be dismissed from elsewhere, but never comes back, as when a
+                // reset or an import would otherwise bring it back
+                if !config.first_run && self.live_config.first_run {
+                    self.live_config.first_run = false;
+                }
+                if config.display_cap_bytes != self.live_config.display_cap_bytes {
+                    self.ui_set_display_cap_bytes(config.display_cap_bytes);
+                }
+                if config.usage_log_max_size != self.live_config.usage_log_max_size {
+                    self.ui_set_usage_log_max_size(config.usage_log_max_size);
+                }
+                if config.usage_log_path != self.live_config.usage_log_path {
+                    self.ui_set_usage_log_path(config.usage_log_path);
+                }
+                if config.gauge_icon != self.live_config.gauge_icon {
+                    self.ui_set_gauge_icon(config.gauge_icon);
+                }
+                if config.gauge_swap_ring != self.live_config.gauge_swap_ring {
+                    self.ui_set_gauge_swap_ring(config.gauge_swap_ring);
+                }
+                if config.gauge_label != self.live_config.gauge_label {
+                    self.ui_set_gauge_label(config.gauge_label);
+                }
+                if config.percent_step != self.live_config.percent_step {
+                    self.ui_set_percent_step(config.percent_step);
+                }
+                if config.clamp_percent != self.live_config.clamp_percent {
+                    self.ui_set_clamp_percent(config.clamp_percent);
+                }
+                if config.reduced_motion != self.live_config.reduced_motion {
+                    self.ui_set_reduced_motion(config.reduced_motion);
+                }
+                if config.tight_layout != self.live_config.tight_layout {
+                    self.ui_set_tight_layout(config.tight_layout);
+                }
+                if config.tint_background != self.live_config.tint_background {
+                    self.ui_set_tint_background(config.tint_background);
+                }
+                if config.warning_threshold != self.live_config.warning_threshold {
+                    self.ui_set_warning_threshold(config.warning_threshold);
+                }
+                if config.critical_threshold != self.live_config.critical_threshold {
+                    self.ui_set_critical_threshold(config.critical_threshold);
+                }
+                if config.swap_thresholds != self.live_config.swap_thresholds {
+                    self.ui_set_swap_thresholds(config.swap_thresholds);
+                }
+                if config.process_thresholds != self.live_config.process_thresholds {
+                    self.ui_set_process_thresholds(config.process_thresholds);
+                }
+                if config.critical_action_command != self.live_config.critical_action_command {
+                    self.ui_set_critical_action_command(config.critical_action_command);
+                }
+                if config.critical_sound != self.live_config.critical_sound {
+                    self.ui_set_critical_sound(config.critical_sound);
+                }
+                if config.critical_sound_file != self.live_config.critical_sound_file {
+                    self.ui_set_critical_sound_file(config.critical_sound_file);
+                }
+                if config.pressure_events != self.live_config.pressure_events {
+                    self.ui_set_pressure_events(config.pressure_events);
+                }
+                if config.standard != self.live_config.standard {
+                    self.ui_set_standard(config.standard);
+                }
+                if config.prefix_base != self.live_config.prefix_base {
+                    self.ui_set_prefix_base(config.prefix_base);
+                }
+                if config.prefix_style != self.live_config.prefix_style {
+                    self.ui_set_prefix_style(config.prefix_style);
+                }
+                if config.like_free != self.live_config.like_free {
+                    self.ui_set_like_free(config.like_free);
+                }
+                if config.unit != self.live_config.unit {
+                    self.ui_set_unit(config.unit);
+                }
+                if config.update_interval != self.live_config.update_interval {
+                    self.ui_set_update_interval(config.update_interval);
+                }
+                if config.popup_update_interval != self.live_config.popup_update_interval {
+                    self.ui_set_popup_update_interval(config.popup_update_interval);
+                }
+                if config.align_to_clock != self.live_config.align_to_clock {
+                    self.ui_set_align_to_clock(config.align_to_clock);
+                }
+                if config.missed_ticks != self.live_config.missed_ticks {
+                    self.ui_set_missed_ticks(config.missed_ticks);
+                }
+            }
+        }
+        Task::none() // Again not doing anything that requires multi-threading here.
+    }
+
+    /*
+    *  For an applet, the view function describes what an applet looks like. There's a
+    *  secondary view function (view_window) that shows the widgets in the popup when it's
+    *  opened.
+    */
+    fn view(&self) -> Element<Self::Message> {
+        let (icon_size, _) = self.core.applet.suggested_size(false);
+        let layout = effective_layout(self.core.applet.anchor, icon_size, &self.live_config);
+
+        let padding = self.core.applet.suggested_padding(false);
+        // On a small panel, the percentage wouldn't be legible inside the ring, and with swap
+        // the inner ring takes up its place
+        let gauge_label = self.live_config.gauge_icon
+            && self.live_config.gauge_label
+            && !self.live_config.gauge_swap_ring
+            && self.live_config.display_mode != DisplayMode::Dot
+            && icon_size >= GAUGE_LABEL_MIN_ICON_SIZE;
+        let icon = if self.live_config.display_mode == DisplayMode::Dot {
+            let fill = self.usage_band().color(&cosmic::theme::active());
+            icon::icon(icon::from_svg_bytes(gauge::dot_svg(fill).into_bytes()))
+        } else if self.live_config.gauge_icon {
+            // The gauge is a vector image, so it stays sharp on any display scale
+            let theme = cosmic::theme::active();
+            let mut track: Color = theme.cosmic().on_bg_color().into();
+            track.a = 0.3;
+            let fill = self.usage_band().color(&theme);
+            let svg = if self.live_config.gauge_swap_ring {
+                gauge::dual_ring_svg(
+                    self.icon_ratio(),
+                    self.swap_icon_ratio(),
+                    track,
+                    fill,
+                    // Swap is colored by its own thresholds, not by those of what the outer ring shows
+                    self.swap_band().unwrap_or(UsageBand::Normal).color(&theme),
+                    icon_size < DUAL_GAUGE_THIN_ICON_SIZE,
+                )
+            } else {
+                gauge::ring_svg(self.icon_ratio(), track, fill)
+            };
+            let icon = icon::icon(icon::from_svg_bytes(svg.into_bytes()));
+            // The label needs the ring at full size to fit
+            if gauge_label {
+                icon.size(icon_size)
+            } else {
+                icon
+            }
+        } else if self.live_config.icon_style == IconStyle::FullColor {
+            // Tinting would paint over the colors, so the gradient only applies to symbolic icons
+            icon::icon(icon::from_svg_bytes(FULL_COLOR_ICON).symbolic(false))
+        } else {
+            let mut icon = icon::from_name(self.icon_name.as_str()).icon();
+            if self.live_config.icon_gradient {
+                let ratio = self.icon_ratio();
+                icon = icon.class(cosmic::theme::Svg::Custom(Rc::new(move |theme: &cosmic::Theme| {
+                    svg::Style {
+                        color: Some(usage_gradient(ratio, theme.cosmic().on_bg_color().into())),
+                    }
+                })));
+            }
+            icon
+        };
+        let icon = container(icon)
+            .padding(icon_padding(padding, self.live_config.tight_layout));
+        let text_color = self.panel_text_color();
+        let icon = if gauge_label {
+            // The ring can't fill past full, but unless percentages are capped the label can tell
+            // by how much the total is exceeded
+            let percent = match self.usage_percent() {
+                percent if percent > 100.0 => percent,
+                _ => self.icon_ratio() * 100.0,
+            };
+            let label = text(format!("{:.0}", self.display_percent(f64::from(percent))))
+                .size(f32::from(icon_size) * GAUGE_LABEL_SCALE);
+            let label = match text_color {
+                Some(color) => label.class(cosmic::theme::Text::Color(color)),
+                None => label,
+            };
+            Element::from(stack![icon, container(label).center(Length::Fill)])
+        } else {
+            Element::from(icon)
+        };
+        let label = self.panel_label(layout);
+        // Without any text, as in the icon-only mode, the icon is all there is to show
+        let mut children = if layout.show_icon || label.is_empty() {
+            vec![icon]
+        } else {
+            Vec::new()
+        };
+        for (segment, content) in label {
+            let text = self.core.applet.text(content);
+            let text = if self.monospaced(segment) {
+                text.font(cosmic::font::mono())
+            } else {
+                text
+            };
+            let text = match text_color {
+                Some(color) => Element::from(text.class(cosmic::theme::Text::Color(color))),
+                None => Element::from(text),
+            };
+            // The segment takes the click, so that the button underneath doesn't see it; the icon
+            // and the rest of the text still do the primary action
+            children.push(if self.live_config.clickable_segments && matches!(segment, Segment::Used | Segment::Total) {
+                Element::from(mouse_area(text).on_press(Message::SegmentClicked(segment)))
+            } else {
+                text
+            });
+        }
+        let children = panel_order(children, layout, self.rtl);
+        let content = if layout.horizontal {
+            Element::from(cosmic::widget::row::with_children(children).align_y(Center))
+        } else {
+            Element::from(cosmic::widget::column::with_children(children).align_x(Center))
+        };
+        // The tint goes inside the button, so that the whole of it still takes clicks
+        let theme = cosmic::theme::active();
+        let content = match self.usage_band().tint(&theme).filter(|_| self.live_config.tint_background) {
+            Some(tint) => {
+                let radius = theme.cosmic().corner_radii.radius_s;
+                Element::from(
+                    container(content).class(cosmic::theme::Container::custom(move |_| cosmic::iced::widget::container::Style {
+                        background: Some(tint.into()),
+                        border: cosmic::iced::Border {
+                            radius: radius.into(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }))
+                )
+            }
+            None => content,
+        };
+        let button = button::custom(content)
+        // Screen readers announce the usage, even when the panel shows nothing but the icon
+        .name(self.tooltip_text())
+        .on_press_down(
+            match self.live_config.primary_action {
+                PrimaryAction::TogglePopup => Message::TogglePopup,
+                PrimaryAction::LaunchSystemMonitor => Message::LaunchSystemMonitor,
+                PrimaryAction::CopyUsage => Message::CopyUsage,
+            }
+        )
+        .class(cosmic::theme::Button::AppletIcon);
+
+        // Right-clicking always toggles the popup, so that its settings remain reachable when the
+        // primary action was changed to something else
+        let button = mouse_area(button)
+            .on_right_press(Message::TogglePopup)
+            .on_enter(Message::Hover(true))
+            .on_exit(Message::Hover(false));
+
+        // In minimal mode, this is the only place where the usage can be read from the panel
+        let button = self.core.applet.applet_tooltip::<Message>(
+            button,
+            self.tooltip_text(),
+            self.popup.is_some(),
+            Message::Surface,
+            None,
+        );
+
+        autosize::autosize(
+            button,
+            AUTOSIZE_MAIN_ID.clone()
+        )
+        .into()
+        // self.core
+        //     .applet
+        //     .icon_button("display-symbolic") // Using a default button image
+        //     .on_press(Message::TogglePopup)
+        //     .into()
+    }
+
+    // The actual GUI window for the applet. It's a popup.
+    fn view_window(&self, _id: window::Id) -> Element<Self::Message> {
+
+        // Needed to compare later on which control is selected
+        let entity_iec = self.entity_iec.clone();
+        let entity_si = self.entity_si.clone();
+        let entity_byte = self.entity_byte.clone();
+        let entity_bit = self.entity_bit.clone();
+
+        // Copied so that the spin buttons can change one threshold and keep the other
+        let swap_thresholds = self.live_config.swap_thresholds;
+        let process_thresholds = self.live_config.process_thresholds;
+
+        // Truncating keeps the position of every entry, so indices still map to the same prefix
+        let prefix_menu_items = if self.live_config.advanced_units {
+            &PREFIX_MENU_ITEMS[..]
+        } else {
+            &PREFIX_MENU_ITEMS[..BASIC_PREFIX_MENU_ITEMS]
+        };
+
+        let cosmic::cosmic_theme::Spacing {
+            space_s, ..
+        } = cosmic::theme::spacing();
+
+        let mut content_list = column![].spacing(space_s);
+
+        if self.live_config.first_run {
+            content_list = content_list.push(
+                column![
+                    text::heading("Welcome"),
+                    text("Most settings can wait, but have a look at these first: Standard picks between units such as GB and GiB, Update Interval sets how often usage is read, and Show Total adds the total memory to the panel."),
+                    button::suggested("Got it").on_press(Message::DismissFirstRun),
+                ]
+                .spacing(space_s)
+            );
+        }
+
+        content_list = content_list.push(settings::item(
+            "Keep Open",
+            checkbox("", self.pinned)
+                .on_toggle(Message::UpdatePinned)
+        ));
+
+        if self.stalled {
+            content_list = content_list.push(text(format!(
+                "Metrics stalled for {}, the numbers below may be out of date",
+                self.format_duration(self.refreshed_at.elapsed().as_millis() as u64),
+            )));
+        }
+
+        content_list = content_list.push(settings::item(
+            "Source",
+            cosmic::widget::row::with_children(vec![
+                Element::from(text(match (&self.source_error, &self.command_errors.remote) {
+                    // Explains the failure better than the error of running the command does
+                    (Some(_), Some(problem)) => format!("Failing: {problem}"),
+                    (Some(error), None) => format!("Failing: {error}"),
+                    (None, _) if !self.has_sample => String::from("Waiting for data"),
+                    (None, _) => String::from("Working"),
+                })),
+                Element::from(
+                    button::standard(if matches!(self.live_config.source, Source::Remote { .. }) { "Reconnect" } else { "Restart" })
+                        .on_press(Message::RestartSource)
+                ),
+            ])
+            .spacing(space_s)
+            .align_y(Center),
+        ));
+
+        // Redrawn on every tick while the popup is open, which keeps the time current
+        if self.live_config.show_last_update {
+            content_list = content_list.push(settings::item(
+                "Updated",
+                text(match self.updated_at {
+                    Some(updated_at) => format!("{} ago", self.format_duration(updated_at.elapsed().as_millis() as u64)),
+                    None => String::from("never"),
+                }),
+            ));
+        }
+
+        if let Some(comparison) = self.last_session_text() {
+            content_list = content_list.push(settings::item("Last Session", text(comparison)));
+        }
+
+        if let Some(warning) = &self.icon_warning {
+            content_list = content_list.push(text(warning));
+        }
+
+        if let Some(version) = self.newer_config_version {
+            content_list = content_list.push(text(format!(
+                "Settings from a newer version of the applet (v{version}) are kept, but not used by this one",
+            )));
+        }
+
+        if !self.unknown_config_fields.is_empty() {
+            content_list = content_list.push(text(format!(
+                "Settings unknown to this version of the applet are kept, but not used: {}",
+                self.unknown_config_fields.join(", "),
+            )));
+        }
+
+        if let Some(problem) = &self.degraded {
+            content_list = content_list.push(text(format!("Memory statistics look wrong: {problem}")));
+        }
+
+        // The rounded value is easier to read, but the exact one is only a hover away
+        let exact = |label: &'static str, count: u64| settings::item(
+            label,
+            tooltip(
+                text(self.format(count)),
+                text(format!("{} bytes", format_grouped(count))),
+                tooltip::Position::Top,
+            ),
+        );
+        content_list = content_list.push(exact("Used", self.displayed_used()));
+        content_list = content_list.push(exact("Total", self.displayed_total()));
+        if self.memory.total_swap > 0 {
+            content_list = content_list.push(exact("Swap", self.memory.used_swap));
+        }
+        if let Some((label, usage)) = self.combined_item() {
+            content_list = content_list.push(settings::item(label, text(usage)));
+        }
+
+        // Without a total there is no fraction to fill the bar by
+        if self.live_config.usage_bar != UsageBar::Hidden && self.displayed_total() > 0 {
+            let theme = cosmic::theme::active();
+            let mut track: Color = theme.cosmic().on_bg_color().into();
+            track.a = 0.2;
+            // Whichever way the bar fills, its color says how much memory is left
+            let fill = self.usage_band().color(&theme);
+            content_list = content_list.push(match self.live_config.usage_bar {
+                UsageBar::Headroom => fill_bar(1.0 - self.usage_ratio(), true, fill, track),
+                UsageBar::Free => {
+                    let free = 1.0 - self.usage_ratio();
+                    let fill = UsageBand::classify_free(free * 100.0, self.live_config.thresholds(self.monitored_metric())).color(&theme);
+                    fill_bar(free, false, fill, track)
+                }
+                _ => fill_bar(self.usage_ratio(), false, fill, track),
+            });
+        }
+
+        // Lets the user compare both values while tuning the smoothing
+        if self.smoothing_enabled() {
+            content_list = content_list.push(settings::item(
+                "Raw / Smoothed",
+                text(format!(
+                    "{} / {}",
+                    self.format(self.memory.used),
+                    self.format(self.live_used()),
+                )),
+            ));
+        }
+
+        if let Some(words) = self.usage_words().filter(|_| self.live_config.usage_in_words_popup) {
+            content_list = content_list.push(text(words));
+        }
+
+        if self.live_config.show_uptime {
+            content_list = content_list.push(settings::item(
+                "Uptime",
+                text(format_uptime(self.uptime)),
+            ));
+        }
+
+        // The same amount in both standards, regardless of the one the panel uses
+        if self.live_config.show_both_standards {
+            let mut iec = self.byte_format();
+            iec.set_standard(Standard::Iec);
+            let mut si = self.byte_format();
+            si.set_standard(Standard::Si);
+            content_list = content_list.push(settings::item(
+                "Used (IEC / SI)",
+                text(format!(
+                    "{} / {}",
+                    format_bytes(self.displayed_used(), &iec),
+                    format_bytes(self.displayed_used(), &si),
+                )),
+            ));
+        }
+
+        if let Some(estimate) = self.oom_estimate().filter(|_| self.live_config.show_oom_estimate) {
+            content_list = content_list.push(settings::item(
+                "Full In",
+                text(match estimate {
+                    Some(secs) if secs < 60 => String::from("less than a minute at current rate"),
+                    Some(secs) => format!("~{} at current rate", format_uptime(secs)),
+                    None => String::from("stable"),
+                }),
+            ));
+        }
+
+        if self.live_config.show_forecast {
+            content_list = content_list.push(settings::item(
+                "Forecast",
+                text(match self.forecast() {
+                    Some(used) => format!(
+                        "in {}: ~{}",
+                        self.format_duration(u64::from(self.live_config.forecast_horizon) * 1000),
+                        self.format(used),
+                    ),
+                    None => String::from("insufficient data"),
+                }),
+            ));
+        }
+
+        // With fewer than two samples, there is nothing to summarize yet
+        if let Some(stats) = self.history.stats().filter(|_| self.live_config.show_history_stats) {
+            content_list = content_list.push(settings::item(
+                "History",
+                text(format!(
+                    "min: {} / avg: {} / max: {}",
+                    self.format(stats.min),
+                    self.format(stats.mean),
+                    self.format(stats.max),
+                )),
+            ));
+        }
+
+        if self.live_config.show_rate_graph {
+            content_list = content_list.push(self.rate_graph());
+        }
+
+        // A single node is the same as the aggregate, so there's nothing to show
+        if self.live_config.show_numa && self.numa_nodes.len() > 1 {
+            for node in &self.numa_nodes {
+                content_list = content_list.push(settings::item(
+                    format!("Node {}", node.id),
+                    text(format!("{} / {}", self.format(node.used()), self.format(node.total))),
+                ));
+            }
+        }
+
+        // Without EDAC, as on most consumer hardware, there is nothing to show
+        if let Some(errors) = self.ecc_errors.filter(|_| self.live_config.show_ecc) {
+            let summary = text(format!("{} corrected, {} uncorrected", errors.corrected, errors.uncorrected));
+            content_list = content_list.push(settings::item(
+                "Memory Errors",
+                // Uncorrected errors may have corrupted data, so they shouldn't go unnoticed
+                if errors.uncorrected > 0 {
+                    let color = UsageBand::Critical.color(&cosmic::theme::active());
+                    Element::from(summary.class(cosmic::theme::Text::Color(color)))
+                } else {
+                    Element::from(summary)
+                },
+            ));
+        }
+
+        // The firmware tables are usually only readable by root, in which case there is nothing to show
+        if let Some(Some(summary)) = self.modules_summary.as_ref().filter(|_| self.live_config.show_modules) {
+            content_list = content_list.push(settings::item("Modules", text(summary)));
+        }
+
+        if let Some(used_at_open) = self.used_at_open {
+            content_list = content_list.push(settings::item(
+                "Since Opened",
+                text(format!("{} since opened", self.format_change(used_at_open, self.memory.used))),
+            ));
+        }
+
+        content_list = content_list.push(settings::item(
+            "Baseline",
+            match self.baseline {
+                Some(baseline) => Element::from(
+                    cosmic::widget::row::with_children(vec![
+                        Element::from(text(format!(
+                            "{} since baseline",
+                            self.format_change(baseline, self.memory.used),
+                        ))),
+                        Element::from(button::standard("Clear").on_press(Message::ClearBaseline)),
+                    ])
+                    .spacing(space_s)
+                    .align_y(Center)
+                ),
+                None => Element::from(button::standard("Set").on_press(Message::SetBaseline)),
+            },
+        ));
+
+        // Marking A again starts a new measurement at any point
+        let mark_a = || Element::from(button::standard("Mark A").on_press(Message::MarkA));
+        let clear_markers = || Element::from(button::standard("Clear").on_press(Message::ClearMarkers));
+        content_list = content_list.push(settings::item(
+            "Measure",
+            cosmic::widget::row::with_children(match (self.marker_a, self.marker_b) {
+                (Some(a), Some(b)) => vec![
+                    Element::from(text(format!(
+                        "A→B: {} over {}",
+                        self.format_change(a.used, b.used),
+                        self.format_duration(b.at.duration_since(a.at).as_millis() as u64),
+                    ))),
+                    mark_a(),
+                    clear_markers(),
+                ],
+                (Some(a), None) => vec![
+                    Element::from(text(format!(
+                        "A: {} ago",
+                        self.format_duration(a.at.elapsed().as_millis() as u64),
+                    ))),
+                    mark_a(),
+                    Element::from(button::standard("Mark B").on_press(Message::MarkB)),
+                    clear_markers(),
+                ],
+                _ => vec![mark_a()],
+            })
+            .spacing(space_s)
+            .align_y(Center),
+        ));
+
+        for process in &self.top_processes {
+            content_list = content_list.push(settings::item(
+                process.name.as_str(),
+                text(self.format(process.memory)),
+            ));
+        }
+
+        for (index, name) in self.live_config.pinned_processes.iter().enumerate() {
+            content_list = content_list.push(settings::item(
+                name.as_str(),
+                cosmic::widget::row::with_children(vec![
+                    Element::from(text(match self.pinned_process_memory.get(index).copied().flatten() {
+                        Some(memory) => self.format(memory),
+                        None => String::from("not running"),
+                    })),
+                    Element::from(button::standard("Unpin").on_press(Message::UnpinProcess(index))),
+                ])
+                .spacing(space_s)
+                .align_y(Center),
+            ));
+        }
+
+        content_list = content_list.push(settings::item(
+            "All Memory Statistics",
+            button::standard(if self.meminfo_expanded { "Hide" } else { "Show" })
+                .on_press(Message::ToggleMeminfo),
+        ));
+        if self.meminfo_expanded {
+            let mut fields = column![].spacing(space_s);
+            for field in &self.meminfo_fields {
+                fields = fields.push(settings::item(
+                    field.name.as_str(),
+                    // Counts such as of huge pages aren't amounts of memory
+                    text(if field.bytes { self.format(field.value) } else { format_grouped(field.value) }),
+                ));
+            }
+            content_list = content_list.push(scrollable(fields).height(Length::Fixed(MEMINFO_LIST_HEIGHT)));
+        }
+
+        let mut settings_list = column![
+            settings::item::builder("Update Interval (in ms)")
+                .description(self.format_duration(self.live_config.update_interval))
+                .control(
+                    text_input("", &self.update_interval_text)
+                        .on_input(Message::UpdateInterval),
+                ),
+            settings::item::builder("Presets")
+                .description(
+                    INTERVAL_PRESETS.iter()
+                        .map(|&(_, msec)| self.format_duration(msec))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+                // Going through the text keeps the field above in sync with the preset
+                .control(cosmic::widget::row::with_children(INTERVAL_PRESETS.iter().map(|&(label, msec)| {
+                    let button = if self.live_config.update_interval == msec {
+                        button::suggested(label)
+                    } else {
+                        button::standard(label)
                     };
-                    let new_id = window::Id::unique();
-                    self.popup.replace(new_id);
+                    Element::from(button.on_press(Message::UpdateInterval(msec.to_string())))
+                }).collect::<Vec<_>>())
+                .spacing(space_s)),
+            settings::item(
+                "Update Interval With Popup Open",
+                spin_button(
+                    match self.live_config.popup_update_interval {
+                        0 => String::from("Same as panel"),
+                        msec => self.format_duration(msec),
+                    },
+                    self.live_config.popup_update_interval,
+                    MIN_UPDATE_INTERVAL,
+                    0,
+                    MAX_POPUP_UPDATE_INTERVAL,
+                    Message::UpdatePopupInterval,
+                ),
+            ),
+            settings::item(
+                "Align Updates to Clock",
+                checkbox("", self.live_config.align_to_clock)
+                    .on_toggle(Message::UpdateAlignToClock)
+            ),
+            settings::item(
+                "Missed Updates",
+                popup_dropdown(
+                    &MISSED_TICKS_MENU_ITEMS,
+                    Some(
+                        match self.live_config.missed_ticks {
+                            MissedTicks::Skip => 0,
+                            MissedTicks::Delay => 1,
+                            MissedTicks::Burst => 2,
+                        }
+                    ),
+                    |i| Message::UpdateMissedTicks(
+                        match i {
+                            0 => MissedTicks::Skip,
+                            1 => MissedTicks::Delay,
+                            2 => MissedTicks::Burst,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Standard",
+                segmented_control::horizontal(&self.standard_model)
+                    .on_activate(move |e| Message::UpdateStandard(
+                        if e == entity_iec {
+                            Standard::Iec
+                        } else if e == entity_si {
+                            Standard::Si
+                        } else {
+                            unreachable!()
+                        }
+                    ))
+            ),
+            settings::item(
+                "Prefix Base",
+                popup_dropdown(
+                    &PREFIX_BASE_MENU_ITEMS,
+                    Some(
+                        match self.live_config.prefix_base {
+                            None => 0,
+                            Some(Base::Decimal) => 1,
+                            Some(Base::Binary) => 2,
+                        }
+                    ),
+                    |i| Message::UpdatePrefixBase(
+                        match i {
+                            0 => None,
+                            1 => Some(Base::Decimal),
+                            2 => Some(Base::Binary),
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Prefix Labels",
+                popup_dropdown(
+                    &PREFIX_STYLE_MENU_ITEMS,
+                    Some(
+                        match self.live_config.prefix_style {
+                            None => 0,
+                            Some(PrefixStyle::Plain) => 1,
+                            Some(PrefixStyle::Infix) => 2,
+                        }
+                    ),
+                    |i| Message::UpdatePrefixStyle(
+                        match i {
+                            0 => None,
+                            1 => Some(PrefixStyle::Plain),
+                            2 => Some(PrefixStyle::Infix),
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Format Like free -h",
+                checkbox("", self.live_config.like_free)
+                    .on_toggle(Message::UpdateLikeFree)
+            ),
+            settings::item(
+                "Display",
+                popup_dropdown(
+                    &DISPLAY_MODE_MENU_ITEMS,
+                    Some(
+                        match self.live_config.display_mode {
+                            DisplayMode::Full => 0,
+                            DisplayMode::IconOnly => 1,
+                            DisplayMode::Dot => 2,
+                            DisplayMode::FreeAndPercent => 3,
+                            DisplayMode::Ratio => 4,
+                        }
+                    ),
+                    |m| Message::UpdateDisplayMode(
+                        match m {
+                            0 => DisplayMode::Full,
+                            1 => DisplayMode::IconOnly,
+                            2 => DisplayMode::Dot,
+                            3 => DisplayMode::FreeAndPercent,
+                            4 => DisplayMode::Ratio,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Label Before Usage",
+                text_input("", &self.live_config.label_prefix)
+                    .on_input(Message::UpdateLabelPrefix),
+            ),
+            settings::item(
+                "Label After Usage",
+                text_input("", &self.live_config.label_suffix)
+                    .on_input(Message::UpdateLabelSuffix),
+            ),
+            settings::item(
+                "Second Metric",
+                popup_dropdown(
+                    &SLOT_METRIC_MENU_ITEMS,
+                    Some(
+                        match self.live_config.secondary_metric.metric {
+                            SlotMetric::Disabled => 0,
+                            SlotMetric::Ram => 1,
+                            SlotMetric::Swap => 2,
+                        }
+                    ),
+                    |i| Message::UpdateSecondaryMetric(
+                        match i {
+                            0 => SlotMetric::Disabled,
+                            1 => SlotMetric::Ram,
+                            2 => SlotMetric::Swap,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Second Metric Shows",
+                popup_dropdown(
+                    &SLOT_STYLE_MENU_ITEMS,
+                    Some(
+                        match self.live_config.secondary_metric.style {
+                            SlotStyle::Amount => 0,
+                            SlotStyle::Percentage => 1,
+                        }
+                    ),
+                    |i| Message::UpdateSecondaryStyle(
+                        match i {
+                            0 => SlotStyle::Amount,
+                            1 => SlotStyle::Percentage,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Separator",
+                text_input("", &self.live_config.metric_separator)
+                    .on_input(Message::UpdateMetricSeparator),
+            ),
+            settings::item(
+                "Text Color",
+                text_input("#rrggbb or accent", &self.text_color_text)
+                    .on_input(Message::UpdateTextColorText)
+                    .on_submit(Message::UpdateTextColor),
+            ),
+            settings::item(
+                "Red Text When Critical",
+                checkbox("", self.live_config.critical_text_color)
+                    .on_toggle(Message::UpdateCriticalTextColor)
+            ),
+            settings::item(
+                "Blink When Critical",
+                checkbox("", self.live_config.critical_blink)
+                    .on_toggle(Message::UpdateCriticalBlink)
+            ),
+            settings::item(
+                "Unit",
+                segmented_control::horizontal(&self.unit_model)
+                    .on_activate(move |e| Message::UpdateUnit(
+                        if e == entity_byte {
+                            Unit::Byte
+                        } else if e == entity_bit {
+                            Unit::Bit
+                        } else {
+                            unreachable!()
+                        }
+                    ))
+            ),
+            settings::item(
+                "Prefix",
+                popup_dropdown(
+                    prefix_menu_items,
+                    Some(
+                        match self.live_config.prefix {
+                            Prefix::Auto => 0,
+                            Prefix::None => 1,
+                            Prefix::Kilo => 2,
+                            Prefix::Mega => 3,
+                            Prefix::Giga => 4,
+                            Prefix::Tera => 5,
+                            Prefix::Peta => 6,
+                            Prefix::Exa => 7,
+                            Prefix::Zeta => 8,
+                            Prefix::Yotta => 9,
+                        }
+                    ).filter(|&i| i < prefix_menu_items.len()),
+                    |p| Message::UpdatePrefix(
+                        match p {
+                            0 => Prefix::Auto,
+                            1 => Prefix::None,
+                            2 => Prefix::Kilo,
+                            3 => Prefix::Mega,
+                            4 => Prefix::Giga,
+                            5 => Prefix::Tera,
+                            6 => Prefix::Peta,
+                            7 => Prefix::Exa,
+                            8 => Prefix::Zeta,
+                            9 => Prefix::Yotta,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Show Advanced Units",
+                checkbox("", self.live_config.advanced_units)
+                    .on_toggle(Message::UpdateAdvancedUnits)
+            ),
+            settings::item(
+                "Smallest Prefix",
+                popup_dropdown(
+                    &SMALLEST_PREFIX_MENU_ITEMS,
+                    self.live_config.smallest_prefix.index()
+                        .or(Some(0))
+                        .filter(|&i| i < SMALLEST_PREFIX_MENU_ITEMS.len()),
+                    |p| Message::UpdateSmallestPrefix(
+                        match p {
+                            0 => Prefix::None,
+                            1 => Prefix::Kilo,
+                            2 => Prefix::Mega,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Custom Prefix Labels",
+                text_input(", K, M, G", &self.prefix_labels_text)
+                    .on_input(Message::UpdatePrefixLabelsText)
+                    .on_submit(Message::UpdatePrefixLabels),
+            ),
+            settings::item(
+                "Promote Auto Prefix At",
+                spin_button(
+                    format!("{}%", self.live_config.auto_hysteresis),
+                    self.live_config.auto_hysteresis,
+                    5,
+                    100,
+                    200,
+                    Message::UpdateAutoHysteresis,
+                ),
+            ),
+            settings::item(
+                "Use Prefix of Total Everywhere",
+                checkbox("", self.live_config.lock_prefix_to_total)
+                    .on_toggle(Message::UpdateLockPrefixToTotal)
+            ),
+            settings::item(
+                "Show Swap With Prefix of RAM",
+                checkbox("", self.live_config.swap_matches_ram)
+                    .on_toggle(Message::UpdateSwapMatchesRam)
+            ),
+            settings::item(
+                "Keep Auto Values Between 1 and 999",
+                checkbox("", self.live_config.auto_nice_values)
+                    .on_toggle(Message::UpdateAutoNiceValues)
+            ),
+            settings::item(
+                "Unit Only on Total",
+                checkbox("", self.live_config.unit_on_total_only)
+                    .on_toggle(Message::UpdateUnitOnTotalOnly)
+            ),
+            settings::item(
+                "Smoothing Mode",
+                popup_dropdown(
+                    &SMOOTHING_MODE_MENU_ITEMS,
+                    Some(
+                        match self.live_config.smoothing_mode {
+                            SmoothingMode::Exponential => 0,
+                            SmoothingMode::MovingAverage => 1,
+                        }
+                    ),
+                    |i| Message::UpdateSmoothingMode(
+                        match i {
+                            0 => SmoothingMode::Exponential,
+                            1 => SmoothingMode::MovingAverage,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Average Over",
+                spin_button(
+                    match self.live_config.moving_average_window {
+                        1 => String::from("1 sample"),
+                        samples => format!("{samples} samples"),
+                    },
+                    self.live_config.moving_average_window,
+                    1,
+                    1,
+                    MAX_MOVING_AVERAGE_WINDOW,
+                    Message::UpdateMovingAverageWindow,
+                ),
+            ),
+            settings::item(
+                "Smoothing",
+                spin_button(
+                    format!("{}%", self.live_config.smoothing_factor),
+                    self.live_config.smoothing_factor,
+                    5,
+                    0,
+                    MAX_SMOOTHING_FACTOR,
+                    Message::UpdateSmoothingFactor,
+                ),
+            ),
+            settings::item(
+                "Precision",
+                spin_button(
+                    format!("{}", self.live_config.precision),
+                    self.live_config.precision,
+                    PRECISION_STEP,
+                    MIN_PRECISION,
+                    MAX_PRECISION,
+                    Message::UpdatePrecision,
+                ),
+            ),
+            settings::item(
+                "Decimal Comma",
+                checkbox("", self.live_config.decimal_comma)
+                    .on_toggle(Message::UpdateDecimalComma)
+            ),
+            settings::item(
+                "More Decimals for Small Values",
+                checkbox("", self.live_config.expand_small_values)
+                    .on_toggle(Message::UpdateExpandSmallValues)
+            ),
+            settings::item(
+                "Rounding",
+                popup_dropdown(
+                    &ROUNDING_MENU_ITEMS,
+                    Some(
+                        match self.live_config.rounding {
+                            Rounding::Nearest => 0,
+                            Rounding::Floor => 1,
+                            Rounding::Ceil => 2,
+                        }
+                    ),
+                    |m| Message::UpdateRounding(
+                        match m {
+                            0 => Rounding::Nearest,
+                            1 => Rounding::Floor,
+                            2 => Rounding::Ceil,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Show Total",
+                checkbox("", self.live_config.show_total)
+                    .on_toggle(Message::UpdateShowTotal)
+            ),
+            settings::item(
+                "Format Total Only When It Changes",
+                checkbox("", self.live_config.static_total)
+                    .on_toggle(Message::UpdateStaticTotal)
+            ),
+            settings::item(
+                "Show Trend",
+                checkbox("", self.live_config.show_trend)
+                    .on_toggle(Message::UpdateShowTrend)
+            ),
+            settings::item(
+                "Ignore Changes Below",
+                spin_button(
+                    format!("{} MiB", self.live_config.trend_epsilon),
+                    self.live_config.trend_epsilon,
+                    8,
+                    0,
+                    4096,
+                    Message::UpdateTrendEpsilon,
+                ),
+            ),
+            settings::item(
+                "Trend Arrow",
+                popup_dropdown(
+                    &TREND_MODE_MENU_ITEMS,
+                    Some(
+                        match self.live_config.trend_mode {
+                            TrendMode::EveryUpdate => 0,
+                            TrendMode::Turns => 1,
+                        }
+                    ),
+                    |i| Message::UpdateTrendMode(
+                        match i {
+                            0 => TrendMode::EveryUpdate,
+                            1 => TrendMode::Turns,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Monitor",
+                popup_dropdown(
+                    &MONITOR_TARGET_MENU_ITEMS,
+                    Some(
+                        match self.live_config.monitor_target {
+                            MonitorTarget::Ram => 0,
+                            MonitorTarget::Swap => 1,
+                            MonitorTarget::Both => 2,
+                        }
+                    ),
+                    |i| Message::UpdateMonitorTarget(
+                        match i {
+                            0 => MonitorTarget::Ram,
+                            1 => MonitorTarget::Swap,
+                            2 => MonitorTarget::Both,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Watch Process",
+                text_input("Name or PID", &self.process_target_text)
+                    .on_input(Message::UpdateProcessTargetText)
+                    .on_submit(Message::UpdateProcessTarget),
+            ),
+            settings::item(
+                "Show RAM+Swap Combined",
+                checkbox("", self.live_config.combined_view)
+                    .on_toggle(Message::UpdateCombinedView)
+            ),
+            settings::item(
+                "Show Free Instead of Used",
+                checkbox("", self.live_config.show_headroom)
+                    .on_toggle(Message::UpdateShowHeadroom)
+            ),
+            settings::item(
+                "Click Usage or Total to Switch",
+                checkbox("", self.live_config.clickable_segments)
+                    .on_toggle(Message::UpdateClickableSegments)
+            ),
+            settings::item(
+                "Monospaced Numbers",
+                checkbox("", self.live_config.monospace_digits)
+                    .on_toggle(Message::UpdateMonospaceDigits)
+            ),
+            settings::item::builder("Count as Used")
+                .description("What the system reports can include caches that are freed on demand; total minus available leaves those out, like the free command")
+                .control(popup_dropdown(
+                    &USED_DEFINITION_MENU_ITEMS,
+                    Some(
+                        match self.live_config.used_definition {
+                            UsedDefinition::Sysinfo => 0,
+                            UsedDefinition::TotalMinusAvailable => 1,
+                        }
+                    ),
+                    |m| Message::UpdateUsedDefinition(
+                        match m {
+                            0 => UsedDefinition::Sysinfo,
+                            1 => UsedDefinition::TotalMinusAvailable,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )),
+            settings::item(
+                "Relative To",
+                popup_dropdown(
+                    &DENOMINATOR_MENU_ITEMS,
+                    Some(
+                        match self.live_config.denominator {
+                            Denominator::Total => 0,
+                            Denominator::UsedAndAvailable => 1,
+                            Denominator::PeakTotal => 2,
+                        }
+                    ),
+                    |m| Message::UpdateDenominator(
+                        match m {
+                            0 => Denominator::Total,
+                            1 => Denominator::UsedAndAvailable,
+                            2 => Denominator::PeakTotal,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Icon",
+                text_input(DEFAULT_ICON_NAME, &self.icon_name_text)
+                    .on_input(Message::UpdateIconNameText)
+                    .on_submit(Message::UpdateIconName),
+            ),
+            settings::item(
+                "Icon Style",
+                popup_dropdown(
+                    &ICON_STYLE_MENU_ITEMS,
+                    Some(
+                        match self.live_config.icon_style {
+                            IconStyle::Symbolic => 0,
+                            IconStyle::FullColor => 1,
+                        }
+                    ),
+                    |i| Message::UpdateIconStyle(
+                        match i {
+                            0 => IconStyle::Symbolic,
+                            1 => IconStyle::FullColor,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            settings::item(
+                "Color Icon by Usage",
+                checkbox("", self.live_config.icon_gradient)
+                    .on_toggle(Message::UpdateIconGradient)
+            ),
+            settings::item(
+                "Click Action",
+                popup_dropdown(
+                    &PRIMARY_ACTION_MENU_ITEMS,
+                    Some(
+                        match self.live_config.primary_action {
+                            PrimaryAction::TogglePopup => 0,
+                            PrimaryAction::LaunchSystemMonitor => 1,
+                            PrimaryAction::CopyUsage => 2,
+                        }
+                    ),
+                    |a| Message::UpdatePrimaryAction(
+                        match a {
+                            0 => PrimaryAction::TogglePopup,
+                            1 => PrimaryAction::LaunchSystemMonitor,
+                            2 => PrimaryAction::CopyUsage,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+            command_item(
+                "System Monitor",
+                self.command_errors.system_monitor.as_ref(),
+                text_input(DEFAULT_SYSTEM_MONITOR_COMMAND, &self.live_config.system_monitor_command)
+                    .on_input(Message::UpdateSystemMonitorCommand),
+            ),
+            settings::item(
+                "Popup Shortcut",
+                text_input("Super+R", &self.popup_shortcut_text)
+                    .on_input(Message::UpdatePopupShortcutText)
+                    .on_submit(Message::UpdatePopupShortcut),
+            ),
+            settings::item(
+                "Log Usage to CSV File",
+                text_input("", &self.usage_log_path_text)
+                    .on_input(Message::UpdateUsageLogPathText)
+                    .on_submit(Message::UpdateUsageLogPath),
+            ),
+            settings::item(
+                "Show Top Processes",
+                checkbox("", self.live_config.show_top_processes)
+                    .on_toggle(Message::UpdateShowTopProcesses)
+            ),
+            settings::item(
+                "Pin Process",
+                cosmic::widget::row::with_children(vec![
+                    Element::from(
+                        text_input("Name", &self.pinned_process_text)
+                            .on_input(Message::UpdatePinnedProcessText)
+                    ),
+                    Element::from(button::standard("Pin").on_press(Message::PinProcess)),
+                ])
+                .spacing(space_s)
+                .align_y(Center),
+            ),
+            settings::item(
+                "Refresh on Open",
+                checkbox("", self.live_config.refresh_on_open)
+                    .on_toggle(Message::UpdateRefreshOnOpen)
+            ),
+            settings::item(
+                "Show Total on Vertical Panels",
+                checkbox("", self.live_config.show_total_vertical)
+                    .on_toggle(Message::UpdateShowTotalVertical)
+            ),
+            settings::item(
+                "Show Icon on Vertical Panels",
+                checkbox("", self.live_config.show_icon_vertical)
+                    .on_toggle(Message::UpdateShowIconVertical)
+            ),
+            settings::item(
+                "Freeze on Hover",
+                checkbox("", self.live_config.freeze_on_hover)
+                    .on_toggle(Message::UpdateFreezeOnHover)
+            ),
+            settings::item(
+                "Gauge Icon",
+                checkbox("", self.live_config.gauge_icon)
+                    .on_toggle(Message::UpdateGaugeIcon)
+            ),
+            settings::item(
+                "Show Swap in Gauge",
+                checkbox("", self.live_config.gauge_swap_ring)
+                    .on_toggle(Message::UpdateGaugeSwapRing)
+            ),
+            settings::item(
+                "Percentage in Gauge",
+                checkbox("", self.live_config.gauge_label)
+                    .on_toggle(Message::UpdateGaugeLabel)
+            ),
+            settings::item(
+                "Round Percentages To",
+                spin_button(
+                    format!("{}%", self.live_config.percent_step),
+                    self.live_config.percent_step,
+                    1,
+                    1,
+                    MAX_PERCENT_STEP,
+                    Message::UpdatePercentStep,
+                ),
+            ),
+            settings::item(
+                "Cap Percentages at 100%",
+                checkbox("", self.live_config.clamp_percent)
+                    .on_toggle(Message::UpdateClampPercent)
+            ),
+            settings::item(
+                "Compare Usage Against",
+                spin_button(
+                    match self.live_config.display_cap_bytes {
+                        Some(cap) => self.format(cap),
+                        None => String::from("Total memory"),
+                    },
+                    self.live_config.display_cap_bytes.map_or(0, |cap| cap >> 30),
+                    1,
+                    0,
+                    MAX_DISPLAY_CAP_GIB,
+                    // Budgets are set in whole GiB, where 0 compares against the total again
+                    |gib: u64| Message::UpdateDisplayCap(Some(gib << 30)),
+                ),
+            ),
+            settings::item(
+                "Reduce Motion",
+                checkbox("", self.live_config.reduced_motion)
+                    .on_toggle(Message::UpdateReducedMotion)
+            ),
+            settings::item(
+                "Tight Layout",
+                checkbox("", self.live_config.tight_layout)
+                    .on_toggle(Message::UpdateTightLayout)
+            ),
+            settings::item(
+                "Tint Background When Usage Is High",
+                checkbox("", self.live_config.tint_background)
+                    .on_toggle(Message::UpdateTintBackground)
+            ),
+            settings::item(
+                "Fit Popup to Contents",
+                checkbox("", self.live_config.fit_popup_width)
+                    .on_toggle(Message::UpdateFitPopupWidth)
+            ),
+            settings::item(
+                "Move Popup Horizontally",
+                spin_button(
+                    format!("{} px", self.live_config.popup_offset_x),
+                    self.live_config.popup_offset_x,
+                    10,
+                    -MAX_POPUP_OFFSET,
+                    MAX_POPUP_OFFSET,
+                    Message::UpdatePopupOffsetX,
+                ),
+            ),
+            settings::item(
+                "Move Popup Vertically",
+                spin_button(
+                    format!("{} px", self.live_config.popup_offset_y),
+                    self.live_config.popup_offset_y,
+                    10,
+                    -MAX_POPUP_OFFSET,
+                    MAX_POPUP_OFFSET,
+                    Message::UpdatePopupOffsetY,
+                ),
+            ),
+            settings::item(
+                "RAM Warning Threshold",
+                spin_button(
+                    format!("{}%", self.live_config.warning_threshold),
+                    self.live_config.warning_threshold,
+                    5,
+                    0,
+                    100,
+                    Message::UpdateWarningThreshold,
+                ),
+            ),
+            settings::item(
+                "RAM Critical Threshold",
+                spin_button(
+                    format!("{}%", self.live_config.critical_threshold),
+                    self.live_config.critical_threshold,
+                    5,
+                    0,
+                    100,
+                    Message::UpdateCriticalThreshold,
+                ),
+            ),
+            settings::item(
+                "Swap Warning Threshold",
+                spin_button(
+                    format!("{}%", swap_thresholds.warning),
+                    swap_thresholds.warning,
+                    5,
+                    0,
+                    100,
+                    move |warning| Message::UpdateSwapThresholds(Thresholds { warning, ..swap_thresholds }),
+                ),
+            ),
+            settings::item(
+                "Swap Critical Threshold",
+                spin_button(
+                    format!("{}%", swap_thresholds.critical),
+                    swap_thresholds.critical,
+                    5,
+                    0,
+                    100,
+                    move |critical| Message::UpdateSwapThresholds(Thresholds { critical, ..swap_thresholds }),
+                ),
+            ),
+            settings::item(
+                "Process Warning Threshold",
+                spin_button(
+                    format!("{}%", process_thresholds.warning),
+                    process_thresholds.warning,
+                    5,
+                    0,
+                    100,
+                    move |warning| Message::UpdateProcessThresholds(Thresholds { warning, ..process_thresholds }),
+                ),
+            ),
+            settings::item(
+                "Process Critical Threshold",
+                spin_button(
+                    format!("{}%", process_thresholds.critical),
+                    process_thresholds.critical,
+                    5,
+                    0,
+                    100,
+                    move |critical| Message::UpdateProcessThresholds(Thresholds { critical, ..process_thresholds }),
+                ),
+            ),
+            command_item(
+                "Run When Critical",
+                self.command_errors.critical_action.as_ref(),
+                text_input("", &self.critical_action_command_text)
+                    .on_input(Message::UpdateCriticalActionCommandText)
+                    .on_submit(Message::UpdateCriticalActionCommand),
+            ),
+            settings::item(
+                "Sound When Critical",
+                checkbox("", self.live_config.critical_sound)
+                    .on_toggle(Message::UpdateCriticalSound)
+            ),
+            settings::item(
+                "Critical Sound File",
+                text_input("System alert sound", &self.critical_sound_file_text)
+                    .on_input(Message::UpdateCriticalSoundFileText)
+                    .on_submit(Message::UpdateCriticalSoundFile),
+            ),
+            settings::item(
+                "Update on Memory Pressure",
+                checkbox("", self.live_config.pressure_events)
+                    .on_toggle(Message::UpdatePressureEvents)
+            ),
+            settings::item(
+                "Show Uptime",
+                checkbox("", self.live_config.show_uptime)
+                    .on_toggle(Message::UpdateShowUptime)
+            ),
+            settings::item(
+                "Describe Usage in Words",
+                checkbox("", self.live_config.usage_in_words)
+                    .on_toggle(Message::UpdateUsageInWords)
+            ),
+            settings::item(
+                "Also in Popup",
+                checkbox("", self.live_config.usage_in_words_popup)
+                    .on_toggle(Message::UpdateUsageInWordsPopup)
+            ),
+            settings::item(
+                "Show History Statistics",
+                checkbox("", self.live_config.show_history_stats)
+                    .on_toggle(Message::UpdateShowHistoryStats)
+            ),
+            settings::item(
+                "Show Allocation Rate Graph",
+                checkbox("", self.live_config.show_rate_graph)
+                    .on_toggle(Message::UpdateShowRateGraph)
+            ),
+            settings::item(
+                "Smooth Allocation Rate Graph",
+                checkbox("", self.live_config.smooth_rate_graph)
+                    .on_toggle(Message::UpdateSmoothRateGraph)
+            ),
+            settings::item(
+                "Break Graph After",
+                spin_button(
+                    match self.live_config.graph_gap_intervals {
+                        0 => String::from("Never"),
+                        intervals => format!("{intervals} intervals"),
+                    },
+                    self.live_config.graph_gap_intervals,
+                    1,
+                    0,
+                    MAX_GRAPH_GAP_INTERVALS,
+                    Message::UpdateGraphGapIntervals,
+                ),
+            ),
+            settings::item(
+                "Show Usage in Both Standards",
+                checkbox("", self.live_config.show_both_standards)
+                    .on_toggle(Message::UpdateShowBothStandards)
+            ),
+            settings::item(
+                "Estimate When Memory Is Full",
+                checkbox("", self.live_config.show_oom_estimate)
+                    .on_toggle(Message::UpdateShowOomEstimate)
+            ),
+            settings::item(
+                "Show Forecast",
+                checkbox("", self.live_config.show_forecast)
+                    .on_toggle(Message::UpdateShowForecast)
+            ),
+            settings::item(
+                "Forecast Ahead",
+                spin_button(
+                    self.format_duration(u64::from(self.live_config.forecast_horizon) * 1000),
+                    self.live_config.forecast_horizon,
+                    5,
+                    MIN_FORECAST_HORIZON,
+                    MAX_FORECAST_HORIZON,
+                    Message::UpdateForecastHorizon,
+                ),
+            ),
+            settings::item(
+                "Show Time Since Last Update",
+                checkbox("", self.live_config.show_last_update)
+                    .on_toggle(Message::UpdateShowLastUpdate)
+            ),
+            settings::item(
+                "Compare With Last Session",
+                checkbox("", self.live_config.compare_last_session)
+                    .on_toggle(Message::UpdateCompareLastSession)
+            ),
+            settings::item(
+                "Show NUMA Nodes",
+                checkbox("", self.live_config.show_numa)
+                    .on_toggle(Message::UpdateShowNuma)
+            ),
+            settings::item(
+                "Show Memory Errors",
+                checkbox("", self.live_config.show_ecc)
+                    .on_toggle(Message::UpdateShowEcc)
+            ),
+            settings::item(
+                "Show Memory Modules",
+                checkbox("", self.live_config.show_modules)
+                    .on_toggle(Message::UpdateShowModules)
+            ),
+            settings::item(
+                "Usage Bar",
+                popup_dropdown(
+                    &USAGE_BAR_MENU_ITEMS,
+                    Some(
+                        match self.live_config.usage_bar {
+                            UsageBar::Hidden => 0,
+                            UsageBar::Used => 1,
+                            UsageBar::Headroom => 2,
+                            UsageBar::Free => 3,
+                        }
+                    ),
+                    |i| Message::UpdateUsageBar(
+                        match i {
+                            0 => UsageBar::Hidden,
+                            1 => UsageBar::Used,
+                            2 => UsageBar::Headroom,
+                            3 => UsageBar::Free,
+                            _ => unreachable!(),
+                        }
+                    ),
+                    self.popup.unwrap_or(window::Id::RESERVED),
+                    Message::Surface,
+                    |a| a,
+                )
+            ),
+        ]
+        .spacing(space_s);
+
+        // Swap notifications would never fire on a system without swap
+        if self.memory.total_swap > 0 {
+            settings_list = settings_list.push(settings::item(
+                "Notify Above Swap Usage",
+                spin_button(
+                    match self.live_config.swap_notify_threshold {
+                        0 => String::from("Off"),
+                        percent => format!("{percent}%"),
+                    },
+                    self.live_config.swap_notify_threshold,
+                    5,
+                    0,
+                    100,
+                    Message::UpdateSwapNotifyThreshold,
+                ),
+            ));
+        }
+
+        settings_list = settings_list.push(settings::item(
+            "Settings File",
+            cosmic::widget::row::with_children(vec![
+                Element::from(text_input("", &self.settings_file_text).on_input(Message::UpdateSettingsFile)),
+                Element::from(button::standard("Export").on_press(Message::ExportSettings)),
+                Element::from(button::standard("Import").on_press(Message::ImportSettings)),
+            ])
+            .spacing(space_s)
+            .align_y(Center),
+        ));
+        settings_list = settings_list.push(settings::item(
+            "Share Settings",
+            cosmic::widget::row::with_children(vec![
+                Element::from(
+                    checkbox("Include Commands", self.snippet_includes_commands)
+                        .on_toggle(Message::UpdateSnippetIncludesCommands)
+                ),
+                Element::from(button::standard("Copy").on_press(Message::CopySettings)),
+            ])
+            .spacing(space_s)
+            .align_y(Center),
+        ));
+        if let Some(status) = &self.settings_file_status {
+            settings_list = settings_list.push(text(status.as_str()));
+        }
+        if let Some(dir) = &self.config_dir {
+            settings_list = settings_list.push(settings::item(
+                "Configuration",
+                cosmic::widget::row::with_children(vec![
+                    Element::from(text(dir.display().to_string())),
+                    Element::from(button::standard("Open Folder").on_press(Message::OpenConfigDir)),
+                ])
+                .spacing(space_s)
+                .align_y(Center),
+            ));
+        }
+
+        content_list = content_list.push(settings_list);
+
+        // Set the widget content list as the popup_container for the applet
+        self.core
+            .applet
+            .popup_container(container(content_list).padding(space_s))
+            .into()
+    }
+}
+
+const PRIMARY_ACTION_MENU_ITEMS: [&str; 3] = [
+    "Toggle Popup",
+    "Open System Monitor",
+    "Copy Usage",
+];
+
+const DISPLAY_MODE_MENU_ITEMS: [&str; 5] = [
+    "Icon and Usage",
+    "Icon Only",
+    "Colored Dot",
+    "Free and Percentage",
+    "Used/Total Without Units",
+];
+
+const PREFIX_BASE_MENU_ITEMS: [&str; 3] = [
+    "Like Standard",
+    "1000",
+    "1024",
+];
+
+const PREFIX_STYLE_MENU_ITEMS: [&str; 3] = [
+    "Like Standard",
+    "KB, MB, GB",
+    "KiB, MiB, GiB",
+];
+
+const SMOOTHING_MODE_MENU_ITEMS: [&str; 2] = [
+    "Exponential",
+    "Moving Average",
+];
+
+const SLOT_METRIC_MENU_ITEMS: [&str; 3] = [
+    "None",
+    "RAM",
+    "Swap",
+];
+
+const SLOT_STYLE_MENU_ITEMS: [&str; 2] = [
+    "Amount",
+    "Percentage",
+];
+
+const ICON_STYLE_MENU_ITEMS: [&str; 2] = [
+    "Symbolic",
+    "Full Color",
+];
+
+const USAGE_BAR_MENU_ITEMS: [&str; 4] = [
+    "Hidden",
+    "Used",
+    "Headroom",
+    "Free",
+];
+
+const TREND_MODE_MENU_ITEMS: [&str; 2] = [
+    "Every Update",
+    "Turning Points",
+];
+
+const MISSED_TICKS_MENU_ITEMS: [&str; 3] = [
+    "Skip",
+    "Delay",
+    "Catch Up",
+];
+
+const MONITOR_TARGET_MENU_ITEMS: [&str; 3] = [
+    "RAM",
+    "Swap",
+    "RAM and Swap",
+];
+
+const USED_DEFINITION_MENU_ITEMS: [&str; 2] = [
+    "Reported by System",
+    "Total Minus Available",
+];
+
+const DENOMINATOR_MENU_ITEMS: [&str; 3] = [
+    "Total",
+    "Used and Available",
+    "Highest Total Seen",
+];
+
+const ROUNDING_MENU_ITEMS: [&str; 3] = [
+    "Nearest",
+    "Down",
+    "Up",
+];
+
+/// Counts below a kilobyte are only worth showing in a larger unit up to megabytes.
+const SMALLEST_PREFIX_MENU_ITEMS: [&str; 3] = [
+    "None",
+    "Kilo",
+    "Mega",
+];
+
+/// The entries of the prefix dropdown, in the order of [`Prefix`].
+///
+/// This is a static, rather than a constant, so that the dropdown can borrow part of it.
+static PREFIX_MENU_ITEMS: [&str; 10] = [
+    "Auto",
+    "None",
+    "Kilo",
+    "Mega",
+    "Giga",
+    "Tera",
+    "Peta",
+    "Exa",
+    "Zeta",
+    "Yotta",
+];
+
+/// How many entries of [`PREFIX_MENU_ITEMS`] are offered unless the user asks for advanced units.
+const BASIC_PREFIX_MENU_ITEMS: usize = 6;
+
+/// A preset for both the base and the labels of the prefixes.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum Standard {
+    #[default]
+    Si,
+    Iec,
+}
+
+impl Standard {
+
+    fn base(self) -> Base {
+        match self {
+            Standard::Si => Base::Decimal,
+            Standard::Iec => Base::Binary,
+        }
+    }
+
+    fn prefix_style(self) -> PrefixStyle {
+        match self {
+            Standard::Si => PrefixStyle::Plain,
+            Standard::Iec => PrefixStyle::Infix,
+        }
+    }
+
+}
+
+/// What each prefix multiplies by the previous one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum Base {
+    /// 1000, as in SI
+    Decimal,
+    /// 1024, as in IEC, and as many older tools use with SI labels
+    Binary,
+}
+
+/// How prefixes are labeled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum PrefixStyle {
+    /// Such as `MB`, as in SI
+    Plain,
+    /// Such as `MiB`, as in IEC
+    Infix,
+}
+
+/// How the displayed usage is smoothed.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum SmoothingMode {
+    /// Retain a percentage of the previously displayed usage on every tick
+    #[default]
+    Exponential,
+    /// Display the mean of the last few samples
+    MovingAverage,
+}
+
+/// What the timer does about ticks that it missed because the applet was busy.
+///
+/// This doesn't matter when ticks are aligned to the clock, since the timer is then started anew
+/// on every tick.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum MissedTicks {
+    /// Tick once right away, and then keep to the original schedule
+    #[default]
+    Skip,
+    /// Tick once right away, and then every interval from that moment on
+    Delay,
+    /// Tick once for every missed tick in quick succession
+    Burst,
+}
+
+impl MissedTicks {
+
+    fn behavior(self) -> time::MissedTickBehavior {
+        match self {
+            MissedTicks::Skip => time::MissedTickBehavior::Skip,
+            MissedTicks::Delay => time::MissedTickBehavior::Delay,
+            MissedTicks::Burst => time::MissedTickBehavior::Burst,
+        }
+    }
+
+}
+
+/// Everything the time subscription needs to know to set up its timer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct TickSettings {
+    /// The update interval in milliseconds
+    msec: u64,
+    missed: MissedTicks,
+}
+
+/// How much usage history is kept.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+enum HistoryWindow {
+    /// A fixed amount of samples
+    Samples(u32),
+    /// As many samples as are taken in the given amount of minutes
+    Minutes(u32),
+}
+
+impl Default for HistoryWindow {
+    fn default() -> Self {
+        HistoryWindow::Samples(HISTORY_CAPACITY as u32)
+    }
+}
+
+impl HistoryWindow {
+
+    /// The amount of samples in this window when sampling every `msec` milliseconds.
+    fn capacity(self, msec: u64) -> usize {
+        let samples = match self {
+            HistoryWindow::Samples(samples) => u64::from(samples),
+            HistoryWindow::Minutes(minutes) => u64::from(minutes) * 60_000 / msec.max(1),
+        };
+        samples.clamp(1, MAX_HISTORY_CAPACITY as u64) as usize
+    }
+
+    /// How old, in milliseconds, samples in this window may get, if the window is a duration.
+    ///
+    /// The capacity alone doesn't suffice for a duration, since samples that were taken at an
+    /// earlier update interval cover a different amount of time.
+    fn max_age(self) -> Option<u64> {
+        match self {
+            HistoryWindow::Samples(_) => None,
+            HistoryWindow::Minutes(minutes) => Some(u64::from(minutes) * 60_000),
+        }
+    }
+
+}
+
+/// Where memory statistics are read from.
+#[derive(Default, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+enum Source {
+    /// The machine the applet runs on
+    #[default]
+    Local,
+    /// The output of a command that prints `/proc/meminfo`, such as `ssh host cat /proc/meminfo`
+    ///
+    /// This only covers the memory statistics; the other popup rows and `--dump-status` still
+    /// describe the local machine.
+    Remote { command: String },
+    /// The memory charged to the cgroup of the user's session
+    Session,
+    /// The machine the applet runs on, read from `/proc/meminfo` instead of through sysinfo
+    ProcMeminfo,
+}
+
+/// Creates the source that the metrics are read from.
+///
+/// Local sources are sampled on a background thread, so that a hung `/proc` stalls the metrics
+/// rather than the applet, and the watchdog can still notice. Commands already run in the
+/// background.
+fn create_source(source: &Source, interval: u64) -> Box<dyn MetricsSource> {
+    match source {
+        Source::Local => Box::new(BackgroundSource::spawn(Box::new(SysinfoSource::default()))),
+        Source::Remote { command } => Box::new(CommandSource::spawn(command.clone(), interval)),
+        Source::Session => Box::new(BackgroundSource::spawn(Box::new(SessionSource::detect()))),
+        Source::ProcMeminfo => Box::new(BackgroundSource::spawn(Box::new(ProcMeminfoSource::default()))),
+    }
+}
+
+/// A metric, and how it is shown, in a place on the panel that can hold one.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+struct MetricSlot {
+    metric: SlotMetric,
+    style: SlotStyle,
+    /// The prefix of amounts, if it should differ from the one of the usage
+    prefix: Option<Prefix>,
+}
+
+/// What a metric slot measures.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum SlotMetric {
+    /// The slot is left out entirely
+    #[default]
+    Disabled,
+    Ram,
+    Swap,
+}
+
+/// How a metric slot shows its metric.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum SlotStyle {
+    #[default]
+    Amount,
+    /// The percentage of the total that is in use
+    Percentage,
+}
+
+/// Which kind of icon is shown on the panel, unless it is replaced by the gauge or the dot.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum IconStyle {
+    /// The icon named in the configuration, recolored to match the panel
+    #[default]
+    Symbolic,
+    /// The applet's own icon, in its own colors
+    FullColor,
+}
+
+/// Which bar is shown in the popup.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum UsageBar {
+    #[default]
+    Hidden,
+    /// Filled from the left by the used memory
+    Used,
+    /// Filled from the right by the free memory
+    Headroom,
+    /// Filled from the left by the free memory, so that the bar shrinks as memory runs out
+    Free,
+}
+
+/// The percentages of use from which a metric is considered high and critical.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+struct Thresholds {
+    warning: u32,
+    critical: u32,
+}
+
+impl Thresholds {
+
+    /// Clamp both thresholds to 100%, and lower a warning threshold above the critical one to it.
+    fn validated(self) -> Self {
+        let critical = self.critical.min(100);
+        Self {
+            warning: self.warning.min(critical),
+            critical,
+        }
+    }
+
+}
+
+/// The metrics that each have their own thresholds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ThresholdMetric {
+    Ram,
+    Swap,
+    Process,
+}
+
+/// How alarming the amount of memory in use is, according to the user's thresholds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+enum UsageBand {
+    Normal,
+    Warning,
+    Critical,
+}
+
+impl UsageBand {
+
+    /// The color that the background of the panel button is tinted with in this band, if any.
+    ///
+    /// Normal usage leaves the panel alone. The tint is only as opaque as the panel's own
+    /// background allows, so that it blends in with a translucent panel.
+    fn tint(self, theme: &cosmic::Theme) -> Option<Color> {
+        if self == UsageBand::Normal {
+            return None
+        }
+        let mut color = self.color(theme);
+        color.a = BACKGROUND_TINT_OPACITY * theme.cosmic().background.base.alpha;
+        Some(color)
+    }
+
+    fn classify(percent: f32, thresholds: Thresholds) -> Self {
+        if percent >= thresholds.critical as f32 {
+            UsageBand::Critical
+        } else if percent >= thresholds.warning as f32 {
+            UsageBand::Warning
+        } else {
+            UsageBand::Normal
+        }
+    }
+
+    /// Classify by the percentage of memory that is free instead, which crosses each threshold
+    /// from the other side: with a warning threshold of 70%, less than 30% free is a warning.
+    fn classify_free(percent: f32, thresholds: Thresholds) -> Self {
+        Self::classify(100.0 - percent, thresholds)
+    }
+
+    /// The color of the current theme that represents this band.
+    fn color(self, theme: &cosmic::Theme) -> Color {
+        let cosmic = theme.cosmic();
+        match self {
+            UsageBand::Normal => cosmic.success_color(),
+            UsageBand::Warning => cosmic.warning_color(),
+            UsageBand::Critical => cosmic.destructive_color(),
+        }.into()
+    }
+
+}
+
+/// A setting in the popup that can be changed with the keyboard once it was changed with the mouse.
+///
+/// libcosmic's spin buttons can't take focus, so the keys go to the setting that was changed last
+/// instead of to the focused one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SteppedSetting {
+    Precision,
+    UpdateInterval,
+}
+
+/// What happens when the panel button is clicked with the primary mouse button.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum PrimaryAction {
+    #[default]
+    TogglePopup,
+    LaunchSystemMonitor,
+    CopyUsage,
+}
+
+/// Which way memory usage is heading.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+impl Trend {
+
+    /// Compare two samples, treating changes smaller than `epsilon` bytes as no change at all.
+    fn between(before: u64, after: u64, epsilon: u64) -> Self {
+        if after > before && after - before >= epsilon.max(1) {
+            Trend::Rising
+        } else if before > after && before - after >= epsilon.max(1) {
+            Trend::Falling
+        } else {
+            Trend::Steady
+        }
+    }
+
+    /// The arrow that is shown next to the usage, if any.
+    fn arrow(self) -> Option<&'static str> {
+        match self {
+            Trend::Rising => Some("↑"),
+            Trend::Falling => Some("↓"),
+            Trend::Steady => None,
+        }
+    }
+
+}
+
+/// When the trend arrow changes direction.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum TrendMode {
+    /// Show which way the usage went since the previous tick
+    #[default]
+    EveryUpdate,
+    /// Keep pointing the same way until the usage turned around for good
+    Turns,
+}
+
+/// Follows the direction of the usage from tick to tick, and only gives up on a direction once
+/// the usage went the other way for [`TURN_CONFIRM_TICKS`] ticks.
+///
+/// Ticks without a significant change neither confirm nor cancel a turn, so that noise around a
+/// turning point doesn't delay it forever.
+#[derive(Clone, Copy, Debug)]
+struct TurnTracker {
+    /// The direction that was last confirmed, which is steady until the usage first moves
+    confirmed: Trend,
+    /// The opposite direction that the usage went in lately, and for how many ticks
+    pending: Option<(Trend, u32)>,
+}
+
+impl Default for TurnTracker {
+    fn default() -> Self {
+        Self {
+            confirmed: Trend::Steady,
+            pending: None,
+        }
+    }
+}
+
+impl TurnTracker {
+
+    /// Take the direction of the latest tick into account.
+    fn update(&mut self, trend: Trend) {
+        if trend == Trend::Steady {
+            return
+        }
+        if trend == self.confirmed {
+            self.pending = None;
+            return
+        }
+        let count = match self.pending {
+            Some((pending, count)) if pending == trend => count + 1,
+            _ => 1,
+        };
+        if count >= TURN_CONFIRM_TICKS {
+            self.confirmed = trend;
+            self.pending = None;
+        } else {
+            self.pending = Some((trend, count));
+        }
+    }
+
+}
+
+/// What the applet monitors on the panel, in the tooltip, in the icon and for the thresholds.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum MonitorTarget {
+    #[default]
+    Ram,
+    Swap,
+    /// RAM, with the swap usage following it on the panel
+    Both,
+}
+
+/// What memory counts as used.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum UsedDefinition {
+    /// Whatever the metrics source reports as used
+    #[default]
+    Sysinfo,
+    /// Everything that isn't available for new allocations, which is what `free` reports
+    TotalMinusAvailable,
+}
+
+/// What the used memory is displayed relative to, in the panel, the tooltip and the gauge.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum Denominator {
+    /// All memory installed in the system
+    #[default]
+    Total,
+    /// The memory that is in use plus the memory that is available for new allocations, which
+    /// leaves out what the kernel keeps for itself
+    UsedAndAvailable,
+    /// The largest total seen since the source was created, which stays put when a virtual
+    /// machine's memory is ballooned up and down
+    PeakTotal,
+}
+
+impl Denominator {
+
+    /// The amount of memory that usage is compared against in the given sample, given the
+    /// largest total seen so far.
+    fn of(self, memory: &MemorySample, peak_total: u64) -> u64 {
+        match self {
+            Denominator::Total => memory.total,
+            Denominator::UsedAndAvailable => memory.used.saturating_add(memory.available),
+            Denominator::PeakTotal => memory.total.max(peak_total),
+        }
+    }
+
+}
+
+/// What is rendered inside the panel button.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum DisplayMode {
+    /// The icon followed by the used (and optionally the total) amount of memory
+    #[default]
+    Full,
+    /// Only the icon; the usage can still be read from the tooltip and the popup
+    IconOnly,
+    /// A small dot that is colored by the usage band, in place of the icon
+    Dot,
+    /// The icon followed by the free memory and the percentage in use, as in `8.2 GiB free · 49%`
+    FreeAndPercent,
+    /// The icon followed by the used and the total memory without units, as in `8.2/16`
+    Ratio,
+}
+
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+enum Unit {
+    #[default]
+    Byte,
+    Bit,
+}
+
+/// How far off a whole value may be, relative to it, for [`Rounding::apply`] to take it as whole.
+///
+/// This covers the last bits that scaling gets wrong, while a single byte would still make a
+/// difference to all but the largest counts.
+const ROUNDING_EPSILON: f64 = 4.0 * f64::EPSILON;
+
+/// How a byte count is rounded to the configured amount of decimals.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+enum Rounding {
+    /// Round to the nearest value, with halfway values rounded away from zero
+    #[default]
+    Nearest,
+    /// Round down, so that usage is never overstated
+    Floor,
+    /// Round up, so that usage is never understated
+    Ceil,
+}
+
+impl Rounding {
+
+    /// Round `value` to `precision` decimals.
+    fn apply(self, value: f64, precision: u32) -> f64 {
+        let scale = 10f64.powi(precision as i32);
+        let mut scaled = value * scale;
+        // Scaling leaves errors in the last bits, as 1100 B in KB scales to 11.000000000000002 at
+        // one decimal; those must not make a whole value round up or down
+        let nearest = scaled.round();
+        if (scaled - nearest).abs() <= ROUNDING_EPSILON * nearest.abs().max(1.0) {
+            scaled = nearest;
+        }
+        let rounded = match self {
+            Rounding::Nearest => scaled.round(),
+            Rounding::Floor => scaled.floor(),
+            Rounding::Ceil => scaled.ceil(),
+        };
+        rounded / scale
+    }
+
+}
+
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
+enum Prefix {
+    #[default]
+    Auto,
+    None,
+    Kilo,
+    Mega,
+    Giga,
+    Tera,
+    Peta,
+    Exa,
+    Zeta,
+    Yotta,
+}
+
+impl Prefix {
+
+    /// The position of the prefix in [`PREFIXES`], or `None` for [`Prefix::Auto`].
+    fn index(self) -> Option<usize> {
+        match self {
+            Prefix::Auto => None,
+            Prefix::None => Some(0),
+            Prefix::Kilo => Some(1),
+            Prefix::Mega => Some(2),
+            Prefix::Giga => Some(3),
+            Prefix::Tera => Some(4),
+            Prefix::Peta => Some(5),
+            Prefix::Exa => Some(6),
+            Prefix::Zeta => Some(7),
+            Prefix::Yotta => Some(8),
+        }
+    }
+
+}
+
+const PREFIXES: [&str; 9] = [
+    "",
+    "K",
+    "M",
+    "G",
+    "T",
+    "P",
+    "E",
+    "Z",
+    "Y"
+];
+
+/// Everything that determines how a byte count is turned into text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ByteFormat<'a> {
+    base: Base,
+    prefix_style: PrefixStyle,
+    prefix: Prefix,
+    /// The smallest prefix that is used, whether a smaller one was configured or picked by
+    /// `Prefix::Auto`
+    smallest_prefix: Prefix,
+    /// Labels that replace the entries of [`PREFIXES`] at the same position, unless empty
+    prefix_labels: &'a [String],
+    precision: u32,
+    /// Separate the decimals with a comma instead of a point
+    decimal_comma: bool,
+    /// Raise the precision, up to [`MAX_PRECISION`], until a non-zero count no longer rounds to zero
+    expand_small_values: bool,
+    rounding: Rounding,
+    unit: Unit,
+    /// How far past a prefix boundary a value must be, in percent, before `Prefix::Auto`
+    /// promotes it to the next prefix. 100 promotes as soon as the boundary is reached.
+    auto_hysteresis: u32,
+    /// The count from which `Prefix::Auto` picks the prefix, if not the count being formatted.
+    ///
+    /// Formatting several values with the same reference makes them all share one prefix.
+    auto_reference: Option<u64>,
+    /// Move away from the prefix that `Prefix::Auto` picked while the value would have an integer
+    /// part of 0, or of 1000 or more if that was due to `auto_reference`
+    auto_nice: bool,
+    /// Ignore every other option and format like `free -h` does
+    like_free: bool,
+    /// Leave out the space between the number and the unit
+    compact: bool,
+}
+
+impl<'a> ByteFormat<'a> {
+
+    fn from_config(config: &'a CosmicAppletRamConfig) -> Self {
+        Self {
+            base: config.prefix_base.unwrap_or(config.standard.base()),
+            prefix_style: config.prefix_style.unwrap_or(config.standard.prefix_style()),
+            prefix: config.prefix,
+            smallest_prefix: config.smallest_prefix,
+            prefix_labels: &config.prefix_labels,
+            precision: config.precision,
+            decimal_comma: config.decimal_comma,
+            expand_small_values: config.expand_small_values,
+            rounding: config.rounding,
+            unit: config.unit,
+            auto_hysteresis: config.auto_hysteresis,
+            auto_reference: None,
+            auto_nice: config.auto_nice_values,
+            like_free: config.like_free,
+            compact: false,
+        }
+    }
+
+    /// Use both the base and the labels of the given standard.
+    fn set_standard(&mut self, standard: Standard) {
+        self.base = standard.base();
+        self.prefix_style = standard.prefix_style();
+        // `free -h` only knows one standard
+        self.like_free = false;
+    }
+
+}
+
+fn format_bytes(count: u64, format: &ByteFormat) -> String {
+    let (number, unit) = format_bytes_parts(count, format);
+    let space = if format.compact || format.like_free { "" } else { " " };
+    format!("{number}{space}{unit}")
+}
+
+/// Format a byte count into its number and its unit the way `free -h` does, such as `15` and `Gi`.
+///
+/// Like procps, this picks the smallest IEC prefix for which the whole, including the unit, fits
+/// in four characters for bytes or five otherwise. A decimal is only kept when it fits too, so
+/// that it only appears below 10. Decimals are rounded, but whole numbers are truncated.
+fn format_like_free(count: u64) -> (String, String) {
+    if count.to_string().len() < 4 {
+        return (count.to_string(), String::from("B"))
+    }
+    let units = ["Ki", "Mi", "Gi", "Ti", "Pi"];
+    for (i, unit) in units.iter().enumerate() {
+        let value = count as f64 / 1024f64.powi(i as i32 + 1);
+        let decimal = format!("{value:.1}");
+        if decimal.len() + unit.len() <= 5 {
+            return (decimal, String::from(*unit))
+        }
+        let whole = (value as u64).to_string();
+        if whole.len() + unit.len() <= 5 {
+            return (whole, String::from(*unit))
+        }
+    }
+    // Past what procps knows about, keep counting in the largest unit
+    (format!("{:.0}", count as f64 / 1024f64.powi(units.len() as i32)), String::from("Pi"))
+}
+
+/// Format a byte count into its number and its unit, such as `8.2` and `GiB`.
+fn format_bytes_parts(count: u64, format: &ByteFormat) -> (String, String) {
+    if format.like_free {
+        let (number, unit) = format_like_free(count);
+        return (with_decimal_separator(number, format), unit)
+    }
+    let (factor, symbol) = match format.unit {
+        Unit::Byte => (1, "B"),
+        Unit::Bit => (8, "bit"),
+    };
+    // Count in a wider type, since the largest byte counts don't fit in a u64 as bits
+    let count = u128::from(count) * factor;
+    let k = match format.base {
+        Base::Decimal => 1000.0,
+        Base::Binary => 1024.0,
+    };
+    let infix = match format.prefix_style {
+        PrefixStyle::Plain => "",
+        PrefixStyle::Infix => "i",
+    };
+    let i = match format.prefix.index() {
+        None => {
+            // Never promote below the boundary itself, whatever is in the config
+            let threshold = k * (format.auto_hysteresis.max(100) as f64 / 100.0);
+            let reference = format.auto_reference.map_or(count, |reference| u128::from(reference) * factor);
+            let mut x = reference as f64;
+            let mut i = 0;
+            while i + 1 < PREFIXES.len() && x >= threshold {
+                x /= k;
+                i += 1;
+            }
+            if format.auto_nice && count > 0 {
+                let value = |i: usize| count as f64 / k.powi(i as i32);
+                while i > 0 && value(i) < 1.0 {
+                    i -= 1;
+                }
+                // Without a reference the hysteresis decided against promoting, on purpose
+                while format.auto_reference.is_some() && i + 1 < PREFIXES.len() && value(i) >= 1000.0 && value(i + 1) >= 1.0 {
+                    i += 1;
+                }
+            }
+            i
+        },
+        Some(i) => i,
+    };
+    let i = i.max(format.smallest_prefix.index().unwrap_or(0));
+    if i == 0 {
+        return (count.to_string(), String::from(symbol))
+    }
+    let f = (count as f64) / k.powi(i as i32);
+    let mut precision = format.precision;
+    if format.expand_small_values && count > 0 {
+        while precision < MAX_PRECISION && (f * 10f64.powi(precision as i32)).round() == 0.0 {
+            precision += 1;
+        }
+    }
+    // Round explicitly, since formatting alone rounds halfway values to even
+    let f = format.rounding.apply(f, precision);
+    let prefix_str = format.prefix_labels.get(i)
+        .map(String::as_str)
+        .filter(|label| !label.is_empty())
+        .unwrap_or(PREFIXES[i]);
+    (
+        with_decimal_separator(format!("{f:.prec$}", prec = precision as usize), format),
+        format!("{prefix_str}{infix}{symbol}"),
+    )
+}
+
+/// Swap the decimal point of a formatted number for a comma if the format asks for one.
+///
+/// The number is fresh from `format!`, so a point can only be the decimal one; units never pass
+/// through here.
+fn with_decimal_separator(number: String, format: &ByteFormat) -> String {
+    if format.decimal_comma {
+        number.replace('.', ",")
+    } else {
+        number
+    }
+}
+
+/// The memory used by a single process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ProcessUsage {
+    pid: Pid,
+    name: String,
+    memory: u64,
+}
+
+/// The used memory at a moment that the user marked.
+#[derive(Clone, Copy, Debug)]
+struct Marker {
+    used: u64,
+    at: time::Instant,
+}
+
+impl Marker {
+
+    fn now(used: u64) -> Self {
+        Self {
+            used,
+            at: time::Instant::now(),
+        }
+    }
+
+}
+
+/// What a piece of text on the panel shows, as far as clicking it and its font are concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Segment {
+    Plain,
+    /// A number that can't be clicked, such as the swap usage
+    Number,
+    /// The used or free memory
+    Used,
+    /// The total that usage is relative to
+    Total,
+}
+
+/// How the contents of the panel button are arranged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PanelLayout {
+    /// Lay out the contents in a row rather than in a column
+    horizontal: bool,
+    show_total: bool,
+    show_icon: bool,
+    /// Format the usage with fewer decimals and without spaces, because the panel is thin
+    compact: bool,
+}
+
+/// Decide how to lay out the panel button on a panel with the given anchor, whose suggested icon
+/// size is `icon_size`.
+///
+/// Every anchor-specific layout preference should be applied here, so that `view` doesn't need to
+/// know about them. The config holds what the user prefers; on a thin panel, this falls back to a
+/// compact readout without the total, regardless of the config.
+fn effective_layout(anchor: PanelAnchor, icon_size: u16, config: &CosmicAppletRamConfig) -> PanelLayout {
+    let horizontal = matches!(anchor, PanelAnchor::Top | PanelAnchor::Bottom);
+    let compact = icon_size <= COMPACT_ICON_SIZE;
+    PanelLayout {
+        horizontal,
+        show_total: !compact && config.show_total && (horizontal || config.show_total_vertical),
+        show_icon: horizontal || config.show_icon_vertical,
+        compact,
+    }
+}
+
+/// The padding around the icon on the panel, given the padding the panel suggests.
+///
+/// A tight layout leaves out the padding altogether.
+fn icon_padding<P: Default>(suggested: P, tight: bool) -> P {
+    if tight {
+        P::default()
+    } else {
+        suggested
+    }
+}
+
+/// Create a timer that ticks every update interval, starting one period from now, and that makes
+/// up for missed ticks as configured.
+///
+/// When aligned, the first tick is instead moved to the next multiple of the period since the Unix
+/// epoch, so that applets with the same interval update in unison.
+fn new_timer(settings: TickSettings, align: bool) -> time::Interval {
+    let msec = settings.msec;
+    let period = time::Duration::from_millis(msec);
+    let mut delay = period;
+    // A clock that is set before 1970 has nothing sensible to align to
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .filter(|_| align);
+    if let Some(since_epoch) = since_epoch {
+        let phase = (since_epoch.as_millis() % u128::from(msec.max(1))) as u64;
+        delay = time::Duration::from_millis(msec - phase);
+    }
+    let mut timer = time::interval_at(time::Instant::now() + delay, period);
+    timer.set_missed_tick_behavior(settings.missed.behavior());
+    timer
+}
+
+/// Send a tick every update interval until the sender of `settings_watch` is dropped together with
+/// the applet, after which the stream completes.
+fn tick_stream(mut settings_watch: watch::Receiver<TickSettings>, align: bool) -> impl Stream<Item = Message> {
+    stream::channel(1, |mut output| async move {
+        // Mark this receiver's state as changed so that it always receives an initial
+        // update during the loop below
+        // This allows us to avoid duplicating code from the loop
+        settings_watch.mark_changed();
+        let mut settings = TickSettings {
+            msec: DEFAULT_UPDATE_INTERVAL,
+            missed: MissedTicks::default(),
+        };
+        let mut timer = new_timer(settings, align);
+
+        loop {
+            tokio::select! {
+                _ = timer.tick() => {
+                    output.send(Message::Tick).await.log("Failed sending tick request to applet");
+                    // The timer runs on a monotonic clock, so align it again on every
+                    // tick in case the wall clock jumped
+                    if align {
+                        timer = new_timer(settings, true);
+                    }
+                },
+                // Update the timer if the user changes the interval or the missed tick
+                // behavior
+                changed = settings_watch.changed() => {
+                    // The sender is dropped together with the applet, which is our cue
+                    // to stop ticking and let the stream complete
+                    if changed.is_err() {
+                        break
+                    }
+                    settings = *settings_watch.borrow_and_update();
+                    timer = new_timer(settings, align);
+                }
+            }
+        }
+    })
+}
+
+/// Why a command could not be run, as found before running it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum CommandError {
+    Empty,
+    /// The program is neither a path to a file nor found on `PATH`
+    NotFound(String),
+    /// The program exists, but can't be executed
+    NotExecutable(String),
+}
+
+impl std::fmt::Display for CommandError {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Empty => write!(f, "No command set"),
+            CommandError::NotFound(program) => write!(f, "{program} was not found"),
+            CommandError::NotExecutable(program) => write!(f, "{program} is not executable"),
+        }
+    }
+
+}
+
+/// What is wrong with each command in the configuration, if anything.
+#[derive(Clone, Debug, Default)]
+struct CommandErrors {
+    system_monitor: Option<CommandError>,
+    /// An empty critical action is fine, since it disables the action
+    critical_action: Option<CommandError>,
+    remote: Option<CommandError>,
+}
+
+impl CommandErrors {
+
+    fn check(config: &CosmicAppletRamConfig) -> Self {
+        let critical_action = &config.critical_action_command;
+        Self {
+            system_monitor: validate_command(&config.system_monitor_command).err(),
+            critical_action: validate_command(critical_action)
+                .err()
+                .filter(|_| !critical_action.trim().is_empty()),
+            remote: match &config.source {
+                Source::Remote { command } => validate_command(command).err(),
+                _ => None,
+            },
+        }
+    }
+
+}
+
+/// Check that a command, split the same way as by [`spawn_command`], names a program that can be
+/// run, without running it.
+///
+/// A program with a slash in it is taken as a path; any other program is looked up on `PATH`.
+fn validate_command(command: &str) -> Result<(), CommandError> {
+    use std::os::unix::fs::PermissionsExt;
+    let program = command.split_whitespace().next().ok_or(CommandError::Empty)?;
+    let candidates: Vec<PathBuf> = if program.contains('/') {
+        vec![PathBuf::from(program)]
+    } else {
+        std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).map(|dir| dir.join(program)).collect())
+            .unwrap_or_default()
+    };
+    let mut found = false;
+    for candidate in candidates {
+        let Ok(metadata) = std::fs::metadata(&candidate) else {
+            continue
+        };
+        if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 {
+            return Ok(())
+        }
+        found = true;
+    }
+    Err(if found {
+        CommandError::NotExecutable(program.to_string())
+    } else {
+        CommandError::NotFound(program.to_string())
+    })
+}
+
+/// A setting that holds a command, described by what is wrong with the command if anything.
+fn command_item<'a>(
+    label: &'a str,
+    error: Option<&CommandError>,
+    control: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+    let item = settings::item::builder(label);
+    match error {
+        Some(error) => item.description(error.to_string()).control(control).into(),
+        None => item.control(control).into(),
+    }
+}
+
+/// The width of the popup, in logical pixels, that fits the widest of the given rows.
+///
+/// Text isn't measured, but estimated from the amount of characters, which is close enough with
+/// the proportional interface font. The width stays between the usual bounds of the popup and
+/// [`POPUP_FIT_MAX_WIDTH`]. The width of the output isn't taken into account, so on a very narrow
+/// output the compositor may still have to squeeze the popup.
+fn fit_popup_width(rows: &[(String, String)]) -> f32 {
+    let widest = rows.iter()
+        .map(|(label, value)| label.chars().count() + value.chars().count())
+        .max()
+        .unwrap_or(0);
+    (widest as f32 * POPUP_CHAR_WIDTH + POPUP_ROW_CHROME).clamp(POPUP_MIN_WIDTH, POPUP_FIT_MAX_WIDTH)
+}
+
+/// Start a command given as a program followed by its arguments, separated by whitespace.
+///
+/// The command is not waited on, but it is reaped in the background once it exits.
+fn spawn_command(command: &str) -> std::io::Result<()> {
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "no command configured"))
+    };
+    let mut child = std::process::Command::new(program)
+        .args(words)
+        .spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// What is wrong with a memory sample that no working system would produce, if anything.
+fn health_problem(memory: &MemorySample) -> Option<String> {
+    if memory.total == 0 {
+        Some(String::from("total memory is zero"))
+    } else if memory.used > memory.total {
+        Some(format!("{} bytes used out of {} in total", memory.used, memory.total))
+    } else {
+        None
+    }
+}
+
+/// Open a file or directory with the default application for it, such as the file manager.
+///
+/// xdg-open only tells whether it found an application once it exits, so that is logged from the
+/// background.
+fn open_path(path: &Path) -> std::io::Result<()> {
+    let mut child = std::process::Command::new("xdg-open")
+        .arg(path)
+        .spawn()?;
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        child.wait()
+            .and_then(|status| exit_result("xdg-open", status))
+            .log(format!("Failed to open {}", path.display()));
+    });
+    Ok(())
+}
+
+/// Turn the exit status of a program into an error unless it succeeded.
+fn exit_result(program: &str, status: std::process::ExitStatus) -> std::io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("{program} {status}")))
+    }
+}
+
+/// The directory that cosmic-config keeps the user's configuration of this applet in.
+///
+/// The `Config` handle doesn't expose it, so this follows the same layout, under
+/// `$XDG_CONFIG_HOME/cosmic`.
+fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("cosmic").join(ID).join(format!("v{VERSION}")))
+}
+
+/// Whether settings were saved by a release from before the first-run introduction, which left
+/// out the flag that hides it.
+///
+/// Every setting is a file of its own, so a directory with files other than the flag means that
+/// the defaults aren't simply in use because nothing was ever saved.
+fn predates_first_run(dir: &Path) -> bool {
+    if dir.join("first_run").exists() {
+        return false
+    }
+    std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+/// The highest version of the configuration above [`VERSION`] that exists, as written by a newer
+/// release of the applet that the user went back from.
+///
+/// Every version is kept in a directory of its own, and every field in a file of its own, so this
+/// release neither reads nor overwrites what a newer one saved. This only lets the user know why
+/// settings they made there don't show up.
+fn newer_config_version() -> Option<u64> {
+    let root = config_dir()?.parent()?.to_path_buf();
+    let entries = match std::fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(error) => {
+            tracing::warn!("Failed to look for newer configuration versions in {}: {}", root.display(), error);
+            return None
+        }
+    };
+    let newest = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.strip_prefix('v')?.parse::<u64>().ok())
+        .filter(|&version| version > VERSION)
+        .max()?;
+    tracing::info!("Found configuration version {} from a newer release, which is left alone", newest);
+    Some(newest)
+}
+
+/// The settings in a configuration directory that this release doesn't know about, sorted by name.
+///
+/// A newer release can add settings without raising [`VERSION`]. Those are kept as they are, since
+/// every field is a file of its own and only known fields are ever written, but they have no effect.
+fn unknown_config_fields(dir: &Path) -> Vec<String> {
+    let Ok(serde_json::Value::Object(known)) = serde_json::to_value(CosmicAppletRamConfig::default()) else {
+        return Vec::new()
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(error) => {
+            tracing::warn!("Failed to look for unknown settings in {}: {}", dir.display(), error);
+            return Vec::new()
+        }
+    };
+    let mut unknown = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        // Leaves out temporary files of writes in progress
+        .filter(|name| !name.starts_with('.') && !known.contains_key(name))
+        .collect::<Vec<_>>();
+    unknown.sort();
+    if !unknown.is_empty() {
+        tracing::info!("Found settings that this release doesn't know about, which are left alone: {}", unknown.join(", "));
+    }
+    unknown
+}
+
+/// Average every rate with the ones next to it, so that a single noisy sample doesn't tower over
+/// the rest of the graph.
+///
+/// Breaks stay breaks, and rates next to one are only averaged with the neighbour on their side.
+fn smooth_rates(rates: &[Option<f64>]) -> Vec<Option<f64>> {
+    (0..rates.len())
+        .map(|i| {
+            rates[i]?;
+            let window = &rates[i.saturating_sub(1)..(i + 2).min(rates.len())];
+            let (sum, count) = window.iter()
+                .flatten()
+                .fold((0.0, 0), |(sum, count), rate| (sum + rate, count + 1));
+            Some(sum / f64::from(count))
+        })
+        .collect()
+}
+
+/// A horizontal bar that is filled by the given fraction, from the left or from the right.
+fn fill_bar(fraction: f32, from_right: bool, fill: Color, track: Color) -> Element<'static, Message> {
+    let filled = (fraction.clamp(0.0, 1.0) * f32::from(BAR_PORTIONS)).round() as u16;
+    let segment = |portion: u16, color: Color| Element::from(
+        container(cosmic::widget::Space::new(Length::Fill, Length::Fixed(BAR_HEIGHT)))
+            .width(Length::FillPortion(portion))
+            .class(cosmic::theme::Container::custom(move |_| cosmic::iced::widget::container::Style {
+                background: Some(color.into()),
+                ..Default::default()
+            }))
+    );
+    let mut segments = vec![(filled, fill), (BAR_PORTIONS - filled, track)];
+    if from_right {
+        segments.reverse();
+    }
+    // Leave out empty segments rather than laying out a portion of zero
+    cosmic::widget::row::with_children(
+        segments.into_iter()
+            .filter(|&(portion, _)| portion > 0)
+            .map(|(portion, color)| segment(portion, color))
+            .collect(),
+    )
+    .into()
+}
+
+/// The color of the icon when the given fraction of memory is in use.
+///
+/// The color moves from green through yellow to red as usage increases. Near both ends of the
+/// scale it is blended toward the theme's foreground so it remains legible on the panel.
+fn usage_gradient(ratio: f32, foreground: Color) -> Color {
+    const GREEN: Color = Color::from_rgb(0.2, 0.75, 0.3);
+    const YELLOW: Color = Color::from_rgb(0.95, 0.75, 0.1);
+    const RED: Color = Color::from_rgb(0.9, 0.2, 0.2);
+    fn mix(a: Color, b: Color, t: f32) -> Color {
+        Color::from_rgb(
+            a.r + (b.r - a.r) * t,
+            a.g + (b.g - a.g) * t,
+            a.b + (b.b - a.b) * t,
+        )
+    }
+    let ratio = ratio.clamp(0.0, 1.0);
+    let color = if ratio < 0.5 {
+        mix(GREEN, YELLOW, ratio * 2.0)
+    } else {
+        mix(YELLOW, RED, (ratio - 0.5) * 2.0)
+    };
+    // Distance from the middle of the scale, between 0 and 1
+    let extremity = (ratio - 0.5).abs() * 2.0;
+    mix(color, foreground, 0.25 * extremity)
+}
+
+/// Parse a panel text color, which is either a hexadecimal color such as `#3584e4` or the name of
+/// one of the theme's colors.
+///
+/// An empty string, like anything else that can't be parsed, results in `None`.
+fn parse_text_color(color: &str, theme: &cosmic::Theme) -> Option<Color> {
+    let color = color.trim();
+    let cosmic = theme.cosmic();
+    let role = match color.to_lowercase().as_str() {
+        "accent" => cosmic.accent_color(),
+        "success" => cosmic.success_color(),
+        "warning" => cosmic.warning_color(),
+        "destructive" => cosmic.destructive_color(),
+        _ => return Color::parse(color),
+    };
+    Some(role.into())
+}
+
+/// Whether the language of the user's locale is written from right to left.
+///
+/// The locale is taken from the same environment variables that gettext looks at for messages.
+fn locale_is_rtl() -> bool {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    language_is_rtl(&locale)
+}
+
+/// Whether the language of `locale`, such as `ar_EG.UTF-8`, is written from right to left.
+///
+/// A `@latin` modifier, as in `ug_CN@latin`, picks the Latin script, which is written from left to
+/// right.
+fn language_is_rtl(locale: &str) -> bool {
+    if locale.split_once('@').is_some_and(|(_, modifier)| modifier.eq_ignore_ascii_case("latin")) {
+        return false
+    }
+    // Strip the territory, encoding and modifier, as in `ar_EG.UTF-8@abjad`
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+    RTL_LANGUAGES.contains(&language)
+}
+
+/// Put the icon and the pieces of the label, given from left to right, in the order in which they
+/// are laid out on the panel.
+///
+/// The usage reads from the right in a right-to-left locale, so it starts next to the icon. A
+/// vertical panel is laid out from top to bottom either way.
+fn panel_order<T>(mut children: Vec<T>, layout: PanelLayout, rtl: bool) -> Vec<T> {
+    if layout.horizontal && rtl {
+        children.reverse();
+    }
+    children
+}
+
+/// The prefix labels in a list separated by commas, in the order of [`PREFIXES`], where extra
+/// labels are left out.
+fn parse_prefix_labels(text: &str) -> Vec<String> {
+    if text.trim().is_empty() {
+        return Vec::new()
+    }
+    text.split(',').map(|label| String::from(label.trim())).take(PREFIXES.len()).collect()
+}
+
+/// Cut a panel label down to [`MAX_LABEL_LENGTH`] characters.
+fn truncate_label(label: String) -> String {
+    if label.chars().count() <= MAX_LABEL_LENGTH {
+        return label
+    }
+    label.chars().take(MAX_LABEL_LENGTH).collect()
+}
+
+/// The value that a key steps a setting to, or `None` for a key that doesn't step.
+///
+/// The arrow keys change the value by `step` and PageUp and PageDown by `page_step`, while Home
+/// and End jump to `min` and `max`. The result never leaves that range.
+fn step_value(value: u64, key: &Key, step: u64, page_step: u64, min: u64, max: u64) -> Option<u64> {
+    let value = match key {
+        Key::Named(Named::ArrowUp) => value.saturating_add(step),
+        Key::Named(Named::ArrowDown) => value.saturating_sub(step),
+        Key::Named(Named::PageUp) => value.saturating_add(page_step),
+        Key::Named(Named::PageDown) => value.saturating_sub(page_step),
+        Key::Named(Named::Home) => min,
+        Key::Named(Named::End) => max,
+        _ => return None,
+    };
+    Some(value.clamp(min, max))
+}
+
+/// Bring a gap after which the rate graph breaks within the supported range, where 0 means never.
+fn clamp_graph_gap_intervals(intervals: u32) -> u32 {
+    match intervals {
+        0 => 0,
+        intervals => intervals.clamp(MIN_GRAPH_GAP_INTERVALS, MAX_GRAPH_GAP_INTERVALS),
+    }
+}
+
+/// Bring a precision that was read from somewhere within the supported range.
+fn clamp_precision(precision: u32) -> u32 {
+    precision.clamp(MIN_PRECISION, MAX_PRECISION)
+}
+
+/// Format a duration in seconds as a short human-readable string, such as `3d 4h 12m`.
+///
+/// Seconds are not displayed, so anything under a minute is shown as `0m`.
+fn format_uptime(secs: u64) -> String {
+    let days = secs / 86400;
+    let hours = (secs % 86400) / 3600;
+    let minutes = (secs % 3600) / 60;
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// The names of the amounts that [`describe_usage`] rounds to, each 1024 times the previous one.
+const WORD_UNITS: [&str; 5] = ["byte", "kilobyte", "megabyte", "gigabyte", "terabyte"];
+
+const NUMBER_WORDS: [&str; 21] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen",
+    "nineteen", "twenty",
+];
+
+/// Describe the usage in plain words, such as `About eight gigabytes used, roughly half full`.
+///
+/// The amount is rounded to a whole number of the largest unit that it has at least one of, and
+/// how full memory is falls into one of a few coarse buckets. Without a total only the amount is
+/// described.
+fn describe_usage(used: u64, total: u64) -> String {
+    let mut value = used as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < WORD_UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    let count = value.round() as u64;
+    let number = match NUMBER_WORDS.get(count as usize) {
+        Some(word) => word.to_string(),
+        None => count.to_string(),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    let amount = format!("About {number} {}{plural} used", WORD_UNITS[unit]);
+    if total == 0 {
+        return amount
+    }
+    let ratio = used as f64 / total as f64;
+    let fullness = if ratio < 0.15 {
+        "nearly empty"
+    } else if ratio < 0.4 {
+        "plenty free"
+    } else if ratio < 0.6 {
+        "roughly half full"
+    } else if ratio < 0.85 {
+        "mostly full"
+    } else if ratio < 0.95 {
+        "nearly full"
+    } else {
+        "full"
+    };
+    format!("{amount}, {fullness}")
+}
+
+/// Pair the formatted free memory with the percentage in use, as in `8.2 GiB free · 49%`, in
+/// pieces of the panel label so that only the numbers are numbers.
+///
+/// The percentage has the same precision and decimal separator as the free memory. Without a
+/// percentage, as when the total is zero, only the free memory is shown.
+fn free_and_percent(free: &str, percent: Option<f64>, format: &ByteFormat) -> Vec<(Segment, String)> {
+    let mut pieces = vec![(Segment::Number, String::from(free)), (Segment::Plain, String::from(" free"))];
+    if let Some(percent) = percent {
+        let percent = with_decimal_separator(format!("{percent:.prec$}", prec = format.precision as usize), format);
+        pieces.push((Segment::Plain, String::from(" · ")));
+        pieces.push((Segment::Number, format!("{percent}%")));
+    }
+    pieces
+}
+
+/// Format the used and the total memory without their units, as in `8.2/16`.
+///
+/// Both share the prefix that the total calls for, since the numbers can't be compared otherwise.
+/// A total of zero, which there is only before the first sample, comes out as `0/0`.
+fn terse_ratio(used: u64, total: u64, format: &ByteFormat) -> String {
+    if total == 0 {
+        return String::from("0/0")
+    }
+    let mut format = *format;
+    format.auto_reference = Some(total);
+    format.auto_nice = false;
+    let (used, _) = format_bytes_parts(used, &format);
+    let (total, _) = format_bytes_parts(total, &format);
+    format!("{used}/{total}")
+}
+
+/// Round a percentage to the nearest multiple of `step`, after rounding it to a whole number, so
+/// that with a step of 5, 62 becomes 60 while 63 and 64 become 65.
+fn snap_percent(percent: f64, step: u32) -> f64 {
+    let step = f64::from(step.max(1));
+    (percent.round() / step).round() * step
+}
+
+/// Format a count with its digits grouped by thousands, such as `8,812,345,344`.
+fn format_grouped(count: u64) -> String {
+    let digits = count.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Format a duration in milliseconds as a short human-readable string in the largest unit that
+/// fits, such as `250 ms`, `1.5 s` or `2 min`.
+///
+/// At most one decimal is shown, and none if it would be zero.
+fn format_duration_ms(msec: u64) -> String {
+    let (value, unit) = if msec < 1000 {
+        return format!("{msec} ms")
+    } else if msec < 60_000 {
+        (msec as f64 / 1000.0, "s")
+    } else if msec < 3_600_000 {
+        (msec as f64 / 60_000.0, "min")
+    } else {
+        (msec as f64 / 3_600_000.0, "h")
+    };
+    let value = format!("{value:.1}");
+    format!("{} {unit}", value.strip_suffix(".0").unwrap_or(&value))
+}
+
+/// Write a configuration to a file as JSON, so that it can be imported again elsewhere.
+fn export_settings(config: &CosmicAppletRamConfig, path: &Path) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(config).map_err(|error| error.to_string())?;
+    std::fs::write(path, contents).map_err(|error| error.to_string())
+}
+
+/// Serialize the fields of a configuration that differ from the defaults as compact JSON, for
+/// pasting into a bug report.
+///
+/// Unless `include_commands` is set, the strings in [`SNIPPET_REDACTED_FIELDS`] are replaced, since
+/// commands and paths can give away host names, user names and the like.
+fn settings_snippet(config: &CosmicAppletRamConfig, include_commands: bool) -> Result<String, String> {
+    let (serde_json::Value::Object(defaults), serde_json::Value::Object(mut fields)) = (
+        serde_json::to_value(CosmicAppletRamConfig::default()).map_err(|error| error.to_string())?,
+        serde_json::to_value(config).map_err(|error| error.to_string())?,
+    ) else {
+        return Err(String::from("configuration is not a JSON object"))
+    };
+    fields.retain(|field, value| defaults.get(field) != Some(value));
+    if !include_commands {
+        for (field, value) in fields.iter_mut() {
+            redact_field(field, value);
+        }
+    }
+    serde_json::to_string(&fields).map_err(|error| error.to_string())
+}
+
+/// Replace the strings in the serialized value of `field`, if it is one of
+/// [`SNIPPET_REDACTED_FIELDS`].
+fn redact_field(field: &str, value: &mut serde_json::Value) {
+    // A source without fields is a plain variant name, which gives nothing away
+    if SNIPPET_REDACTED_FIELDS.contains(&field) && !(field == "source" && value.is_string()) {
+        redact_strings(value);
+    }
+}
+
+/// Replace every string within a JSON value, except for empty ones, which give nothing away.
+fn redact_strings(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(string) if !string.is_empty() => *string = String::from("<redacted>"),
+        // A variant with fields is serialized as an object whose only key is its name, which is
+        // kept along with the keys of its fields
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(redact_strings),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_strings),
+        _ => {},
+    }
+}
+
+/// Read a configuration that was written by [`export_settings`].
+///
+/// Fields that are missing from the file, for example because it was exported by an older version,
+/// keep their default. The result is validated, so that a hand-edited file can't put the applet in
+/// a state that the popup can't represent.
+fn import_settings(path: &Path) -> Result<CosmicAppletRamConfig, String> {
+    let contents = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let imported: serde_json::Value = serde_json::from_str(&contents).map_err(|error| error.to_string())?;
+    let serde_json::Value::Object(imported) = imported else {
+        return Err(String::from("expected a JSON object"))
+    };
+    let mut config = serde_json::to_value(CosmicAppletRamConfig::default()).map_err(|error| error.to_string())?;
+    if let serde_json::Value::Object(fields) = &mut config {
+        fields.extend(imported);
+    }
+    let config: CosmicAppletRamConfig = serde_json::from_value(config).map_err(|error| error.to_string())?;
+    Ok(config.validated())
+}
+
+/// Replace the values of [`SNIPPET_REDACTED_FIELDS`] in an imported configuration with those of
+/// the `current` one.
+///
+/// Settings from elsewhere could otherwise run any command, as the critical action or as a remote
+/// source, without the user ever seeing it. A source without a command is imported as usual.
+fn keep_redacted_fields(imported: CosmicAppletRamConfig, current: &CosmicAppletRamConfig) -> Result<CosmicAppletRamConfig, String> {
+    let (serde_json::Value::Object(mut fields), serde_json::Value::Object(current)) = (
+        serde_json::to_value(imported).map_err(|error| error.to_string())?,
+        serde_json::to_value(current).map_err(|error| error.to_string())?,
+    ) else {
+        return Err(String::from("configuration is not a JSON object"))
+    };
+    for field in SNIPPET_REDACTED_FIELDS {
+        if field == "source" && fields.get(field).is_some_and(serde_json::Value::is_string) {
+            continue
+        }
+        if let Some(value) = current.get(field) {
+            fields.insert(String::from(field), value.clone());
+        }
+    }
+    serde_json::from_value(serde_json::Value::Object(fields)).map_err(|error| error.to_string())
+}
+
+/// Log which fields differ between two configurations, along with their old and new values.
+fn log_config_changes(old: &CosmicAppletRamConfig, new: &CosmicAppletRamConfig) {
+    let changes = config_changes(old, new);
+    // Writes made by the applet itself are echoed back without changing anything
+    if changes.is_empty() {
+        return
+    }
+    tracing::info!(changes = %changes.join(", "), "Configuration changed");
+}
+
+/// Describe every field that differs between two configurations, such as `precision: 1 -> 2`.
+///
+/// The fields are compared in their serialized form, so that new fields are picked up without
+/// having to list them here. The values of [`SNIPPET_REDACTED_FIELDS`] are redacted, since logs
+/// end up in bug reports as well.
+fn config_changes(old: &CosmicAppletRamConfig, new: &CosmicAppletRamConfig) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return Vec::new()
+    };
+    new.into_iter()
+        .filter_map(|(field, mut value)| {
+            let mut previous = old.get(&field).cloned().unwrap_or(serde_json::Value::Null);
+            if previous == value {
+                return None
+            }
+            redact_field(&field, &mut previous);
+            redact_field(&field, &mut value);
+            Some(format!("{field}: {previous} -> {value}"))
+        })
+        .collect()
+}
+
+/// Memory statistics sampled from the system, as included in `--dump-status`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Metrics {
+    #[serde(flatten)]
+    memory: MemorySample,
+    /// The percentage of memory in use, as displayed by the applet
+    percentage: f64,
+}
+
+impl Metrics {
+
+    fn sample(source: &mut dyn MetricsSource, denominator: Denominator, cap: Option<u64>) -> Self {
+        let memory = source.sample();
+        // A single sample is its own peak
+        let total = cap.unwrap_or_else(|| denominator.of(&memory, memory.total));
+        Self {
+            memory,
+            percentage: if total == 0 {
+                0.0
+            } else {
+                memory.used as f64 / total as f64 * 100.0
+            },
+        }
+    }
+
+}
+
+/// Everything needed to reproduce what the applet displays, for attaching to bug reports.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Status {
+    config: CosmicAppletRamConfig,
+    metrics: Metrics,
+}
+
+/// Print the current configuration and memory statistics as JSON on standard output.
+fn dump_status() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::new(ID, VERSION)?;
+    let config = CosmicAppletRamConfig::get_entry(&config).unwrap_or_default();
+    let metrics = Metrics::sample(&mut SysinfoSource::default(), config.denominator, config.display_cap_bytes);
+    let status = Status {
+        config,
+        metrics,
+    };
+    println!("{}", serde_json::to_string_pretty(&status)?);
+    Ok(())
+}
+
+// The main function returns a cosmic::iced::Result that is returned from
+// the run function that's part of the applet module.
+fn main() -> cosmic::iced::Result {
+    if std::env::args().skip(1).any(|arg| arg == "--dump-status") {
+        if let Err(error) = dump_status() {
+            eprintln!("Failed to dump status: {error}");
+            std::process::exit(1);
+        }
+        return Ok(())
+    }
+    // The panel tells its applets which panel they are on; without it, there is no panel
+    // surface to attach to, and the popup can't be opened. Logging isn't set up until the applet
+    // runs, so this goes straight to standard error.
+    if std::env::var_os("COSMIC_PANEL_NAME").is_none() {
+        eprintln!(
+            "Not running inside the COSMIC panel, so the popup can't be opened; add the applet to a \
+            panel instead. To inspect its readings from a terminal, use --dump-status."
+        );
+    }
+    cosmic::applet::run::<Window>(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::MockSource;
+    use cosmic::Application;
+    use tempfile::TempDir;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    /// A sample with `used` of `total` bytes in use and no swap.
+    fn sample(used: u64, total: u64) -> MemorySample {
+        MemorySample {
+            used,
+            total,
+            available: total - used,
+            free: total - used,
+            used_swap: 0,
+            total_swap: 0,
+        }
+    }
+
+    /// A window that reads from `source`, with a configuration stored in a directory of its own,
+    /// which is removed as the returned guard is dropped.
+    fn window_with(config: CosmicAppletRamConfig, source: &MockSource) -> (Window, TempDir) {
+        let dir = TempDir::new().expect("failed to create a directory for the test config");
+        let handle = Config::with_custom_path(ID, VERSION, dir.path().to_path_buf()).expect("failed to create the test config");
+        let window = Window::new(Core::default(), handle, config, Box::new(source.clone()), History::default());
+        (window, dir)
+    }
+
+    /// IEC with one decimal and nothing else changed.
+    fn iec() -> ByteFormat<'static> {
+        ByteFormat {
+            base: Base::Binary,
+            prefix_style: PrefixStyle::Infix,
+            prefix: Prefix::Auto,
+            smallest_prefix: Prefix::None,
+            prefix_labels: &[],
+            precision: 1,
+            decimal_comma: false,
+            expand_small_values: false,
+            rounding: Rounding::Nearest,
+            unit: Unit::Byte,
+            auto_hysteresis: 100,
+            auto_reference: None,
+            auto_nice: false,
+            like_free: false,
+            compact: false,
+        }
+    }
+
+    fn si() -> ByteFormat<'static> {
+        let mut format = iec();
+        format.set_standard(Standard::Si);
+        format
+    }
+
+    #[test]
+    fn iec_prefixes() {
+        assert_eq!(format_bytes(1023, &iec()), "1023 B");
+        assert_eq!(format_bytes(1024, &iec()), "1.0 KiB");
+        assert_eq!(format_bytes(1536, &iec()), "1.5 KiB");
+        assert_eq!(format_bytes(8 * 1024 * 1024 * 1024, &iec()), "8.0 GiB");
+    }
+
+    #[test]
+    fn si_prefixes() {
+        assert_eq!(format_bytes(999, &si()), "999 B");
+        assert_eq!(format_bytes(1000, &si()), "1.0 KB");
+        assert_eq!(format_bytes(1500, &si()), "1.5 KB");
+        assert_eq!(format_bytes(8_000_000_000, &si()), "8.0 GB");
+    }
+
+    #[test]
+    fn base_and_labels_apart_from_the_standard() {
+        let format = |standard, prefix_base, prefix_style| {
+            let config = CosmicAppletRamConfig {
+                standard,
+                prefix_base,
+                prefix_style,
+                precision: 1,
+                ..Default::default()
+            };
+            format_bytes(1536 * 1024, &ByteFormat::from_config(&config))
+        };
+        assert_eq!(format(Standard::Iec, None, None), "1.5 MiB");
+        assert_eq!(format(Standard::Si, None, None), "1.6 MB");
+        // MB meaning 1024², as many older tools have it
+        assert_eq!(format(Standard::Si, Some(Base::Binary), None), "1.5 MB");
+        assert_eq!(format(Standard::Iec, None, Some(PrefixStyle::Plain)), "1.5 MB");
+        assert_eq!(format(Standard::Iec, Some(Base::Decimal), None), "1.6 MiB");
+        assert_eq!(format(Standard::Si, None, Some(PrefixStyle::Infix)), "1.6 MiB");
+    }
+
+    #[test]
+    fn bits() {
+        let mut format = iec();
+        format.unit = Unit::Bit;
+        assert_eq!(format_bytes(1, &format), "8 bit");
+        assert_eq!(format_bytes(1024, &format), "8.0 Kibit");
+        format.set_standard(Standard::Si);
+        assert_eq!(format_bytes(1000, &format), "8.0 Kbit");
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!(format_bytes(0, &iec()), "0 B");
+        assert_eq!(format_bytes(0, &si()), "0 B");
+        let mut format = iec();
+        format.prefix = Prefix::Giga;
+        assert_eq!(format_bytes(0, &format), "0.0 GiB");
+    }
+
+    #[test]
+    fn largest_count() {
+        assert_eq!(format_bytes(u64::MAX, &iec()), "16.0 EiB");
+        assert_eq!(format_bytes(u64::MAX, &si()), "18.4 EB");
+        // Bits neither saturate nor wrap around to a small number
+        let mut format = iec();
+        format.unit = Unit::Bit;
+        assert_eq!(format_bytes(u64::MAX, &format), "128.0 Eibit");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration_ms(0), "0 ms");
+        assert_eq!(format_duration_ms(250), "250 ms");
+        assert_eq!(format_duration_ms(999), "999 ms");
+        assert_eq!(format_duration_ms(1000), "1 s");
+        assert_eq!(format_duration_ms(1500), "1.5 s");
+        assert_eq!(format_duration_ms(60_000), "1 min");
+        assert_eq!(format_duration_ms(90_000), "1.5 min");
+        assert_eq!(format_duration_ms(120_000), "2 min");
+        assert_eq!(format_duration_ms(3_600_000), "1 h");
+        assert_eq!(format_duration_ms(5_400_000), "1.5 h");
+    }
+
+    #[test]
+    fn auto_nice_values_stay_between_1_and_999() {
+        let format = |count, reference| {
+            let format = ByteFormat {
+                auto_nice: true,
+                auto_reference: Some(reference),
+                ..iec()
+            };
+            format_bytes(count, &format)
+        };
+        // Stepping down rather than showing 0.x
+        assert_eq!(format(50 * GIB, 1024 * GIB), "50.0 GiB");
+        assert_eq!(format(1023 * GIB, 1024 * GIB), "1023.0 GiB");
+        assert_eq!(format(1024 * GIB, 1024 * GIB), "1.0 TiB");
+        // Stepping up rather than showing 1000 or more
+        assert_eq!(format(999 * GIB, GIB), "999.0 GiB");
+        assert_eq!(format(2000 * GIB, GIB), "2.0 TiB");
+        // Unless that would give 0.x after all
+        assert_eq!(format(1000 * GIB, GIB), "1000.0 GiB");
+        // Without a reference, the hysteresis still decides when to step up
+        let format = ByteFormat {
+            auto_nice: true,
+            auto_hysteresis: 150,
+            ..iec()
+        };
+        assert_eq!(format_bytes(1200 * MIB, &format), "1200.0 MiB");
+        assert_eq!(format_bytes(0, &format), "0 B");
+    }
+
+    #[test]
+    fn like_free_matches_procps() {
+        let format = ByteFormat {
+            like_free: true,
+            ..iec()
+        };
+        // Taken from `free -h` with the matching values in KiB from `free -k`
+        assert_eq!(format_bytes(16_316_376 * 1024, &format), "15Gi");
+        assert_eq!(format_bytes(5_452_592 * 1024, &format), "5.2Gi");
+        assert_eq!(format_bytes(8_060_968 * 1024, &format), "7.7Gi");
+        assert_eq!(format_bytes(2_097_148 * 1024, &format), "2.0Gi");
+        assert_eq!(format_bytes(0, &format), "0B");
+        // Plain bytes only fit below 1000
+        assert_eq!(format_bytes(999, &format), "999B");
+        assert_eq!(format_bytes(1000, &format), "1.0Ki");
+        // Whole numbers that don't fit move up a prefix
+        assert_eq!(format_bytes(1023 * MIB, &format), "1.0Gi");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * MIB, &format), "3.0Ti");
+    }
+
+    #[test]
+    fn decimal_comma_only_changes_the_decimal_separator() {
+        let labels = [String::new(), String::from("k."), String::from("M.")];
+        let format = ByteFormat {
+            decimal_comma: true,
+            prefix_labels: &labels,
+            ..iec()
+        };
+        assert_eq!(format_bytes(1536, &format), "1,5 k.iB");
+        assert_eq!(format_bytes(1023, &format), "1023 B");
+        assert_eq!(format_bytes(8 * GIB + GIB / 5, &format), "8,2 GiB");
+        let like_free = ByteFormat {
+            like_free: true,
+            ..format
+        };
+        assert_eq!(format_bytes(5_452_592 * 1024, &like_free), "5,2Gi");
+        assert_eq!(format_bytes(16_316_376 * 1024, &like_free), "15Gi");
+    }
+
+    #[test]
+    fn graph_gap_skips_a_single_interval() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            graph_gap_intervals: 0,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        let _ = window.update(Message::UpdateGraphGapIntervals(1));
+        assert_eq!(window.live_config.graph_gap_intervals, MIN_GRAPH_GAP_INTERVALS);
+        let _ = window.update(Message::UpdateGraphGapIntervals(1));
+        assert_eq!(window.live_config.graph_gap_intervals, 0);
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().graph_gap_intervals, 0);
+
+        let config = CosmicAppletRamConfig {
+            graph_gap_intervals: 1,
+            ..Default::default()
+        };
+        assert_eq!(config.validated().graph_gap_intervals, MIN_GRAPH_GAP_INTERVALS);
+    }
+
+    #[test]
+    fn decimal_comma_applies_to_durations() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            decimal_comma: true,
+            ..Default::default()
+        };
+        let (window, _dir) = window_with(config, &source);
+        assert_eq!(window.format_duration(1500), "1,5 s");
+        assert_eq!(window.format_duration(5_400_000), "1,5 h");
+        assert_eq!(window.format_duration(250), "250 ms");
+    }
+
+    #[test]
+    fn smallest_prefix_raises_plain_bytes() {
+        let mut format = iec();
+        format.smallest_prefix = Prefix::Kilo;
+        assert_eq!(format_bytes(512, &format), "0.5 KiB");
+        assert_eq!(format_bytes(0, &format), "0.0 KiB");
+        // Counts that already have a prefix keep it
+        assert_eq!(format_bytes(1536, &format), "1.5 KiB");
+        assert_eq!(format_bytes(8 * 1024 * 1024, &format), "8.0 MiB");
+        // Counts with a prefix below the smallest one are raised as well
+        format.smallest_prefix = Prefix::Mega;
+        assert_eq!(format_bytes(512 * 1024, &format), "0.5 MiB");
+        assert_eq!(format_bytes(8 * 1024 * 1024, &format), "8.0 MiB");
+        format.prefix = Prefix::None;
+        assert_eq!(format_bytes(512 * 1024, &format), "0.5 MiB");
+    }
+
+    #[test]
+    fn prefix_labels_replace_the_defaults() {
+        let labels = [String::new(), String::from("k"), String::new()];
+        let format = ByteFormat {
+            prefix_labels: &labels,
+            ..si()
+        };
+        assert_eq!(format_bytes(1500, &format), "1.5 kB");
+        // Empty and missing entries keep the default
+        assert_eq!(format_bytes(999, &format), "999 B");
+        assert_eq!(format_bytes(1_500_000, &format), "1.5 MB");
+        assert_eq!(format_bytes(1_500_000_000, &format), "1.5 GB");
+        assert_eq!(format_bytes(1536, &iec()), "1.5 KiB");
+    }
+
+    #[test]
+    fn prefix_labels_are_typed_separated_by_commas() {
+        assert_eq!(parse_prefix_labels(" "), Vec::<String>::new());
+        assert_eq!(parse_prefix_labels(", k , M"), ["", "k", "M"]);
+        assert_eq!(parse_prefix_labels(&",".repeat(20)).len(), PREFIXES.len());
+
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let _ = window.update(Message::UpdatePrefixLabelsText(String::from(", K, M, Go")));
+        assert!(window.live_config.prefix_labels.is_empty());
+        let _ = window.update(Message::UpdatePrefixLabels(window.prefix_labels_text.clone()));
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().prefix_labels, ["", "K", "M", "Go"]);
+        assert_eq!(window.prefix_labels_text, ", K, M, Go");
+    }
+
+    #[test]
+    fn validated_drops_extra_prefix_labels() {
+        let config = CosmicAppletRamConfig {
+            prefix_labels: vec![String::from("x"); PREFIXES.len() + 3],
+            ..Default::default()
+        }
+        .validated();
+        assert_eq!(config.prefix_labels.len(), PREFIXES.len());
+    }
+
+    #[test]
+    fn precision_is_clamped_to_the_supported_range() {
+        assert_eq!(clamp_precision(3), 3);
+        assert_eq!(clamp_precision(MAX_PRECISION), MAX_PRECISION);
+        assert_eq!(clamp_precision(99), MAX_PRECISION);
+    }
+
+    #[test]
+    fn uptime() {
+        assert_eq!(format_uptime(0), "0m");
+        assert_eq!(format_uptime(59), "0m");
+        assert_eq!(format_uptime(3600), "1h 0m");
+        assert_eq!(format_uptime(86400), "1d 0h 0m");
+        assert_eq!(format_uptime(3 * 86400 + 4 * 3600 + 12 * 60 + 59), "3d 4h 12m");
+    }
+
+    #[test]
+    fn smoothing_averages_rates_with_their_neighbours() {
+        assert_eq!(smooth_rates(&[Some(0.0), Some(9.0), Some(0.0), Some(3.0)]), [Some(4.5), Some(3.0), Some(4.0), Some(1.5)]);
+        assert_eq!(smooth_rates(&[Some(5.0)]), [Some(5.0)]);
+        assert!(smooth_rates(&[]).is_empty());
+    }
+
+    #[test]
+    fn smoothing_keeps_breaks_in_the_rates() {
+        assert_eq!(
+            smooth_rates(&[Some(2.0), Some(4.0), None, Some(8.0), Some(0.0)]),
+            [Some(3.0), Some(3.0), None, Some(4.0), Some(4.0)],
+        );
+        assert_eq!(smooth_rates(&[None, None]), [None, None]);
+    }
+
+    #[test]
+    fn popup_fits_its_widest_row_within_bounds() {
+        let row = |label: &str, value: &str| (String::from(label), String::from(value));
+        assert_eq!(fit_popup_width(&[]), POPUP_MIN_WIDTH);
+        assert_eq!(fit_popup_width(&[row("firefox", "1.2 GiB")]), POPUP_MIN_WIDTH);
+        // 39 characters
+        let width = fit_popup_width(&[row("firefox", "1.2 GiB"), row("Configuration", "/home/alice/.config/cosmic")]);
+        assert_eq!(width, 39.0 * POPUP_CHAR_WIDTH + POPUP_ROW_CHROME);
+        assert_eq!(fit_popup_width(&[row("Source", &"x".repeat(500))]), POPUP_FIT_MAX_WIDTH);
+    }
+
+    #[test]
+    fn usage_in_words() {
+        assert_eq!(describe_usage(8 * GIB, 16 * GIB), "About eight gigabytes used, roughly half full");
+        assert_eq!(describe_usage(GIB + 100 * MIB, 64 * GIB), "About one gigabyte used, nearly empty");
+        assert_eq!(describe_usage(512 * MIB, 0), "About 512 megabytes used");
+        assert_eq!(describe_usage(0, 16 * GIB), "About zero bytes used, nearly empty");
+        // The buckets start at their lower bound
+        let fullness = |percent: u64| describe_usage(percent * GIB, 100 * GIB).split(", ").nth(1).unwrap().to_string();
+        assert_eq!(fullness(14), "nearly empty");
+        assert_eq!(fullness(15), "plenty free");
+        assert_eq!(fullness(40), "roughly half full");
+        assert_eq!(fullness(60), "mostly full");
+        assert_eq!(fullness(85), "nearly full");
+        assert_eq!(fullness(95), "full");
+    }
+
+    #[test]
+    fn percentages_snap_to_the_step() {
+        assert_eq!(snap_percent(62.0, 5), 60.0);
+        assert_eq!(snap_percent(63.0, 5), 65.0);
+        assert_eq!(snap_percent(64.0, 5), 65.0);
+        // Rounded to a whole number first
+        assert_eq!(snap_percent(62.4, 5), 60.0);
+        assert_eq!(snap_percent(62.6, 5), 65.0);
+        assert_eq!(snap_percent(62.4, 1), 62.0);
+        assert_eq!(snap_percent(62.4, 0), 62.0);
+        assert_eq!(snap_percent(99.0, 25), 100.0);
+    }
+
+    #[test]
+    fn grouped_by_thousands() {
+        assert_eq!(format_grouped(0), "0");
+        assert_eq!(format_grouped(999), "999");
+        assert_eq!(format_grouped(1000), "1,000");
+        assert_eq!(format_grouped(8_812_345_344), "8,812,345,344");
+    }
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn auto_promotes_at_the_boundary_without_hysteresis() {
+        assert_eq!(format_bytes(1023 * MIB, &iec()), "1023.0 MiB");
+        assert_eq!(format_bytes(1024 * MIB, &iec()), "1.0 GiB");
+        assert_eq!(format_bytes(1025 * MIB, &iec()), "1.0 GiB");
+    }
+
+    #[test]
+    fn auto_hysteresis_holds_back_promotion() {
+        let mut format = iec();
+        format.auto_hysteresis = 110;
+        assert_eq!(format_bytes(1024 * MIB, &format), "1024.0 MiB");
+        assert_eq!(format_bytes(1126 * MIB, &format), "1126.0 MiB");
+        assert_eq!(format_bytes(1127 * MIB, &format), "1.1 GiB");
+        format.auto_hysteresis = 120;
+        assert_eq!(format_bytes(1228 * MIB, &format), "1228.0 MiB");
+        assert_eq!(format_bytes(1229 * MIB, &format), "1.2 GiB");
+        // Less than the boundary itself would promote values that don't have one of the next prefix
+        format.auto_hysteresis = 50;
+        assert_eq!(format_bytes(1023 * MIB, &format), "1023.0 MiB");
+    }
+
+    #[test]
+    fn small_values_get_more_decimals_with_a_fixed_prefix() {
+        let mut format = iec();
+        format.prefix = Prefix::Giga;
+        format.precision = 2;
+        assert_eq!(format_bytes(MIB, &format), "0.00 GiB");
+        format.expand_small_values = true;
+        assert_eq!(format_bytes(MIB, &format), "0.001 GiB");
+        // Values that are visible already and zero itself keep the configured precision
+        assert_eq!(format_bytes(50 * MIB, &format), "0.05 GiB");
+        assert_eq!(format_bytes(6 * GIB, &format), "6.00 GiB");
+        assert_eq!(format_bytes(0, &format), "0.00 GiB");
+    }
+
+    #[test]
+    fn rounding_halfway_values() {
+        assert_eq!(Rounding::Nearest.apply(2.5, 0), 3.0);
+        assert_eq!(Rounding::Nearest.apply(1.25, 1), 1.3);
+        assert_eq!(Rounding::Floor.apply(2.5, 0), 2.0);
+        assert_eq!(Rounding::Floor.apply(1.25, 1), 1.2);
+        assert_eq!(Rounding::Ceil.apply(2.5, 0), 3.0);
+        assert_eq!(Rounding::Ceil.apply(1.25, 1), 1.3);
+        // Whole values stay put in every mode
+        assert_eq!(Rounding::Ceil.apply(8.0, 0), 8.0);
+        assert_eq!(Rounding::Floor.apply(8.0, 0), 8.0);
+    }
+
+    #[test]
+    fn rounding_ignores_errors_of_scaling() {
+        // 1.1 and 0.7 have no exact binary representation, so scaling them is a little off
+        assert_eq!(Rounding::Ceil.apply(1.1, 1), 1.1);
+        assert_eq!(Rounding::Floor.apply(0.7, 2), 0.7);
+        assert_eq!(Rounding::Ceil.apply(1.100001, 1), 1.2);
+        assert_eq!(Rounding::Floor.apply(0.699999, 2), 0.69);
+
+        let mut format = si();
+        format.rounding = Rounding::Ceil;
+        assert_eq!(format_bytes(1100, &format), "1.1 KB");
+        assert_eq!(format_bytes(1101, &format), "1.2 KB");
+        format.rounding = Rounding::Floor;
+        assert_eq!(format_bytes(4_100_000_000, &format), "4.1 GB");
+        format.precision = 2;
+        assert_eq!(format_bytes(4_100_000_000, &format), "4.10 GB");
+        assert_eq!(format_bytes(4_099_999_999, &format), "4.09 GB");
+    }
+
+    #[test]
+    fn rounding_formatted_counts() {
+        let mut format = iec();
+        format.precision = 0;
+        // Formatting alone would round both to the even 2
+        assert_eq!(format_bytes(2560, &format), "3 KiB");
+        assert_eq!(format_bytes(1536, &format), "2 KiB");
+        format.rounding = Rounding::Floor;
+        assert_eq!(format_bytes(2560, &format), "2 KiB");
+        assert_eq!(format_bytes(3071, &format), "2 KiB");
+        format.rounding = Rounding::Ceil;
+        assert_eq!(format_bytes(2049, &format), "3 KiB");
+        assert_eq!(format_bytes(8_805_000_000, &format), "9 GiB");
+    }
+
+    fn assert_close(actual: Color, expected: Color) {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        assert!(
+            close(actual.r, expected.r) && close(actual.g, expected.g) && close(actual.b, expected.b),
+            "{actual:?} is not {expected:?}",
+        );
+    }
+
+    #[test]
+    fn gradient_ends_lean_toward_the_foreground() {
+        assert_close(usage_gradient(0.0, Color::WHITE), Color::from_rgb(0.4, 0.8125, 0.475));
+        assert_close(usage_gradient(1.0, Color::WHITE), Color::from_rgb(0.925, 0.4, 0.4));
+        assert_close(usage_gradient(0.5, Color::WHITE), Color::from_rgb(0.95, 0.75, 0.1));
+    }
+
+    #[test]
+    fn gradient_clamps_the_ratio() {
+        assert_eq!(usage_gradient(-0.5, Color::BLACK), usage_gradient(0.0, Color::BLACK));
+        assert_eq!(usage_gradient(1.5, Color::BLACK), usage_gradient(1.0, Color::BLACK));
+    }
+
+    #[test]
+    fn text_colors_are_hex_or_theme_colors() {
+        let theme = cosmic::Theme::dark();
+        assert_eq!(parse_text_color(" #ff0000 ", &theme), Some(Color::from_rgb(1.0, 0.0, 0.0)));
+        assert_eq!(parse_text_color("#00ff0080", &theme).map(|color| color.g), Some(1.0));
+        assert_eq!(parse_text_color("Accent", &theme), Some(theme.cosmic().accent_color().into()));
+        // Anything else is the foreground color
+        assert_eq!(parse_text_color("", &theme), None);
+        assert_eq!(parse_text_color("#ff00", &theme), None);
+        assert_eq!(parse_text_color("lime", &theme), None);
+    }
+
+    #[test]
+    fn only_high_usage_tints_the_background() {
+        let theme = cosmic::Theme::dark();
+        assert_eq!(UsageBand::Normal.tint(&theme), None);
+        for band in [UsageBand::Warning, UsageBand::Critical] {
+            let tint = band.tint(&theme).unwrap();
+            let color = band.color(&theme);
+            assert_eq!((tint.r, tint.g, tint.b), (color.r, color.g, color.b));
+            assert!(tint.a > 0.0 && tint.a <= BACKGROUND_TINT_OPACITY);
+        }
+    }
+
+    #[test]
+    fn text_only_blinks_while_critical_without_reduced_motion() {
+        let source = MockSource::default();
+        source.set(sample(15 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            critical_blink: true,
+            ..CosmicAppletRamConfig::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert!(window.blinking());
+        let _ = window.update(Message::Blink);
+        assert!(window.blink_on);
+        let _ = window.update(Message::Blink);
+        assert!(!window.blink_on);
+
+        window.ui_set_reduced_motion(true);
+        assert!(!window.blinking());
+        window.ui_set_reduced_motion(false);
+        source.set(sample(8 * GIB, 16 * GIB));
+        window.refresh_metrics();
+        assert!(!window.blinking());
+    }
+
+    #[test]
+    fn bands_start_at_their_threshold() {
+        let config = Thresholds { warning: 70, critical: 90 };
+        assert_eq!(UsageBand::classify(69.9, config), UsageBand::Normal);
+        assert_eq!(UsageBand::classify(70.0, config), UsageBand::Warning);
+        assert_eq!(UsageBand::classify(70.1, config), UsageBand::Warning);
+        assert_eq!(UsageBand::classify(89.9, config), UsageBand::Warning);
+        assert_eq!(UsageBand::classify(90.0, config), UsageBand::Critical);
+        assert_eq!(UsageBand::classify(90.1, config), UsageBand::Critical);
+    }
+
+    #[test]
+    fn free_memory_crosses_the_bands_from_the_other_side() {
+        let config = CosmicAppletRamConfig::default().thresholds(ThresholdMetric::Ram);
+        assert_eq!(UsageBand::classify_free(31.0, config), UsageBand::Normal);
+        assert_eq!(UsageBand::classify_free(30.0, config), UsageBand::Warning);
+        assert_eq!(UsageBand::classify_free(11.0, config), UsageBand::Warning);
+        assert_eq!(UsageBand::classify_free(10.0, config), UsageBand::Critical);
+        // The same band as the used memory at the same usage
+        for used in [0.0, 50.0, 70.0, 85.0, 90.0, 100.0] {
+            assert_eq!(UsageBand::classify_free(100.0 - used, config), UsageBand::classify(used, config));
+        }
+    }
+
+    #[test]
+    fn each_metric_is_classified_by_its_own_thresholds() {
+        let source = MockSource::default();
+        // 60% of RAM and of swap in use
+        source.set(MemorySample {
+            used_swap: 3 * GIB,
+            total_swap: 5 * GIB,
+            ..sample(6 * GIB, 10 * GIB)
+        });
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.monitored_metric(), ThresholdMetric::Ram);
+        assert_eq!(window.usage_band(), UsageBand::Normal);
+        assert_eq!(window.swap_band(), Some(UsageBand::Warning));
+
+        window.ui_set_monitor_target(MonitorTarget::Swap);
+        assert_eq!(window.monitored_metric(), ThresholdMetric::Swap);
+        assert_eq!(window.usage_band(), UsageBand::Warning);
+        window.ui_set_swap_thresholds(Thresholds { warning: 40, critical: 55 });
+        assert_eq!(window.usage_band(), UsageBand::Critical);
+
+        let config = CosmicAppletRamConfig {
+            swap_thresholds: Thresholds { warning: 90, critical: 60 },
+            ..Default::default()
+        };
+        assert_eq!(config.validated().swap_thresholds, Thresholds { warning: 60, critical: 60 });
+    }
+
+    #[test]
+    fn changes_below_epsilon_are_steady() {
+        assert_eq!(Trend::between(100, 110, 10), Trend::Rising);
+        assert_eq!(Trend::between(100, 109, 10), Trend::Steady);
+        assert_eq!(Trend::between(110, 100, 10), Trend::Falling);
+        // Without an epsilon, any change counts
+        assert_eq!(Trend::between(100, 101, 0), Trend::Rising);
+        assert_eq!(Trend::between(100, 100, 0), Trend::Steady);
+    }
+
+    /// A tracker that has seen each of `trends`, in order.
+    fn tracker(trends: &[Trend]) -> TurnTracker {
+        let mut tracker = TurnTracker::default();
+        for &trend in trends {
+            tracker.update(trend);
+        }
+        tracker
+    }
+
+    #[test]
+    fn turn_is_confirmed_after_enough_ticks() {
+        use Trend::*;
+        assert_eq!(tracker(&[Rising, Rising]).confirmed, Steady);
+        assert_eq!(tracker(&[Rising, Rising, Rising]).confirmed, Rising);
+        assert_eq!(tracker(&[Rising, Rising, Rising, Falling, Falling]).confirmed, Rising);
+        assert_eq!(tracker(&[Rising, Rising, Rising, Falling, Falling, Falling]).confirmed, Falling);
+    }
+
+    #[test]
+    fn flicker_is_rejected() {
+        use Trend::*;
+        // Going back up cancels the turn, so it has to start over
+        let flicker = tracker(&[Rising, Rising, Rising, Falling, Falling, Rising, Falling, Falling]);
+        assert_eq!(flicker.confirmed, Rising);
+    }
+
+    #[test]
+    fn steady_ticks_neither_confirm_nor_cancel() {
+        use Trend::*;
+        let noisy = tracker(&[Rising, Rising, Rising, Falling, Steady, Falling, Steady, Falling]);
+        assert_eq!(noisy.confirmed, Falling);
+        assert_eq!(tracker(&[Rising, Rising, Rising, Steady, Steady, Steady]).confirmed, Rising);
+    }
+
+    /// Records the level of every event, to tell at which level something was logged.
+    #[derive(Clone, Default)]
+    struct LevelRecorder(std::sync::Arc<std::sync::Mutex<Vec<tracing::Level>>>);
+
+    impl tracing::Subscriber for LevelRecorder {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+        fn event(&self, event: &tracing::Event<'_>) {
+            self.0.lock().unwrap().push(*event.metadata().level());
+        }
+        fn enter(&self, _span: &tracing::span::Id) {}
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn errors_are_logged_at_the_chosen_level() {
+        let recorder = LevelRecorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            Err::<(), _>("gone").log_at(tracing::Level::DEBUG, "Failed to save");
+            Err::<(), _>("gone").log_at(tracing::Level::WARN, "Failed to save");
+            Err::<(), _>("gone").log("Failed to save");
+            Ok::<(), &str>(()).log_at(tracing::Level::INFO, "Nothing to see");
+        });
+        assert_eq!(*recorder.0.lock().unwrap(), [tracing::Level::DEBUG, tracing::Level::WARN, tracing::Level::ERROR]);
+    }
+
+    #[test]
+    fn implausible_samples_are_unhealthy() {
+        assert_eq!(health_problem(&sample(0, 0)).as_deref(), Some("total memory is zero"));
+        let overused = MemorySample {
+            used: 17 * GIB,
+            ..sample(0, 16 * GIB)
+        };
+        assert_eq!(
+            health_problem(&overused).as_deref(),
+            Some("18253611008 bytes used out of 17179869184 in total"),
+        );
+        assert_eq!(health_problem(&sample(16 * GIB, 16 * GIB)), None);
+        assert_eq!(health_problem(&sample(0, 16 * GIB)), None);
+    }
+
+    #[test]
+    fn check_health_marks_the_window_degraded() {
+        let source = MockSource::default();
+        source.set(sample(0, 0));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        window.check_health();
+        assert!(window.degraded.is_some());
+
+        source.set(sample(6 * GIB, 16 * GIB));
+        window.refresh_metrics();
+        window.check_health();
+        assert_eq!(window.degraded, None);
+    }
+
+    #[test]
+    fn unknown_config_fields_leave_out_known_ones() {
+        let dir = TempDir::new().unwrap();
+        for name in ["precision", "future_field", "another_future_field", ".precision.tmp"] {
+            std::fs::write(dir.path().join(name), "1").unwrap();
+        }
+
+        assert_eq!(unknown_config_fields(dir.path()), vec!["another_future_field", "future_field"]);
+        assert!(unknown_config_fields(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn first_run_is_over_for_settings_from_before_the_flag() {
+        let dir = TempDir::new().unwrap();
+        // Nothing was ever saved
+        assert!(!predates_first_run(dir.path()));
+        assert!(!predates_first_run(&dir.path().join("missing")));
+        std::fs::write(dir.path().join("precision"), "2").unwrap();
+        assert!(predates_first_run(dir.path()));
+        std::fs::write(dir.path().join("first_run"), "true").unwrap();
+        assert!(!predates_first_run(dir.path()));
+    }
+
+    #[test]
+    fn dismissed_introduction_stays_dismissed() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert!(window.live_config.first_run);
+        let _ = window.update(Message::DismissFirstRun);
+        assert!(!window.live_config.first_run);
+        assert!(!CosmicAppletRamConfig::get_entry(&window.config).unwrap().first_run);
+
+        let _ = window.update(Message::ConfigChanged(CosmicAppletRamConfig::default()));
+        assert!(!window.live_config.first_run);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn last_session_is_only_compared_with_for_a_while() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.last_session_text(), None);
+        window.ui_set_compare_last_session(true);
+        assert_eq!(window.last_session_text().as_deref(), Some("no previous session"));
+        window.last_session_used = Some(5 * GIB);
+        assert_eq!(window.last_session_text().as_deref(), Some("+1 GiB vs last session"));
+
+        time::advance(LAST_SESSION_DISPLAY_TIME).await;
+        assert_eq!(window.last_session_text(), None);
+        // Dropping the window would otherwise save over the real last session
+        window.ui_set_compare_last_session(false);
+    }
+
+    #[test]
+    fn introduction_can_be_dismissed_from_elsewhere() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let dismissed = CosmicAppletRamConfig {
+            first_run: false,
+            ..Default::default()
+        };
+        let _ = window.update(Message::ConfigChanged(dismissed));
+        assert!(!window.live_config.first_run);
+    }
+
+    #[test]
+    fn unknown_config_fields_survive_a_save() {
+        use cosmic::cosmic_config::{ConfigGet, ConfigSet};
+
+        let dir = TempDir::new().unwrap();
+        let handle = Config::with_custom_path(ID, VERSION, dir.path().to_path_buf()).expect("failed to create the test config");
+        handle.set("future_field", 7u32).unwrap();
+
+        let config = CosmicAppletRamConfig { precision: 3, ..Default::default() };
+        config.write_entry(&handle).unwrap();
+
+        assert_eq!(handle.get::<u32>("future_field").ok(), Some(7));
+        assert_eq!(CosmicAppletRamConfig::get_entry(&handle).ok().map(|config| config.precision), Some(3));
+    }
+
+    #[test]
+    fn reload_picks_up_changes_made_on_disk() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let edited = CosmicAppletRamConfig { precision: 3, ..Default::default() };
+        edited.write_entry(&window.config).unwrap();
+        assert_eq!(window.live_config.precision, CosmicAppletRamConfig::default().precision);
+
+        let _ = window.update(Message::ReloadConfig);
+        assert_eq!(window.live_config.precision, 3);
+    }
+
+    #[test]
+    fn reload_applies_the_fields_that_could_be_read() {
+        use cosmic::cosmic_config::ConfigSet;
+
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let edited = CosmicAppletRamConfig { precision: 3, ..Default::default() };
+        edited.write_entry(&window.config).unwrap();
+        window.config.set("update_interval", "soon").unwrap();
+        assert!(CosmicAppletRamConfig::get_entry(&window.config).is_err());
+
+        let _ = window.update(Message::ReloadConfig);
+        assert_eq!(window.live_config.precision, 3);
+        assert_eq!(window.live_config.update_interval, DEFAULT_UPDATE_INTERVAL);
+    }
+
+    #[test]
+    fn bursts_of_config_updates_are_applied_once() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let mut ticks = window.tick_settings_tx.subscribe();
+        let samples_taken = source.samples_taken();
+        let config = CosmicAppletRamConfig {
+            precision: 2,
+            update_interval: 2000,
+            ..window.live_config.clone()
+        };
+        let mut restarts = 0;
+        for _ in 0..5 {
+            let _ = window.update(Message::ConfigChanged(config.clone()));
+            if ticks.has_changed().unwrap() {
+                restarts += 1;
+                assert_eq!(ticks.borrow_and_update().msec, 2000);
+            }
+        }
+        assert_eq!((window.live_config.precision, window.live_config.update_interval), (2, 2000));
+        // Only the first update of the burst changed the timer, and none of them sampled
+        assert_eq!(restarts, 1);
+        assert_eq!(source.samples_taken(), samples_taken);
+
+        let _ = window.update(Message::Tick);
+        assert_eq!(source.samples_taken(), samples_taken + 1);
+    }
+
+    /// Counts the events of one level that are emitted while it is the default subscriber.
+    struct EventCounter(tracing::Level, std::sync::atomic::AtomicUsize);
+
+    impl EventCounter {
+        fn new(level: tracing::Level) -> std::sync::Arc<Self> {
+            std::sync::Arc::new(EventCounter(level, std::sync::atomic::AtomicUsize::new(0)))
+        }
+
+        fn count(&self) -> usize {
+            self.1.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl tracing::Subscriber for EventCounter {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+        fn event(&self, event: &tracing::Event<'_>) {
+            if *event.metadata().level() == self.0 {
+                self.1.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn closed_interval_channel_is_logged_and_recovered() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let counter = EventCounter::new(tracing::Level::ERROR);
+        let errors = || counter.count();
+
+        // While the time subscription is listening, nothing goes wrong
+        let receiver = window.tick_settings_tx.subscribe();
+        tracing::subscriber::with_default(counter.clone(), || window.set_ticks(2000));
+        assert_eq!((errors(), window.time_subscription_generation), (0, 0));
+        assert_eq!(receiver.borrow().msec, 2000);
+
+        // The subscription died: the interval is still stored and a new timer is started
+        drop(receiver);
+        tracing::subscriber::with_default(counter.clone(), || window.set_ticks(500));
+        assert_eq!((errors(), window.time_subscription_generation), (1, 1));
+        assert_eq!(window.tick_settings_tx.borrow().msec, 500);
+    }
+
+    #[test]
+    fn window_refreshes_from_its_source() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.memory, sample(6 * GIB, 16 * GIB));
+
+        source.set(sample(7 * GIB, 16 * GIB));
+        window.refresh_metrics();
+        assert_eq!(window.memory, sample(7 * GIB, 16 * GIB));
+        // The first sample is taken as the window is created
+        assert_eq!(source.samples_taken(), 2);
+    }
+
+    #[test]
+    fn usage_band_follows_the_samples() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 10 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.usage_band(), UsageBand::Normal);
+
+        source.set(sample(7 * GIB, 10 * GIB));
+        window.refresh_metrics();
+        assert_eq!(window.usage_band(), UsageBand::Warning);
+
+        source.set(sample(9 * GIB, 10 * GIB));
+        window.refresh_metrics();
+        assert_eq!(window.usage_band(), UsageBand::Critical);
+        assert_eq!(source.samples_taken(), 3);
+    }
+
+    #[test]
+    fn last_update_skips_failed_reads() {
+        let source = MockSource::default();
+        source.fail(Some("no reading yet"));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.updated_at, None);
+
+        source.fail(None);
+        source.set(sample(6 * GIB, 16 * GIB));
+        window.refresh_metrics();
+        let updated_at = window.updated_at;
+        assert!(updated_at.is_some());
+        source.fail(Some("connection lost"));
+        window.refresh_metrics();
+        assert_eq!(window.updated_at, updated_at);
+    }
+
+    #[test]
+    fn pressure_events_refresh_right_away() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        source.set(sample(15 * GIB, 16 * GIB));
+        let _ = window.update(Message::PressureEvent);
+        assert_eq!(window.memory, sample(15 * GIB, 16 * GIB));
+        assert_eq!(window.usage_band(), UsageBand::Critical);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn markers_measure_the_change_between_two_moments() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        window.refresh_metrics();
+        let _ = window.update(Message::MarkA);
+        time::advance(time::Duration::from_secs(90)).await;
+        source.set(sample(7 * GIB, 16 * GIB));
+        window.refresh_metrics();
+        let _ = window.update(Message::MarkB);
+        let (a, b) = (window.marker_a.unwrap(), window.marker_b.unwrap());
+        assert_eq!(b.used - a.used, GIB);
+        assert_eq!(b.at.duration_since(a.at), time::Duration::from_secs(90));
+
+        // Marking A again starts over
+        let _ = window.update(Message::MarkA);
+        assert_eq!(window.marker_a.unwrap().used, 7 * GIB);
+        assert!(window.marker_b.is_none());
+
+        let _ = window.update(Message::MarkB);
+        let _ = window.update(Message::ClearMarkers);
+        assert!(window.marker_a.is_none());
+        assert!(window.marker_b.is_none());
+    }
+
+    #[test]
+    fn watchdog_restarts_a_stalled_source() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        window.check_stalled();
+        assert!(!window.stalled);
+
+        // The source hangs, so no refresh happened for a minute
+        window.refreshed_at -= time::Duration::from_secs(60);
+        window.check_stalled();
+        assert!(window.stalled);
+        assert_eq!(window.time_subscription_generation, 1);
+
+        // The stalled source is replaced, and the next refresh clears the state
+        window.refresh_metrics();
+        assert!(!window.stalled);
+        assert_eq!(source.samples_taken(), 1);
+    }
+
+    /// Blocks until it is released, like sysinfo on a hung `/proc`.
+    struct BlockingSource(std::sync::mpsc::Receiver<()>);
+
+    impl MetricsSource for BlockingSource {
+
+        fn sample(&mut self) -> MemorySample {
+            let _ = self.0.recv();
+            MemorySample::default()
+        }
+
+    }
+
+    #[test]
+    fn watchdog_restarts_a_source_that_blocks() {
+        let config = CosmicAppletRamConfig {
+            source: Source::ProcMeminfo,
+            update_interval: MIN_UPDATE_INTERVAL,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &MockSource::default());
+        window.create_source = fixture_source;
+        let (_release, blocked) = std::sync::mpsc::channel();
+        window.source = Box::new(BackgroundSource::spawn(Box::new(BlockingSource(blocked))));
+
+        // Refreshing doesn't block, but doesn't count as a refresh either
+        let refreshed_at = window.refreshed_at;
+        window.refresh_metrics();
+        assert_eq!(window.refreshed_at, refreshed_at);
+        std::thread::sleep(std::time::Duration::from_millis(MIN_UPDATE_INTERVAL) * (STALL_INTERVALS + 1));
+        window.refresh_metrics();
+        window.check_stalled();
+        assert!(window.stalled);
+
+        window.refresh_metrics();
+        assert!(!window.stalled);
+        assert_eq!(window.memory.total, 16 * GIB);
+    }
+
+    #[test]
+    fn popup_is_retried_once_without_a_panel_window() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        // A window without a runtime has no panel window to attach the popup to
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let _ = window.update(Message::TogglePopup);
+        assert!(!window.popup_unavailable());
+        // Clicking again while waiting doesn't schedule another attempt
+        let _ = window.update(Message::TogglePopup);
+        assert!(window.popup_retry_pending);
+        let _ = window.update(Message::RetryPopup);
+        assert!(window.popup_unavailable());
+        assert!(window.popup.is_none());
+        assert!(window.tooltip_text().ends_with("(the popup could not be opened)"));
+
+        // The tooltip stops mentioning it after a while
+        window.popup_unavailable_at = window.popup_unavailable_at.map(|at| at - POPUP_UNAVAILABLE_TIMEOUT);
+        assert!(!window.popup_unavailable());
+
+        let config = CosmicAppletRamConfig {
+            retry_popup: false,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        let _ = window.update(Message::TogglePopup);
+        assert!(window.popup_unavailable());
+    }
+
+    #[test]
+    fn pinning_reopens_an_open_popup_right_away() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        window.core.set_main_window_id(Some(window::Id::unique()));
+        let _ = window.update(Message::TogglePopup);
+        let unpinned = window.popup.expect("the popup is open");
+
+        // Whether the popup grabs the pointer can't change while it is open, so it is replaced
+        let _ = window.update(Message::UpdatePinned(true));
+        assert!(window.pinned);
+        assert!(window.popup.is_some_and(|pinned| pinned != unpinned));
+
+        // A closed popup stays closed
+        let _ = window.update(Message::TogglePopup);
+        let _ = window.update(Message::UpdatePinned(false));
+        assert!(!window.pinned);
+        assert!(window.popup.is_none());
+    }
+
+    #[test]
+    fn popup_opens_when_the_panel_window_is_ready_for_the_retry() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let _ = window.update(Message::TogglePopup);
+        assert!(window.popup.is_none());
+
+        window.core.set_main_window_id(Some(window::Id::unique()));
+        let _ = window.update(Message::RetryPopup);
+        assert!(window.popup.is_some());
+        assert!(!window.popup_retry_pending);
+        assert!(!window.popup_unavailable());
+    }
+
+    #[test]
+    fn interval_presets_update_the_field_and_the_setting() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        for (_, msec) in INTERVAL_PRESETS {
+            let _ = window.update(Message::UpdateInterval(msec.to_string()));
+            assert_eq!(window.update_interval_text, msec.to_string());
+            assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().update_interval, msec);
+            assert_eq!(window.tick_settings_tx.borrow().msec, msec);
+        }
+    }
+
+    #[test]
+    fn popup_has_an_update_interval_of_its_own() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            update_interval: 2000,
+            popup_update_interval: 250,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert_eq!(window.effective_interval(), 2000);
+
+        let popup = window::Id::unique();
+        window.popup = Some(popup);
+        window.apply_interval();
+        assert_eq!(window.tick_settings_tx.borrow().msec, 250);
+        // The history keeps the panel's pace
+        assert!(!window.sample_due());
+
+        let _ = window.update(Message::PopupClosed(popup));
+        assert_eq!(window.tick_settings_tx.borrow().msec, 2000);
+
+        // A popup interval slower than the panel's is ignored
+        window.popup = Some(popup);
+        window.ui_set_popup_update_interval(5000);
+        assert_eq!(window.tick_settings_tx.borrow().msec, 2000);
+    }
+
+    #[test]
+    fn unit_is_only_left_out_when_shared_with_the_total() {
+        let config = CosmicAppletRamConfig {
+            precision: 1,
+            unit_on_total_only: true,
+            ..Default::default()
+        };
+        let (window, _dir) = window_with(config, &MockSource::default());
+        let layout = PanelLayout { horizontal: true, show_total: true, show_icon: true, compact: false };
+        assert_eq!(window.format_panel_used(8 * GIB, 16 * GIB, layout), "8.0");
+        assert_eq!(window.format_panel_used(900 * MIB, 16 * GIB, layout), "900.0 MiB");
+        // Without a total, there is nothing else to carry the unit
+        let layout = PanelLayout { show_total: false, ..layout };
+        assert_eq!(window.format_panel_used(8 * GIB, 16 * GIB, layout), "8.0 GiB");
+    }
+
+    #[test]
+    fn used_definition_decides_what_counts_as_used() {
+        let source = MockSource::default();
+        // Caches that are freed on demand count as used, but also as available
+        source.set(MemorySample {
+            available: 10 * GIB,
+            ..sample(8 * GIB, 16 * GIB)
+        });
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.memory.used, 8 * GIB);
+
+        window.ui_set_used_definition(UsedDefinition::TotalMinusAvailable);
+        assert_eq!(window.memory.used, 6 * GIB);
+        window.ui_set_used_definition(UsedDefinition::Sysinfo);
+        assert_eq!(window.memory.used, 8 * GIB);
+    }
+
+    #[test]
+    fn reduced_motion_rounds_the_icon_to_quarters() {
+        let source = MockSource::default();
+        source.set(sample(7 * GIB, 20 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.icon_ratio(), 0.35);
+        window.ui_set_reduced_motion(true);
+        assert_eq!(window.icon_ratio(), 0.25);
+    }
+
+    #[test]
+    fn swap_ring_is_left_out_without_swap() {
+        let source = MockSource::default();
+        source.set(sample(7 * GIB, 20 * GIB));
+        let (window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.swap_icon_ratio(), None);
+
+        source.set(MemorySample {
+            used_swap: 3 * GIB,
+            total_swap: 4 * GIB,
+            ..sample(7 * GIB, 20 * GIB)
+        });
+        let (window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.swap_icon_ratio(), Some(0.75));
+    }
+
+    #[test]
+    fn empty_sample_has_no_usage() {
+        let source = MockSource::default();
+        source.fail(Some("no reading yet"));
+        let (window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(window.usage_ratio(), 0.0);
+        assert_eq!(window.usage_band(), UsageBand::Normal);
+        assert_eq!(window.source_error.as_deref(), Some("no reading yet"));
+    }
+
+    #[test]
+    fn peak_total_stays_put_as_the_total_fluctuates() {
+        let source = MockSource::default();
+        source.set(sample(4 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            denominator: Denominator::PeakTotal,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert_eq!(window.usage_percent(), 25.0);
+
+        // Memory is ballooned down and back up
+        source.set(sample(4 * GIB, 8 * GIB));
+        window.refresh_metrics();
+        assert_eq!(window.live_total(), 16 * GIB);
+        assert_eq!(window.usage_percent(), 25.0);
+        source.set(sample(4 * GIB, 32 * GIB));
+        window.refresh_metrics();
+        assert_eq!(window.usage_percent(), 12.5);
+
+        // A budget takes precedence
+        window.live_config.display_cap_bytes = Some(8 * GIB);
+        assert_eq!(window.usage_percent(), 50.0);
+    }
+
+    #[test]
+    fn percentage_follows_the_budget() {
+        let mut source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        assert_eq!(Metrics::sample(&mut source, Denominator::Total, Some(8 * GIB)).percentage, 75.0);
+        // Over budget is more than 100%, rather than capped
+        assert_eq!(Metrics::sample(&mut source, Denominator::Total, Some(4 * GIB)).percentage, 150.0);
+
+        let config = CosmicAppletRamConfig {
+            display_cap_bytes: Some(4 * GIB),
+            ..Default::default()
+        };
+        let (window, _dir) = window_with(config, &source);
+        assert_eq!(window.live_total(), 4 * GIB);
+        assert_eq!(window.usage_percent(), 150.0);
+        assert_eq!(window.usage_ratio(), 1.0);
+        assert_eq!(window.usage_band(), UsageBand::Critical);
+        // A budget of nothing is no budget at all
+        let config = CosmicAppletRamConfig {
+            display_cap_bytes: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(config.validated().display_cap_bytes, None);
+    }
+
+    #[test]
+    fn second_metric_follows_the_budget() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            secondary_metric: MetricSlot { metric: SlotMetric::Ram, style: SlotStyle::Percentage, prefix: None },
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 16 GiB • RAM 38%");
+
+        let _ = window.update(Message::UpdateDisplayCap(Some(8 * GIB)));
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().display_cap_bytes, Some(8 * GIB));
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 8 GiB • RAM 75%");
+
+        let _ = window.update(Message::UpdateDisplayCap(Some(0)));
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().display_cap_bytes, None);
+        assert_eq!(window.live_total(), 16 * GIB);
+    }
+
+    #[test]
+    fn percentages_over_the_total_are_capped_unless_turned_off() {
+        let source = MockSource::default();
+        source.set(sample(9 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            display_cap_bytes: Some(8 * GIB),
+            display_mode: DisplayMode::FreeAndPercent,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert_eq!(window.display_percent(f64::from(window.usage_percent())), 100.0);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "0 B free · 100%");
+
+        let _ = window.update(Message::UpdateClampPercent(false));
+        assert_eq!(window.display_percent(f64::from(window.usage_percent())), 113.0);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "0 B free · 113%");
+        assert!(!CosmicAppletRamConfig::get_entry(&window.config).unwrap().clamp_percent);
+    }
+
+    #[test]
+    fn status_round_trips_through_json() {
+        let mut source = MockSource::default();
+        source.set(sample(4 * GIB, 16 * GIB));
+        let status = Status {
+            config: CosmicAppletRamConfig {
+                source: Source::Remote { command: String::from("ssh server cat /proc/meminfo") },
+                ..Default::default()
+            },
+            metrics: Metrics::sample(&mut source, Denominator::Total, None),
+        };
+        assert_eq!(status.metrics.percentage, 25.0);
+
+        let json = serde_json::to_value(&status).unwrap();
+        // The memory statistics sit next to the percentage rather than in an object of their own
+        assert_eq!(json["metrics"]["used"], 4 * GIB);
+        assert_eq!(json["metrics"]["percentage"], 25.0);
+        assert_eq!(serde_json::from_value::<Status>(json).unwrap(), status);
+    }
+
+    #[test]
+    fn validated_clamps_out_of_range_values() {
+        let config = CosmicAppletRamConfig {
+            precision: 99,
+            update_interval: 1,
+            smoothing_factor: 100,
+            auto_hysteresis: 50,
+            swap_notify_threshold: 150,
+            warning_threshold: 95,
+            critical_threshold: 120,
+            label_prefix: "x".repeat(MAX_LABEL_LENGTH + 5),
+            popup_offset_x: -100_000,
+            popup_offset_y: 20,
+            ..Default::default()
+        }
+        .validated();
+        assert_eq!(config.precision, MAX_PRECISION);
+        assert_eq!(config.update_interval, MIN_UPDATE_INTERVAL);
+        assert_eq!(config.smoothing_factor, MAX_SMOOTHING_FACTOR);
+        assert_eq!(config.auto_hysteresis, 100);
+        assert_eq!(config.swap_notify_threshold, 100);
+        assert_eq!(config.critical_threshold, 100);
+        assert_eq!(config.warning_threshold, 95);
+        assert_eq!(config.label_prefix.chars().count(), MAX_LABEL_LENGTH);
+        assert_eq!(config.popup_offset_x, -MAX_POPUP_OFFSET);
+        assert_eq!(config.popup_offset_y, 20);
+    }
+
+    #[test]
+    fn popup_offsets_are_clamped_and_saved() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let _ = window.update(Message::UpdatePopupOffsetX(-100_000));
+        let _ = window.update(Message::UpdatePopupOffsetY(20));
+        let saved = CosmicAppletRamConfig::get_entry(&window.config).unwrap();
+        assert_eq!((saved.popup_offset_x, saved.popup_offset_y), (-MAX_POPUP_OFFSET, 20));
+        assert_eq!((window.live_config.popup_offset_x, window.live_config.popup_offset_y), (-MAX_POPUP_OFFSET, 20));
+    }
+
+    #[test]
+    fn validated_keeps_valid_values() {
+        let config = CosmicAppletRamConfig {
+            warning_threshold: 60,
+            critical_threshold: 80,
+            ..Default::default()
+        };
+        assert_eq!(config.clone().validated(), config);
+        // A warning threshold above the critical one is lowered to it
+        let config = CosmicAppletRamConfig {
+            warning_threshold: 90,
+            critical_threshold: 80,
+            ..Default::default()
+        }
+        .validated();
+        assert_eq!(config.warning_threshold, 80);
+    }
+
+    #[test]
+    fn history_window_capacity_follows_the_interval() {
+        assert_eq!(HistoryWindow::default().capacity(1000), HISTORY_CAPACITY);
+        assert_eq!(HistoryWindow::Samples(50).capacity(5000), 50);
+        assert_eq!(HistoryWindow::Minutes(10).capacity(1000), 600);
+        assert_eq!(HistoryWindow::Minutes(10).capacity(5000), 120);
+        // Never less than one sample nor more than the maximum
+        assert_eq!(HistoryWindow::Minutes(0).capacity(1000), 1);
+        assert_eq!(HistoryWindow::Minutes(u32::MAX).capacity(1000), MAX_HISTORY_CAPACITY);
+        assert_eq!(HistoryWindow::Minutes(10).max_age(), Some(600_000));
+        assert_eq!(HistoryWindow::Samples(50).max_age(), None);
+    }
+
+    #[test]
+    fn keys_step_within_the_range() {
+        let step = |value, key: Named| step_value(value, &Key::Named(key), 1, 4, 0, 16);
+        assert_eq!(step(3, Named::ArrowUp), Some(4));
+        assert_eq!(step(3, Named::ArrowDown), Some(2));
+        assert_eq!(step(3, Named::PageUp), Some(7));
+        assert_eq!(step(3, Named::PageDown), Some(0));
+        assert_eq!(step(15, Named::PageUp), Some(16));
+        assert_eq!(step(0, Named::ArrowDown), Some(0));
+        assert_eq!(step(3, Named::Home), Some(0));
+        assert_eq!(step(3, Named::End), Some(16));
+        assert_eq!(step(3, Named::Enter), None);
+        assert_eq!(step_value(3, &Key::Character("+".into()), 1, 4, 0, 16), None);
+    }
+
+    #[test]
+    fn keys_step_the_update_interval() {
+        let step = |value, key: Named| {
+            step_value(value, &Key::Named(key), INTERVAL_STEP, INTERVAL_PAGE_STEP, MIN_UPDATE_INTERVAL, MAX_UPDATE_INTERVAL)
+        };
+        assert_eq!(step(1000, Named::PageDown), Some(MIN_UPDATE_INTERVAL));
+        assert_eq!(step(1000, Named::ArrowUp), Some(1100));
+        assert_eq!(step(MAX_UPDATE_INTERVAL - 50, Named::ArrowUp), Some(MAX_UPDATE_INTERVAL));
+        assert_eq!(step(1000, Named::End), Some(MAX_UPDATE_INTERVAL));
+    }
+
+    /// The text next to the icon on a panel with this anchor and icon size, all in one string.
+    fn panel_text(window: &Window, anchor: PanelAnchor, icon_size: u16) -> String {
+        let layout = effective_layout(anchor, icon_size, &window.live_config);
+        window.panel_label(layout).into_iter().map(|(_, content)| content).collect()
+    }
+
+    /// The text next to the icon on a horizontal and a vertical panel of regular size, with
+    /// `used` of 16 GiB in use.
+    fn panel_texts(config: CosmicAppletRamConfig, used: u64) -> (String, String) {
+        let source = MockSource::default();
+        source.set(sample(used, 16 * GIB));
+        let (window, _dir) = window_with(config, &source);
+        (panel_text(&window, PanelAnchor::Bottom, 32), panel_text(&window, PanelAnchor::Left, 32))
+    }
+
+    #[test]
+    fn panel_label_by_default() {
+        let (horizontal, vertical) = panel_texts(CosmicAppletRamConfig::default(), 6 * GIB);
+        assert_eq!(horizontal, "6 GiB / 16 GiB");
+        assert_eq!(vertical, "6 GiB / 16 GiB");
+    }
+
+    #[test]
+    fn panel_label_standard() {
+        let config = CosmicAppletRamConfig {
+            standard: Standard::Si,
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config, 6 * GIB).0, "6 GB / 17 GB");
+    }
+
+    #[test]
+    fn panel_label_prefix() {
+        let config = CosmicAppletRamConfig {
+            prefix: Prefix::Mega,
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config, 6 * GIB).0, "6144 MiB / 16384 MiB");
+    }
+
+    #[test]
+    fn combined_view_adds_swap_to_both_values() {
+        let source = MockSource::default();
+        source.set(MemorySample {
+            used_swap: 2 * GIB,
+            total_swap: 4 * GIB,
+            ..sample(6 * GIB, 16 * GIB)
+        });
+        let config = CosmicAppletRamConfig {
+            combined_view: true,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert_eq!(window.combined_usage(), Some((8 * GIB, 20 * GIB)));
+        assert_eq!(window.combined_item(), Some(("RAM+Swap", String::from("8 GiB / 20 GiB"))));
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "8 GiB / 20 GiB");
+
+        // Without swap, the combined values are those of RAM alone
+        source.set(sample(6 * GIB, 16 * GIB));
+        window.refresh_metrics();
+        assert_eq!(window.combined_usage(), Some((6 * GIB, 16 * GIB)));
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 16 GiB");
+
+        source.set(MemorySample {
+            used_swap: u64::MAX,
+            total_swap: u64::MAX,
+            ..sample(6 * GIB, 16 * GIB)
+        });
+        window.refresh_metrics();
+        assert_eq!(window.combined_usage(), Some((u64::MAX, u64::MAX)));
+
+        let _ = window.update(Message::UpdateCombinedView(false));
+        assert_eq!(window.combined_usage(), None);
+        assert_eq!(window.combined_item(), None);
+    }
+
+    #[test]
+    fn panel_label_prefix_locked_to_total() {
+        let config = CosmicAppletRamConfig {
+            precision: 1,
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config.clone(), 900 * MIB).0, "900.0 MiB / 16.0 GiB");
+        let config = CosmicAppletRamConfig {
+            lock_prefix_to_total: true,
+            ..config
+        };
+        assert_eq!(panel_texts(config, 900 * MIB).0, "0.9 GiB / 16.0 GiB");
+    }
+
+    #[test]
+    fn panel_label_precision() {
+        let config = CosmicAppletRamConfig {
+            precision: 2,
+            ..Default::default()
+        };
+        let (horizontal, vertical) = panel_texts(config, 6 * GIB + GIB / 2);
+        assert_eq!(horizontal, "6.50 GiB / 16.00 GiB");
+        assert_eq!(vertical, "6.50 GiB / 16.00 GiB");
+    }
+
+    #[test]
+    fn panel_label_total() {
+        let config = CosmicAppletRamConfig {
+            show_total: false,
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config, 6 * GIB), (String::from("6 GiB"), String::from("6 GiB")));
+        // Vertical panels have a setting of their own
+        let config = CosmicAppletRamConfig {
+            show_total_vertical: false,
+            ..Default::default()
+        };
+        let (horizontal, vertical) = panel_texts(config, 6 * GIB);
+        assert_eq!(horizontal, "6 GiB / 16 GiB");
+        assert_eq!(vertical, "6 GiB");
+    }
+
+    #[test]
+    fn panel_label_labels_and_display_mode() {
+        let config = CosmicAppletRamConfig {
+            label_prefix: String::from("RAM "),
+            label_suffix: String::from(" used"),
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config, 6 * GIB).0, "RAM 6 GiB / 16 GiB used");
+        // Empty labels leave the panel text exactly as it was, without empty pieces around it
+        let config = CosmicAppletRamConfig {
+            label_prefix: String::new(),
+            label_suffix: String::new(),
+            ..Default::default()
+        };
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (window, _dir) = window_with(config, &source);
+        let pieces = window.panel_label(effective_layout(PanelAnchor::Bottom, 32, &window.live_config));
+        assert!(pieces.iter().all(|(_, content)| !content.is_empty()));
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 16 GiB");
+        // Only the full display mode has text next to the icon
+        let config = CosmicAppletRamConfig {
+            display_mode: DisplayMode::IconOnly,
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config, 6 * GIB), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn placeholder_from_elsewhere_is_cut_to_length() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let config = CosmicAppletRamConfig {
+            placeholder: "…".repeat(MAX_LABEL_LENGTH + 1),
+            ..window.live_config.clone()
+        };
+        let _ = window.update(Message::ConfigChanged(config));
+        assert_eq!(window.live_config.placeholder, "…".repeat(MAX_LABEL_LENGTH));
+    }
+
+    #[test]
+    fn icon_only_panel_still_names_the_usage() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            display_mode: DisplayMode::IconOnly,
+            ..Default::default()
+        };
+        let (window, _dir) = window_with(config, &source);
+        assert_eq!(window.tooltip_text(), "6 GiB / 16 GiB");
+    }
+
+    #[test]
+    fn tooltip_names_the_session_unless_it_fell_back() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            source: Source::Session,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert_eq!(window.tooltip_text(), "Session used: 6 GiB / 16 GiB");
+
+        source.fall_back(true);
+        window.refresh_metrics();
+        assert_eq!(window.tooltip_text(), "System used: 6 GiB / 16 GiB");
+    }
+
+    #[test]
+    fn panel_label_of_swap() {
+        let source = MockSource::default();
+        source.set(MemorySample {
+            used_swap: GIB,
+            total_swap: 4 * GIB,
+            ..sample(6 * GIB, 16 * GIB)
+        });
+        let config = CosmicAppletRamConfig {
+            monitor_target: MonitorTarget::Swap,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "1 GiB / 4 GiB");
+        window.ui_set_monitor_target(MonitorTarget::Both);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 16 GiB | 1 GiB / 4 GiB");
+
+        let config = CosmicAppletRamConfig {
+            monitor_target: MonitorTarget::Swap,
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config, 6 * GIB).0, "no swap");
+    }
+
+    #[test]
+    fn only_numbers_are_monospaced() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert!(!window.monospaced(Segment::Used));
+        window.ui_set_monospace_digits(true);
+        let monospaced: Vec<(bool, String)> = window.panel_label(effective_layout(PanelAnchor::Bottom, 32, &window.live_config))
+            .into_iter()
+            .map(|(segment, content)| (window.monospaced(segment), content))
+            .collect();
+        assert_eq!(monospaced, [
+            (true, String::from("6 GiB")),
+            (false, String::from(" / ")),
+            (true, String::from("16 GiB")),
+        ]);
+
+        // Swap, the second metric and the composed display modes are numbers as well
+        source.set(MemorySample {
+            used_swap: GIB,
+            total_swap: 4 * GIB,
+            ..sample(6 * GIB, 16 * GIB)
+        });
+        let config = CosmicAppletRamConfig {
+            monospace_digits: true,
+            monitor_target: MonitorTarget::Both,
+            label_prefix: String::from("RAM "),
+            secondary_metric: MetricSlot { metric: SlotMetric::Swap, style: SlotStyle::Percentage, prefix: None },
+            ..Default::default()
+        };
+        let (window, _dir) = window_with(config.clone(), &source);
+        let monospaced = |window: &Window| window.panel_label(effective_layout(PanelAnchor::Bottom, 32, &window.live_config))
+            .into_iter()
+            .filter(|&(segment, _)| window.monospaced(segment))
+            .map(|(_, content)| content)
+            .collect::<Vec<_>>();
+        // Words around the numbers, such as the name of the second metric, are not
+        assert_eq!(monospaced(&window), ["6 GiB", "16 GiB", "1 GiB", "4 GiB", "25%"]);
+        for (display_mode, numbers) in [
+            (DisplayMode::FreeAndPercent, vec!["10 GiB", "38%", "25%"]),
+            (DisplayMode::Ratio, vec!["6/16", "25%"]),
+        ] {
+            let config = CosmicAppletRamConfig {
+                display_mode,
+                ..config.clone()
+            };
+            let (window, _dir) = window_with(config, &source);
+            assert_eq!(monospaced(&window), numbers);
+        }
+    }
+
+    #[test]
+    fn swap_can_take_the_prefix_of_ram() {
+        let source = MockSource::default();
+        source.set(MemorySample {
+            used_swap: 512 * MIB,
+            total_swap: 4 * GIB,
+            ..sample(6 * GIB, 16 * GIB)
+        });
+        let config = CosmicAppletRamConfig {
+            monitor_target: MonitorTarget::Both,
+            precision: 1,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        let swap = |window: &Window| panel_text(window, PanelAnchor::Bottom, 32).split(" | ").nth(1).map(String::from);
+        assert_eq!(swap(&window).as_deref(), Some("512.0 MiB / 4.0 GiB"));
+        window.ui_set_swap_matches_ram(true);
+        assert_eq!(swap(&window).as_deref(), Some("0.5 GiB / 4.0 GiB"));
+    }
+
+    #[test]
+    fn panel_label_of_free_and_percent() {
+        let config = CosmicAppletRamConfig {
+            display_mode: DisplayMode::FreeAndPercent,
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config.clone(), 12 * GIB).0, "4 GiB free · 75%");
+        assert_eq!(free_and_percent("0 B", None, &iec()), [
+            (Segment::Number, String::from("0 B")),
+            (Segment::Plain, String::from(" free")),
+        ]);
+        let config = CosmicAppletRamConfig {
+            precision: 1,
+            decimal_comma: true,
+            ..config
+        };
+        assert_eq!(panel_texts(config.clone(), 12 * GIB).0, "4,0 GiB free · 75,0%");
+        // The decimals of the percentage are real ones
+        assert_eq!(panel_texts(config.clone(), 12 * GIB + 512 * MIB).0, "3,5 GiB free · 78,1%");
+        let config = CosmicAppletRamConfig {
+            percent_step: 5,
+            ..config
+        };
+        assert_eq!(panel_texts(config, 12 * GIB + 512 * MIB).0, "3,5 GiB free · 80,0%");
+    }
+
+    #[test]
+    fn panel_label_of_a_ratio_without_units() {
+        let config = CosmicAppletRamConfig {
+            display_mode: DisplayMode::Ratio,
+            ..Default::default()
+        };
+        assert_eq!(panel_texts(config, 6 * GIB).0, "6/16");
+        // The used memory takes the prefix of the total
+        assert_eq!(terse_ratio(512 * MIB, 16 * GIB, &iec()), "0.5/16.0");
+        assert_eq!(terse_ratio(0, 0, &iec()), "0/0");
+    }
+
+    #[test]
+    fn headroom_shows_free_memory_with_the_same_bands() {
+        let source = MockSource::default();
+        source.set(sample(15 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            show_headroom: true,
+            ..Default::default()
+        };
+        let (window, _dir) = window_with(config, &source);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "1 GiB free / 16 GiB");
+        // Little free memory is critical, like much used memory
+        assert_eq!(window.usage_band(), UsageBand::Critical);
+    }
+
+    thread_local! {
+        /// The commands that [`record_spawn`] was asked to start on this thread.
+        static SPAWNED: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+    }
+
+    /// Remember the command rather than starting it.
+    fn record_spawn(command: &str) -> std::io::Result<()> {
+        SPAWNED.with_borrow_mut(|spawned| spawned.push(String::from(command)));
+        Ok(())
+    }
+
+    #[test]
+    fn failed_exits_are_errors() {
+        use std::os::unix::process::ExitStatusExt;
+        assert!(exit_result("xdg-open", std::process::ExitStatus::from_raw(0)).is_ok());
+        // The raw status holds the exit code in its second byte
+        let error = exit_result("xdg-open", std::process::ExitStatus::from_raw(3 << 8)).unwrap_err();
+        assert_eq!(error.to_string(), "xdg-open exit status: 3");
+    }
+
+    #[test]
+    fn rapid_launches_spawn_one_monitor() {
+        let config = CosmicAppletRamConfig {
+            system_monitor_command: String::from("observatory"),
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &MockSource::default());
+        window.spawn = record_spawn;
+        let _ = window.update(Message::LaunchSystemMonitor);
+        let _ = window.update(Message::LaunchSystemMonitor);
+        assert!(window.launching());
+        assert_eq!(SPAWNED.with_borrow(Vec::clone), ["observatory"]);
+    }
+
+    #[test]
+    fn recorded_samples_are_published_for_dbus() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let history = window.history_tx.subscribe();
+        assert!(history.borrow().is_empty());
+
+        window.record_sample();
+        source.set(sample(7 * GIB, 16 * GIB));
+        window.refresh_metrics();
+        window.record_sample();
+        let used: Vec<u64> = history.borrow().iter().map(|&(_, used)| used).collect();
+        assert_eq!(used, [6 * GIB, 7 * GIB]);
+        assert_eq!(*history.borrow(), window.history.samples(usize::MAX));
+
+        // Changes to the history that aren't samples are published as well
+        source.set(sample(5 * GIB, 6 * GIB));
+        window.refresh_metrics();
+        let used: Vec<u64> = history.borrow().iter().map(|&(_, used)| used).collect();
+        assert_eq!(used, [6 * GIB, 6 * GIB]);
+    }
+
+    #[test]
+    fn shrinking_total_lowers_earlier_usage() {
+        let config = CosmicAppletRamConfig {
+            smoothing_factor: 50,
+            ..Default::default()
+        };
+        let source = MockSource::default();
+        source.set(sample(14 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(config, &source);
+        window.record_sample();
+
+        source.set(sample(7 * GIB, 8 * GIB));
+        window.refresh_metrics();
+        window.record_sample();
+        // Halfway between the earlier usage, lowered to the new total, and the latest sample
+        assert_eq!(window.live_used(), 7 * GIB + GIB / 2);
+        assert_eq!(window.history.stats().map(|stats| stats.max), Some(8 * GIB));
+    }
+
+    #[test]
+    fn moving_average_warms_up_then_slides() {
+        let config = CosmicAppletRamConfig {
+            smoothing_mode: SmoothingMode::MovingAverage,
+            moving_average_window: 3,
+            ..Default::default()
+        };
+        let source = MockSource::default();
+        source.set(sample(3 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(config, &source);
+        let mut refresh = |used| {
+            source.set(sample(used, 16 * GIB));
+            window.refresh_metrics();
+            window.live_used()
+        };
+        // Fewer samples than the window are averaged as they are
+        assert_eq!(refresh(6 * GIB), 3 * GIB + 3 * GIB / 2);
+        assert_eq!(refresh(9 * GIB), 6 * GIB);
+        // The first sample drops out
+        assert_eq!(refresh(12 * GIB), 9 * GIB);
+        window.ui_set_moving_average_window(1);
+        assert_eq!(window.live_used(), 12 * GIB);
+    }
+
+    #[test]
+    fn advanced_prefix_persists_and_displays() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let _ = window.update(Message::UpdateAdvancedUnits(true));
+        let _ = window.update(Message::UpdatePrefix(Prefix::Peta));
+        let saved = CosmicAppletRamConfig::get_entry(&window.config).unwrap();
+        assert!(saved.advanced_units);
+        assert_eq!(saved.prefix, Prefix::Peta);
+        assert_eq!(PREFIX_MENU_ITEMS[6], "Peta");
+        window.ui_set_precision(6);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "0.000006 PiB / 0.000015 PiB");
+    }
+
+    #[test]
+    fn panel_label_with_a_second_metric() {
+        let source = MockSource::default();
+        source.set(MemorySample {
+            used_swap: GIB,
+            total_swap: 4 * GIB,
+            ..sample(6 * GIB, 16 * GIB)
+        });
+        let slot = |metric, style, prefix| CosmicAppletRamConfig {
+            secondary_metric: MetricSlot { metric, style, prefix },
+            ..Default::default()
+        };
+        let text = |config| {
+            let (window, _dir) = window_with(config, &source);
+            panel_text(&window, PanelAnchor::Bottom, 32)
+        };
+        assert_eq!(text(slot(SlotMetric::Disabled, SlotStyle::Amount, None)), "6 GiB / 16 GiB");
+        assert_eq!(text(slot(SlotMetric::Swap, SlotStyle::Percentage, None)), "6 GiB / 16 GiB • Swap 25%");
+        assert_eq!(text(slot(SlotMetric::Ram, SlotStyle::Amount, None)), "6 GiB / 16 GiB • RAM 6 GiB");
+        assert_eq!(
+            text(slot(SlotMetric::Swap, SlotStyle::Amount, Some(Prefix::Mega))),
+            "6 GiB / 16 GiB • Swap 1024 MiB",
+        );
+        let config = CosmicAppletRamConfig {
+            metric_separator: String::from(" | "),
+            ..slot(SlotMetric::Swap, SlotStyle::Amount, None)
+        };
+        assert_eq!(text(config), "6 GiB / 16 GiB | Swap 1 GiB");
+    }
+
+    #[test]
+    fn clicking_segments_switches_what_they_show() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            clickable_segments: true,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        let layout = effective_layout(PanelAnchor::Bottom, 32, &window.live_config);
+        let segments = window.panel_label(layout).into_iter().map(|(segment, _)| segment).collect::<Vec<_>>();
+        assert_eq!(segments, [Segment::Used, Segment::Plain, Segment::Total]);
+
+        let _ = window.update(Message::SegmentClicked(Segment::Used));
+        assert!(window.live_config.show_headroom);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "10 GiB free / 16 GiB");
+        let _ = window.update(Message::SegmentClicked(Segment::Total));
+        assert_eq!(window.live_config.denominator, Denominator::UsedAndAvailable);
+        let _ = window.update(Message::SegmentClicked(Segment::Plain));
+        assert!(window.live_config.show_headroom);
+        assert_eq!(window.live_config.denominator, Denominator::UsedAndAvailable);
+        // Both are saved, as if they were changed in the popup
+        let saved = CosmicAppletRamConfig::get_entry(&window.config).unwrap();
+        assert!(saved.show_headroom);
+        assert_eq!(saved.denominator, Denominator::UsedAndAvailable);
+    }
+
+    #[test]
+    fn panel_label_waits_for_the_first_sample() {
+        let source = MockSource::default();
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "—");
+        assert_eq!(window.usage_summary(), "Waiting for the first sample");
+
+        source.set(sample(6 * GIB, 16 * GIB));
+        window.refresh_metrics();
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 16 GiB");
+        // Switching sources waits for the new one
+        window.ui_set_source(Source::Remote { command: String::from("false") });
+        assert!(!window.has_sample);
+    }
+
+    /// What `/proc/meminfo` could hold on a machine with 6 GiB of 16 GiB in use and no swap.
+    const MEMINFO_FIXTURE: &str = "MemTotal: 16777216 kB\nMemFree: 10485760 kB\nMemAvailable: 10485760 kB\nSwapTotal: 0 kB\n";
+
+    /// Create sources like the applet does, except that `/proc/meminfo` is [`MEMINFO_FIXTURE`].
+    fn fixture_source(source: &Source, interval: u64) -> Box<dyn MetricsSource> {
+        match source {
+            Source::ProcMeminfo => Box::new(ProcMeminfoSource::with_reader(|| Ok(String::from(MEMINFO_FIXTURE)))),
+            source => create_source(source, interval),
+        }
+    }
+
+    #[test]
+    fn restarting_the_source_starts_it_over() {
+        let source = MockSource::default();
+        source.fail(Some("wedged"));
+        let config = CosmicAppletRamConfig {
+            source: Source::ProcMeminfo,
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        window.create_source = fixture_source;
+        assert!(window.source_error.is_some());
+
+        // The new source is one of the configured kind, which the failing one only stood in for
+        let _ = window.update(Message::RestartSource);
+        assert_eq!(window.source_error, None);
+        assert_eq!(window.memory, sample(6 * GIB, 16 * GIB));
+    }
+
+    #[test]
+    fn processes_are_pinned_once_and_unpinned_by_position() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        for name in ["no-such-process-for-the-ram-applet", "  another-missing-process ", "NO-SUCH-PROCESS-FOR-THE-RAM-APPLET", ""] {
+            let _ = window.update(Message::UpdatePinnedProcessText(String::from(name)));
+            let _ = window.update(Message::PinProcess);
+        }
+        assert_eq!(window.live_config.pinned_processes, ["no-such-process-for-the-ram-applet", "another-missing-process"]);
+        assert_eq!(window.pinned_process_memory, [None, None]);
+        assert!(window.pinned_process_text.is_empty());
+
+        let _ = window.update(Message::UnpinProcess(0));
+        let _ = window.update(Message::UnpinProcess(5));
+        let saved = CosmicAppletRamConfig::get_entry(&window.config).unwrap();
+        assert_eq!(saved.pinned_processes, ["another-missing-process"]);
+        assert_eq!(window.pinned_process_memory, [None]);
+    }
+
+    #[test]
+    fn pinned_processes_add_up_every_instance() {
+        let mut sleeps: Vec<_> = (0..2)
+            .map(|_| std::process::Command::new("sleep").arg("30").spawn().expect("failed to run sleep"))
+            .collect();
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let own = Pid::from_u32(std::process::id());
+        window.sys.refresh_processes(ProcessesToUpdate::Some(&[own]), true);
+        let own_name = window.sys.process(own).unwrap().name().to_string_lossy().into_owned();
+
+        // Names are matched regardless of case
+        window.ui_set_pinned_processes(vec![own_name.to_uppercase(), String::from("SLEEP")]);
+        let memory = |pid: u32| window.sys.process(Pid::from_u32(pid)).map_or(0, |process| process.memory());
+        let own_memory = memory(std::process::id());
+        let sleep_memory: Vec<u64> = sleeps.iter().map(|sleep| memory(sleep.id())).collect();
+        for sleep in &mut sleeps {
+            let _ = sleep.kill();
+            let _ = sleep.wait();
+        }
+        let [Some(own_total), Some(sleep_total)] = window.pinned_process_memory[..] else {
+            panic!("pinned processes weren't found: {:?}", window.pinned_process_memory)
+        };
+        assert!(own_memory > 0 && own_total >= own_memory);
+        // Other processes by the same name may be running as well
+        assert!(sleep_memory.iter().all(|&memory| memory > 0));
+        assert!(sleep_total >= sleep_memory.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn usage_log_path_is_applied_once_submitted() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let path = dir.path().join("logs").join("usage.csv");
+        let _ = window.update(Message::UpdateUsageLogPathText(String::from("/")));
+        let _ = window.update(Message::UpdateUsageLogPathText(path.display().to_string()));
+        assert!(window.usage_log.is_none());
+
+        let _ = window.update(Message::UpdateUsageLogPath(window.usage_log_path_text.clone()));
+        assert!(window.usage_log.is_some());
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().usage_log_path, path.display().to_string());
+        window.record_sample();
+        drop(window);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn critical_action_command_is_applied_once_submitted() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let _ = window.update(Message::UpdateCriticalActionCommandText(String::from("notify-send")));
+        assert!(window.live_config.critical_action_command.is_empty());
+
+        let _ = window.update(Message::UpdateCriticalActionCommand(String::from("notify-send critical")));
+        assert_eq!(window.critical_action_command_text, "notify-send critical");
+        let saved = CosmicAppletRamConfig::get_entry(&window.config).unwrap();
+        assert_eq!(saved.critical_action_command, "notify-send critical");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn critical_action_runs_once_per_rising_edge() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            critical_action_command: String::from("notify-send critical"),
+            ..Default::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        window.spawn = record_spawn;
+        let mut tick_at = |used| {
+            source.set(sample(used, 16 * GIB));
+            let _ = window.update(Message::Tick);
+        };
+        tick_at(15 * GIB);
+        tick_at(15 * GIB);
+        assert_eq!(SPAWNED.with_borrow(Vec::clone), ["notify-send critical"]);
+
+        // Dipping below the threshold and back right away doesn't run it again either
+        tick_at(6 * GIB);
+        tick_at(15 * GIB);
+        assert_eq!(SPAWNED.with_borrow(Vec::len), 1);
+
+        tick_at(6 * GIB);
+        time::advance(alerts::ALERT_COOLDOWN).await;
+        tick_at(15 * GIB);
+        tick_at(15 * GIB);
+        assert_eq!(SPAWNED.with_borrow(Vec::len), 2);
+    }
+
+    #[test]
+    fn icon_name_is_applied_once_submitted_and_warned_about_once() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let counter = EventCounter::new(tracing::Level::WARN);
+        tracing::subscriber::with_default(counter.clone(), || {
+            for text in ["n", "no-such", "no-such-icon"] {
+                let _ = window.update(Message::UpdateIconNameText(String::from(text)));
+            }
+        });
+        assert_eq!(counter.count(), 0);
+        assert_eq!(window.live_config.icon_name, DEFAULT_ICON_NAME);
+
+        tracing::subscriber::with_default(counter.clone(), || {
+            let _ = window.update(Message::UpdateIconName(window.icon_name_text.clone()));
+            let _ = window.update(Message::UpdateIconName(String::from("no-such-icon")));
+        });
+        assert_eq!(counter.count(), 1);
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().icon_name, "no-such-icon");
+        assert_eq!(window.icon_name, DEFAULT_ICON_NAME);
+        assert_eq!(
+            window.icon_warning.as_deref(),
+            Some("Icon 'no-such-icon' not found, keeping 'display-symbolic'"),
+        );
+    }
+
+    #[test]
+    fn text_color_is_applied_once_submitted() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        for text in ["#", "#ff", "#ff0000"] {
+            let _ = window.update(Message::UpdateTextColorText(String::from(text)));
+        }
+        assert_eq!(window.panel_text_color(), None);
+
+        let _ = window.update(Message::UpdateTextColor(window.text_color_text.clone()));
+        assert_eq!(window.panel_text_color(), Some(Color::from_rgb(1.0, 0.0, 0.0)));
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().text_color, "#ff0000");
+    }
+
+    #[test]
+    fn popup_shortcut_is_applied_once_submitted() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        // Halfway through typing, the combination is neither parsed nor saved
+        for text in ["S", "Super+", "Super+R"] {
+            let _ = window.update(Message::UpdatePopupShortcutText(String::from(text)));
+        }
+        assert!(window.shortcut.is_none());
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().popup_shortcut, "");
+
+        let _ = window.update(Message::UpdatePopupShortcut(window.popup_shortcut_text.clone()));
+        assert!(window.shortcut.is_some());
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().popup_shortcut, "Super+R");
+
+        // A combination from elsewhere replaces what is in the text input
+        let config = CosmicAppletRamConfig {
+            popup_shortcut: String::from("Ctrl+M"),
+            ..window.live_config.clone()
+        };
+        let _ = window.update(Message::ConfigChanged(config));
+        assert_eq!(window.popup_shortcut_text, "Ctrl+M");
+    }
+
+    #[test]
+    fn static_total_is_formatted_again_when_it_changes() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            static_total: true,
+            ..CosmicAppletRamConfig::default()
+        };
+        let (mut window, _dir) = window_with(config, &source);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 16 GiB");
+        assert!(window.total_text.borrow().is_some());
+
+        source.set(sample(6 * GIB, 8 * GIB));
+        let _ = window.update(Message::Tick);
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 8 GiB");
+
+        let _ = window.update(Message::UpdatePrecision(1));
+        assert!(window.total_text.borrow().is_none());
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6.0 GiB / 8.0 GiB");
+    }
+
+    #[test]
+    fn panel_label_for_a_process() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        window.ui_set_process_target(Some(String::from("no-such-process-for-the-ram-applet")));
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "not running");
+
+        // The test itself is a process that is certain to be running
+        window.ui_set_process_target(Some(std::process::id().to_string()));
+        assert!(window.process_memory.is_some_and(|memory| memory > 0));
+        assert!(panel_text(&window, PanelAnchor::Bottom, 32).ends_with(" / 16 GiB"));
+    }
+
+    #[test]
+    fn process_matches_are_scanned_again_once_one_is_gone() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let pid = Pid::from_u32(std::process::id());
+        window.ui_set_process_target(Some(pid.to_string()));
+        let name = window.sys.process(pid).unwrap().name().to_string_lossy().into_owned();
+
+        let _ = window.update(Message::UpdateProcessTargetText(name.clone()));
+        assert_eq!(window.live_config.process_target, Some(pid.to_string()));
+        let _ = window.update(Message::UpdateProcessTarget(name));
+        assert!(window.process_pids.contains(&pid));
+        assert!(window.process_memory.is_some_and(|memory| memory > 0));
+
+        // A match that is gone leads to a scan, which leaves it out
+        let gone = Pid::from_u32(u32::MAX);
+        window.process_pids.push(gone);
+        window.refresh_process_memory();
+        assert!(window.process_pids.contains(&pid));
+        assert!(!window.process_pids.contains(&gone));
+    }
+
+    #[test]
+    fn panel_label_shows_when_numbers_are_not_live() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        assert!(window.is_live());
+        window.hover_frozen = Some((5 * GIB, 16 * GIB));
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "5 GiB ⏸ / 16 GiB");
+        window.hover_frozen = None;
+        window.stalled = true;
+        assert!(!window.is_live());
+        window.refresh_metrics();
+        assert_eq!(panel_text(&window, PanelAnchor::Bottom, 32), "6 GiB / 16 GiB");
+    }
+
+    #[test]
+    fn panel_label_on_a_thin_panel() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB + GIB / 2, 16 * GIB));
+        let config = CosmicAppletRamConfig {
+            precision: 2,
+            ..Default::default()
+        };
+        let (window, _dir) = window_with(config, &source);
+        // There is no room for the total, a space or more than one decimal
+        assert_eq!(panel_text(&window, PanelAnchor::Top, COMPACT_ICON_SIZE), "6.5GiB");
+        assert_eq!(panel_text(&window, PanelAnchor::Right, COMPACT_ICON_SIZE), "6.5GiB");
+    }
+
+    #[test]
+    fn tight_layout_leaves_out_the_icon_padding() {
+        assert_eq!(icon_padding(4u16, false), 4);
+        assert_eq!(icon_padding(4u16, true), 0);
+    }
+
+    #[test]
+    fn right_to_left_languages() {
+        assert!(language_is_rtl("ar_EG.UTF-8"));
+        assert!(language_is_rtl("he"));
+        assert!(language_is_rtl("ug_CN"));
+        assert!(!language_is_rtl("ug@latin"));
+        assert!(!language_is_rtl("ug_CN.UTF-8@latin"));
+        assert!(!language_is_rtl("en_US.UTF-8"));
+        assert!(!language_is_rtl("arn_CL"));
+        assert!(!language_is_rtl(""));
+    }
 
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        parent_win_id,
-                        new_id,
-                        None,
-                        None,
-                        None
-                    );
+    #[test]
+    fn right_to_left_mirrors_horizontal_panels() {
+        let children = || vec!["icon", "6 GiB", " / ", "16 GiB"];
+        let horizontal = PanelLayout { horizontal: true, show_total: true, show_icon: true, compact: false };
+        let vertical = PanelLayout { horizontal: false, ..horizontal };
+        assert_eq!(panel_order(children(), horizontal, false), children());
+        assert_eq!(panel_order(children(), horizontal, true), ["16 GiB", " / ", "6 GiB", "icon"]);
+        assert_eq!(panel_order(children(), vertical, true), children());
+    }
 
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .max_width(372.0)
-                        .min_width(300.0)
-                        .min_height(200.0)
-                        .max_height(1080.0);
+    #[test]
+    fn layout_follows_each_anchor() {
+        let anchors = [PanelAnchor::Top, PanelAnchor::Bottom, PanelAnchor::Left, PanelAnchor::Right];
+        let layouts = |config: &CosmicAppletRamConfig| {
+            anchors.map(|anchor| {
+                let layout = effective_layout(anchor, 32, config);
+                (layout.horizontal, layout.show_total)
+            })
+        };
+        // Top and bottom panels lay out in a row, left and right ones in a column
+        assert_eq!(layouts(&CosmicAppletRamConfig::default()), [(true, true), (true, true), (false, true), (false, true)]);
+        let config = CosmicAppletRamConfig {
+            show_total_vertical: false,
+            ..Default::default()
+        };
+        assert_eq!(layouts(&config), [(true, true), (true, true), (false, false), (false, false)]);
+    }
 
-                    get_popup(popup_settings)
-                }
-            }
-            // Unset the popup field after it has been closed
-            Message::PopupClosed(popup_id) => {
-                if self.popup.as_ref() == Some(&popup_id) {
-                    self.popup = None;
-                }
-            }
-            Message::Tick => {
-                self.refresh_metrics();
-            }
-            Message::UpdatePrecision(prec) => {
-                self.live_config
-                    .set_precision(&self.config, prec)
-                    .log("Failed to save applet configuration");
-                self.ui_set_precision(prec);
-                self.refresh_metrics();
-            }
-            Message::UpdateStandard(standard) => {
-                self.live_config
-                    .set_standard(&self.config, standard)
-                    .log("Failed to save applet configuration");
-                self.ui_set_standard(standard);
-                self.refresh_metrics();
-            }
-            Message::UpdateShowTotal(enable) => {
-                self.live_config
-                    .set_show_total(&self.config, enable)
-                    .log("Failed to save applet configuration");
-                self.ui_set_show_total(enable);
-                self.refresh_metrics();
-            }
-            Message::UpdatePrefix(prefix) => {
-                self.live_config
-                    .set_prefix(&self.config, prefix)
-                    .log("Failed to save applet configuration");
-                self.ui_set_prefix(prefix);
-                self.refresh_metrics();
-            }
-            Message::UpdateInterval(text) => {
-                if let Ok(msec) = text.parse::<u64>() {
-                    if msec > 0 {
-                        self.live_config
-                            .set_update_interval(&self.config, msec)
-                            .log("save configuration failed");
-                        self.set_ticks(msec);
-                    }
-                }
-                self.update_interval_text = text;
-            }
-            Message::Surface(a) => return cosmic::task::message(cosmic::Action::Cosmic(
-                cosmic::app::Action::Surface(a)
-            )),
-            Message::ConfigChanged(config) => {
-                if config.precision != self.live_config.precision {
-                    self.ui_set_precision(config.precision);
-                }
-                if config.prefix != self.live_config.prefix {
-                    self.ui_set_prefix(config.prefix);
-                }
-                if config.show_total != self.live_config.show_total {
-                    self.ui_set_show_total(config.show_total);
-                }
-                if config.standard != self.live_config.standard {
-                    self.ui_set_standard(config.standard);
-                }
-                if config.update_interval != self.live_config.update_interval {
-                    self.ui_set_update_interval(config.update_interval);
-                }
-            }
+    #[test]
+    fn icon_can_be_hidden_on_vertical_panels_only() {
+        let anchors = [PanelAnchor::Top, PanelAnchor::Bottom, PanelAnchor::Left, PanelAnchor::Right];
+        let show_icon = |config: &CosmicAppletRamConfig| {
+            anchors.map(|anchor| effective_layout(anchor, 32, config).show_icon)
+        };
+        assert_eq!(show_icon(&CosmicAppletRamConfig::default()), [true; 4]);
+        let config = CosmicAppletRamConfig {
+            show_icon_vertical: false,
+            ..Default::default()
+        };
+        assert_eq!(show_icon(&config), [true, true, false, false]);
+    }
+
+    /// Take `count` ticks from `timer`, and return how long after `start` the last one came.
+    async fn ticks(timer: &mut time::Interval, count: usize, start: time::Instant) -> time::Duration {
+        for _ in 0..count {
+            timer.tick().await;
         }
-        Task::none() // Again not doing anything that requires multi-threading here.
+        start.elapsed()
     }
 
-    /*
-    *  For an applet, the view function describes what an applet looks like. There's a
-    *  secondary view function (view_window) that shows the widgets in the popup when it's
-    *  opened.
-    */
-    fn view(&self) -> Element<Self::Message> {
-        let horizontal = matches!(
-            self.core.applet.anchor,
-            PanelAnchor::Top | PanelAnchor::Bottom
-        );
+    #[tokio::test(start_paused = true)]
+    async fn burst_makes_up_for_missed_ticks() {
+        let settings = TickSettings { msec: 1000, missed: MissedTicks::Burst };
+        let mut timer = new_timer(settings, false);
+        // As if the applet was busy for five and a half intervals
+        time::advance(time::Duration::from_millis(5500)).await;
+        let start = time::Instant::now();
+        assert_eq!(ticks(&mut timer, 5, start).await, time::Duration::ZERO);
+        // Caught up, the original schedule continues
+        assert_eq!(ticks(&mut timer, 1, start).await, time::Duration::from_millis(500));
+        assert_eq!(ticks(&mut timer, 1, start).await, time::Duration::from_millis(1500));
+    }
 
-        let padding = self.core.applet.suggested_padding(false);
-        let icon = container(icon::from_name("display-symbolic"))
-            .padding(padding);
-        let usage = self.core.applet.text(
-            format_bytes(
-                self.used,
-                self.live_config.standard,
-                self.live_config.prefix,
-                self.live_config.precision
-            )
-        );
-        let mut children = vec![
-            Element::from(icon), Element::from(usage)
-        ];
-        if self.live_config.show_total {
-            let total = self.core.applet.text(
-                format_bytes(
-                    self.total,
-                    self.live_config.standard,
-                    self.live_config.prefix,
-                    self.live_config.precision
-                )
-            );
-            children.push(Element::from(self.core.applet.text(" / ")));
-            children.push(Element::from(total));
-        }
-        let button = button::custom(
-            if horizontal {
-                Element::from(cosmic::widget::row::with_children(children).align_y(Center))
-            } else {
-                Element::from(cosmic::widget::column::with_children(children).align_x(Center))
-            },
-        )
-        .on_press_down(Message::TogglePopup)
-        .class(cosmic::theme::Button::AppletIcon);
+    #[tokio::test(start_paused = true)]
+    async fn skip_and_delay_tick_once_after_missed_ticks() {
+        let skip = TickSettings { msec: 1000, missed: MissedTicks::Skip };
+        let delay = TickSettings { msec: 1000, missed: MissedTicks::Delay };
+        let mut skip_timer = new_timer(skip, false);
+        let mut delay_timer = new_timer(delay, false);
+        time::advance(time::Duration::from_millis(5500)).await;
+        let start = time::Instant::now();
+        assert_eq!(ticks(&mut skip_timer, 1, start).await, time::Duration::ZERO);
+        assert_eq!(ticks(&mut delay_timer, 1, start).await, time::Duration::ZERO);
+        // Skipping keeps to the original schedule, delaying starts one from the late tick
+        assert_eq!(ticks(&mut skip_timer, 1, start).await, time::Duration::from_millis(500));
+        assert_eq!(ticks(&mut delay_timer, 1, start).await, time::Duration::from_millis(1000));
+    }
 
-        autosize::autosize(
-            button,
-            AUTOSIZE_MAIN_ID.clone()
-        )
-        .into()
-        // self.core
-        //     .applet
-        //     .icon_button("display-symbolic") // Using a default button image
-        //     .on_press(Message::TogglePopup)
-        //     .into()
+    #[tokio::test(start_paused = true)]
+    async fn tick_stream_completes_when_the_applet_is_dropped() {
+        use cosmic::iced::futures::StreamExt;
+        let settings = TickSettings { msec: 1000, missed: MissedTicks::default() };
+        let (settings_tx, settings_rx) = watch::channel(settings);
+        let mut ticks = std::pin::pin!(tick_stream(settings_rx, false));
+        assert!(matches!(ticks.next().await, Some(Message::Tick)));
+        drop(settings_tx);
+        assert!(ticks.next().await.is_none());
     }
 
-    // The actual GUI window for the applet. It's a popup.
-    fn view_window(&self, _id: window::Id) -> Element<Self::Message> {
+    #[test]
+    fn settings_round_trip_through_a_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("settings.json");
+        let config = CosmicAppletRamConfig {
+            precision: 3,
+            label_prefix: String::from("RAM "),
+            ..Default::default()
+        };
+        export_settings(&config, &path).unwrap();
+        assert_eq!(import_settings(&path).unwrap(), config);
+    }
 
-        // Needed to compare later on which control is selected
-        let entity_iec = self.entity_iec.clone();
-        let entity_si = self.entity_si.clone();
+    #[test]
+    fn imported_settings_are_completed_and_validated() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"precision": 99, "show_total": false}"#).unwrap();
+        let config = import_settings(&path).unwrap();
+        assert_eq!(config.precision, MAX_PRECISION);
+        assert!(!config.show_total);
+        assert_eq!(config.update_interval, DEFAULT_UPDATE_INTERVAL);
 
-        let cosmic::cosmic_theme::Spacing {
-            space_s, ..
-        } = cosmic::theme::spacing();
+        std::fs::write(&path, "[1, 2]").unwrap();
+        assert!(import_settings(&path).is_err());
+        assert!(import_settings(&dir.path().join("missing.json")).is_err());
+    }
 
-        let content_list = column![
-            settings::item(
-                "Update Interval (in ms)",
-                text_input("", &self.update_interval_text)
-                    .on_input(Message::UpdateInterval),
-            ),
-            settings::item(
-                "Standard",
-                segmented_control::horizontal(&self.standard_model)
-                    .on_activate(move |e| Message::UpdateStandard(
-                        if e == entity_iec {
-                            Standard::Iec
-                        } else if e == entity_si {
-                            Standard::Si
-                        } else {
-                            unreachable!()
-                        }
-                    ))
-            ),
-            settings::item(
-                "Prefix",
-                popup_dropdown(
-                    &PREFIX_MENU_ITEMS,
-                    Some(
-                        match self.live_config.prefix {
-                            Prefix::Auto => 0,
-                            Prefix::None => 1,
-                            Prefix::Kilo => 2,
-                            Prefix::Mega => 3,
-                            Prefix::Giga => 4,
-                            Prefix::Tera => 5,
-                            Prefix::Peta => 6,
-                            Prefix::Exa => 7,
-                            Prefix::Zeta => 8,
-                            Prefix::Yotta => 9,
-                        }
-                    ),
-                    |p| Message::UpdatePrefix(
-                        match p {
-                            0 => Prefix::Auto,
-                            1 => Prefix::None,
-                            2 => Prefix::Kilo,
-                            3 => Prefix::Mega,
-                            4 => Prefix::Giga,
-                            5 => Prefix::Tera,
-                            6 => Prefix::Peta,
-                            7 => Prefix::Exa,
-                            8 => Prefix::Zeta,
-                            9 => Prefix::Yotta,
-                            _ => unreachable!(),
-                        }
-                    ),
-                    self.popup.unwrap_or(window::Id::RESERVED),
-                    Message::Surface,
-                    |a| a,
-                )
-            ),
-            settings::item(
-                "Precision",
-                spin_button(
-                    format!("{}", self.live_config.precision),
-                    self.live_config.precision,
-                    1,
-                    0,
-                    10,
-                    Message::UpdatePrecision,
-                ),
-            ),
-            settings::item(
-                "Show Total",
-                checkbox("", self.live_config.show_total)
-                    .on_toggle(Message::UpdateShowTotal)
-            ),
-        ]
-        .spacing(space_s);
+    #[test]
+    fn imported_settings_keep_the_current_commands() {
+        let source = MockSource::default();
+        let config = CosmicAppletRamConfig {
+            critical_action_command: String::from("notify-send critical"),
+            ..Default::default()
+        };
+        let (mut window, dir) = window_with(config, &source);
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{
+            "precision": 2,
+            "critical_action_command": "notify-send imported",
+            "usage_log_path": "/tmp/usage.log",
+            "source": {"Remote": {"command": "ssh elsewhere cat /proc/meminfo"}}
+        }"#).unwrap();
+        window.settings_file_text = path.display().to_string();
+        let _ = window.update(Message::ImportSettings);
+        assert_eq!(window.live_config.precision, 2);
+        assert_eq!(window.live_config.critical_action_command, "notify-send critical");
+        assert_eq!(window.live_config.usage_log_path, "");
+        assert_eq!(window.live_config.source, Source::Local);
 
-        // Set the widget content list as the popup_container for the applet
-        self.core
-            .applet
-            .popup_container(container(content_list).padding(space_s))
-            .into()
+        // A source without a command is nothing to be wary of
+        std::fs::write(&path, r#"{"source": "Session"}"#).unwrap();
+        let _ = window.update(Message::ImportSettings);
+        assert_eq!(window.live_config.source, Source::Session);
     }
-}
 
-const PREFIX_MENU_ITEMS: [&str; 6] = [
-    "Auto",
-    "None",
-    "Kilo",
-    "Mega",
-    "Giga",
-    "Tera",
-    // "Peta",
-    // "Exa",
-    // "Zeta",
-    // "Yotta",
-];
+    #[test]
+    fn failed_sound_is_not_retried_until_its_settings_change() {
+        let source = MockSource::default();
+        source.set(sample(6 * GIB, 16 * GIB));
+        let (mut window, _dir) = window_with(CosmicAppletRamConfig::default(), &source);
+        let _ = window.update(Message::SoundPlayed(Some(String::from("failed to run \"paplay\""))));
+        assert!(window.sound_unavailable);
+        // Typing doesn't change anything until the path is submitted
+        let _ = window.update(Message::UpdateCriticalSoundFileText(String::from("/tmp/al")));
+        assert!(window.sound_unavailable);
+        assert!(window.live_config.critical_sound_file.is_empty());
+        let _ = window.update(Message::UpdateCriticalSoundFile(String::from("/tmp/alert.oga")));
+        assert!(!window.sound_unavailable);
+        assert_eq!(window.critical_sound_file_text, "/tmp/alert.oga");
+        assert_eq!(CosmicAppletRamConfig::get_entry(&window.config).unwrap().critical_sound_file, "/tmp/alert.oga");
+    }
 
-#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
-enum Standard {
-    #[default]
-    Si,
-    Iec,
-}
+    #[test]
+    fn shared_settings_leave_out_defaults_and_redact_commands() {
+        let config = CosmicAppletRamConfig {
+            precision: 2,
+            source: Source::Remote { command: String::from("ssh alice@server cat /proc/meminfo") },
+            critical_action_command: String::from("/home/alice/bin/panic"),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings_snippet(&config, false).unwrap(),
+            r#"{"critical_action_command":"<redacted>","precision":2,"source":{"Remote":{"command":"<redacted>"}}}"#,
+        );
+        assert_eq!(
+            settings_snippet(&config, true).unwrap(),
+            r#"{"critical_action_command":"/home/alice/bin/panic","precision":2,"source":{"Remote":{"command":"ssh alice@server cat /proc/meminfo"}}}"#,
+        );
+        // A source without a command is kept as it is
+        let config = CosmicAppletRamConfig {
+            source: Source::Session,
+            ..Default::default()
+        };
+        assert_eq!(settings_snippet(&config, false).unwrap(), r#"{"source":"Session"}"#);
+        assert_eq!(settings_snippet(&CosmicAppletRamConfig::default(), false).unwrap(), "{}");
+    }
 
-#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize, Serialize)]
-enum Prefix {
-    #[default]
-    Auto,
-    None,
-    Kilo,
-    Mega,
-    Giga,
-    Tera,
-    Peta,
-    Exa,
-    Zeta,
-    Yotta,
-}
+    #[test]
+    fn logged_config_changes_redact_commands() {
+        let old = CosmicAppletRamConfig::default();
+        let new = CosmicAppletRamConfig {
+            precision: 2,
+            source: Source::Remote { command: String::from("ssh alice@server cat /proc/meminfo") },
+            critical_action_command: String::from("/home/alice/bin/panic"),
+            ..Default::default()
+        };
+        assert_eq!(config_changes(&old, &new), [
+            r#"critical_action_command: "" -> "<redacted>""#,
+            "precision: 0 -> 2",
+            r#"source: "Local" -> {"Remote":{"command":"<redacted>"}}"#,
+        ]);
+        assert!(config_changes(&new, &new).is_empty());
+    }
 
-const PREFIXES: [&str; 9] = [
-    "",
-    "K",
-    "M",
-    "G",
-    "T",
-    "P",
-    "E",
-    "Z",
-    "Y"
-];
+    #[test]
+    fn validate_command_needs_a_program() {
+        assert_eq!(validate_command(""), Err(CommandError::Empty));
+        assert_eq!(validate_command(" \t\n"), Err(CommandError::Empty));
+    }
 
-fn format_bytes(count: u64, standard: Standard, prefix: Prefix, precision: u32) -> String {
-    let (k, infix) = match standard {
-        Standard::Si => (1000, "i"),
-        Standard::Iec => (1024, ""),
-    };
-    let i = match prefix {
-        Prefix::Auto => {
-            let mut x = count;
-            let mut i = 0;
-            loop {
-                if i > PREFIXES.len() {
-                    // If the number is excessively large just display in bytes
-                    break 0
-                }
-                if x < k {
-                    break i
-                }
-                x /= k;
-                i += 1;
-            }
-        },
-        Prefix::None => 0,
-        Prefix::Kilo => 1,
-        Prefix::Mega => 2,
-        Prefix::Giga => 3,
-        Prefix::Tera => 4,
-        Prefix::Peta => 5,
-        Prefix::Exa => 6,
-        Prefix::Zeta => 7,
-        Prefix::Yotta => 8,
-    };
-    if i == 0 {
-        return format!("{count} B")
+    #[test]
+    fn validate_command_accepts_programs_on_path_and_paths() {
+        assert_eq!(validate_command("/bin/sh"), Ok(()));
+        assert_eq!(validate_command("/bin/sh -c 'exit 0'"), Ok(()));
+        assert_eq!(validate_command("true"), Ok(()));
     }
-    let f = (count as f64) / (k.pow(i as u32) as f64);
-    let prefix_str = PREFIXES[i];
-    format!("{f:.prec$} {prefix_str}{infix}B", prec = precision as usize)
-}
 
-// The main function returns a cosmic::iced::Result that is returned from
-// the run function that's part of the applet module.
-fn main() -> cosmic::iced::Result {
-    cosmic::applet::run::<Window>(())
-}
+    #[test]
+    fn validate_command_does_not_interpret_the_shell() {
+        // Commands aren't run through a shell, so this is just a program with an odd name
+        assert_eq!(validate_command("$(rm -rf ~)"), Err(CommandError::NotFound("$(rm".to_string())));
+        assert_eq!(
+            validate_command("does-not-exist-anywhere; true"),
+            Err(CommandError::NotFound("does-not-exist-anywhere;".to_string())),
+        );
+    }
 
+    #[test]
+    fn validate_command_rejects_what_cannot_run() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let file = dir.join("not-executable");
+        std::fs::write(&file, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let dir_name = dir.display().to_string();
+        assert_eq!(validate_command(&dir_name), Err(CommandError::NotExecutable(dir_name.clone())));
+        let file_name = file.display().to_string();
+        assert_eq!(validate_command(&file_name), Err(CommandError::NotExecutable(file_name.clone())));
+        let missing = dir.join("missing").display().to_string();
+        assert_eq!(validate_command(&missing), Err(CommandError::NotFound(missing.clone())));
+    }
+}
@@ -0,0 +1,585 @@
+// Readers for the memory statistics that Linux exposes under /proc and /sys
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::metrics::MemorySample;
+
+pub const MEMINFO_PATH: &str = "/proc/meminfo";
+
+/// The directory that contains one `node<N>` subdirectory per NUMA node.
+const NODE_DIR: &str = "/sys/devices/system/node";
+
+/// The directory that contains one `mc<N>` subdirectory per EDAC memory controller.
+const EDAC_DIR: &str = "/sys/devices/system/edac/mc";
+
+/// The directory with one `<type>-<instance>` subdirectory per SMBIOS structure.
+const DMI_ENTRIES_DIR: &str = "/sys/firmware/dmi/entries";
+
+/// The SMBIOS structure type that describes a memory device.
+const DMI_MEMORY_DEVICE: &str = "17-";
+
+/// Where the unified cgroup hierarchy is mounted.
+const CGROUP_DIR: &str = "/sys/fs/cgroup";
+
+/// The memory statistics of a single NUMA node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeMemory {
+    pub id: u32,
+    pub total: u64,
+    pub free: u64,
+}
+
+impl NodeMemory {
+
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.free)
+    }
+
+}
+
+/// The amount of memory errors that were detected by ECC, summed over every memory controller.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EccErrors {
+    /// Errors that were corrected, and are harmless on their own
+    pub corrected: u64,
+    /// Errors that could not be corrected, and may have corrupted data
+    pub uncorrected: u64,
+}
+
+/// Read how many memory errors the EDAC driver counted, or `None` if there is no memory controller
+/// that reports them, as on most hardware without ECC.
+///
+/// Controllers that lack one of the counters are counted as having no errors of that kind.
+pub fn read_ecc_errors() -> io::Result<Option<EccErrors>> {
+    read_ecc_errors_in(Path::new(EDAC_DIR))
+}
+
+/// Read the memory errors of the EDAC memory controllers in `dir`.
+fn read_ecc_errors_in(dir: &Path) -> io::Result<Option<EccErrors>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let mut errors: Option<EccErrors> = None;
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_name().to_str().is_some_and(|name| name.starts_with("mc")) {
+            continue
+        }
+        let total = errors.get_or_insert_default();
+        total.corrected += read_edac_count(&entry.path().join("ce_count"))?;
+        total.uncorrected += read_edac_count(&entry.path().join("ue_count"))?;
+    }
+    Ok(errors)
+}
+
+/// A memory module that is installed in a slot, as described by the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryModule {
+    /// The size of the module in bytes
+    pub size: u64,
+    /// The memory technology, such as `DDR4`, if the firmware names a known one
+    pub kind: Option<&'static str>,
+    /// The rated speed in MT/s, if the firmware reports it
+    pub speed: Option<u32>,
+}
+
+/// Read the memory modules that are installed, from the memory device structures of SMBIOS.
+///
+/// The raw structures are usually only readable by root, in which case this fails with a
+/// permission error. Empty slots are left out.
+pub fn read_memory_modules() -> io::Result<Vec<MemoryModule>> {
+    read_memory_modules_in(Path::new(DMI_ENTRIES_DIR))
+}
+
+/// Read the memory modules from the SMBIOS structures in `dir`.
+fn read_memory_modules_in(dir: &Path) -> io::Result<Vec<MemoryModule>> {
+    let mut entries: Vec<(u32, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let instance = entry.file_name().to_str()?.strip_prefix(DMI_MEMORY_DEVICE)?.parse().ok()?;
+            Some((instance, entry.path().join("raw")))
+        })
+        .collect();
+    // Keeps the modules in slot order, where `17-10` comes after `17-2`
+    entries.sort_by_key(|(instance, _)| *instance);
+    let mut modules = Vec::new();
+    for (_, path) in entries {
+        if let Some(module) = parse_memory_device(&fs::read(path)?) {
+            modules.push(module);
+        }
+    }
+    Ok(modules)
+}
+
+/// Parse a raw SMBIOS memory device structure, or return `None` if the slot is empty or the
+/// structure is too short to tell.
+///
+/// The offsets are those of the SMBIOS specification, version 3.
+fn parse_memory_device(raw: &[u8]) -> Option<MemoryModule> {
+    // Only the formatted area counts, not the strings that follow it
+    let length = usize::from(*raw.get(1)?);
+    let raw = &raw[..length.min(raw.len())];
+    let byte = |offset: usize| raw.get(offset).copied();
+    let word = |offset: usize| Some(u16::from_le_bytes([byte(offset)?, byte(offset + 1)?]));
+    let dword = |offset: usize| Some(u32::from_le_bytes([byte(offset)?, byte(offset + 1)?, byte(offset + 2)?, byte(offset + 3)?]));
+    let size = match word(0x0C)? {
+        0 | 0xFFFF => return None,
+        // The size is given in MiB in the extended size field instead
+        0x7FFF => u64::from(dword(0x1C)? & 0x7FFF_FFFF) << 20,
+        size if size & 0x8000 != 0 => u64::from(size & 0x7FFF) << 10,
+        size => u64::from(size) << 20,
+    };
+    let kind = byte(0x12).and_then(|kind| Some(match kind {
+        0x12 => "DDR",
+        0x13 => "DDR2",
+        0x18 => "DDR3",
+        0x1A => "DDR4",
+        0x1B => "LPDDR",
+        0x1C => "LPDDR2",
+        0x1D => "LPDDR3",
+        0x1E => "LPDDR4",
+        0x22 => "DDR5",
+        0x23 => "LPDDR5",
+        _ => return None,
+    }));
+    let speed = match word(0x15) {
+        Some(0) | None => None,
+        Some(0xFFFF) => dword(0x54).filter(|&speed| speed > 0),
+        Some(speed) => Some(u32::from(speed)),
+    };
+    Some(MemoryModule { size, kind, speed })
+}
+
+/// Summarize the modules compactly, grouping identical ones, as in `2×8 GiB DDR4-3200`.
+///
+/// Sizes are given in GiB, or in MiB for modules smaller than that.
+pub fn summarize_modules(modules: &[MemoryModule]) -> String {
+    let mut groups: Vec<(MemoryModule, usize)> = Vec::new();
+    for module in modules {
+        match groups.iter_mut().find(|(group, _)| group == module) {
+            Some((_, count)) => *count += 1,
+            None => groups.push((*module, 1)),
+        }
+    }
+    groups.iter()
+        .map(|(module, count)| {
+            let mut summary = if module.size >= 1 << 30 {
+                format!("{}×{} GiB", count, module.size >> 30)
+            } else {
+                format!("{}×{} MiB", count, module.size >> 20)
+            };
+            match (module.kind, module.speed) {
+                (Some(kind), Some(speed)) => summary.push_str(&format!(" {kind}-{speed}")),
+                (Some(kind), None) => summary.push_str(&format!(" {kind}")),
+                (None, Some(speed)) => summary.push_str(&format!(" {speed} MT/s")),
+                (None, None) => {},
+            }
+            summary
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Read a single EDAC counter, which is zero if the file doesn't exist.
+fn read_edac_count(path: &Path) -> io::Result<u64> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(error) => return Err(error),
+    };
+    contents.trim().parse().map_err(|error| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid count in {}: {error}", path.display()),
+    ))
+}
+
+/// Read the memory statistics of every NUMA node, sorted by node number.
+pub fn read_numa_nodes() -> io::Result<Vec<NodeMemory>> {
+    let mut nodes = Vec::new();
+    for entry in fs::read_dir(NODE_DIR)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(id) = name.to_str()
+            .and_then(|name| name.strip_prefix("node"))
+            .and_then(|id| id.parse().ok()) else {
+            continue
+        };
+        let contents = fs::read_to_string(entry.path().join("meminfo"))?;
+        let (total, free) = parse_node_meminfo(&contents).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("missing MemTotal or MemFree for NUMA node {id}"),
+        ))?;
+        nodes.push(NodeMemory { id, total, free });
+    }
+    nodes.sort_by_key(|node| node.id);
+    Ok(nodes)
+}
+
+/// Extract the total and free memory, in bytes, from a node's `meminfo` file.
+///
+/// Lines have the form `Node 0 MemTotal:       16318460 kB`.
+fn parse_node_meminfo(contents: &str) -> Option<(u64, u64)> {
+    let mut total = None;
+    let mut free = None;
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace().skip(2);
+        let (Some(key), Some(value)) = (fields.next(), fields.next()) else {
+            continue
+        };
+        let Ok(kib) = value.parse::<u64>() else {
+            continue
+        };
+        match key {
+            "MemTotal:" => total = Some(kib * 1024),
+            "MemFree:" => free = Some(kib * 1024),
+            _ => {},
+        }
+    }
+    Some((total?, free?))
+}
+
+/// A single line of `/proc/meminfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MeminfoField {
+    pub name: String,
+    pub value: u64,
+    /// Whether the value is an amount of memory in bytes, rather than a count such as of pages
+    pub bytes: bool,
+}
+
+/// Parse the contents of `/proc/meminfo` into a map from field names to values.
+///
+/// Values given in `kB` are converted to bytes; values without a unit, such as page counts, are
+/// kept as they are. Lines that can't be parsed are skipped.
+pub fn parse_meminfo(contents: &str) -> HashMap<&str, u64> {
+    contents.lines()
+        .filter_map(parse_meminfo_line)
+        .map(|(key, value, _)| (key, value))
+        .collect()
+}
+
+/// Read every field of `/proc/meminfo`, in the order in which the kernel lists them.
+pub fn read_meminfo_fields() -> io::Result<Vec<MeminfoField>> {
+    Ok(parse_meminfo_fields(&fs::read_to_string(MEMINFO_PATH)?))
+}
+
+/// Parse every field of the contents of `/proc/meminfo`, keeping their order.
+fn parse_meminfo_fields(contents: &str) -> Vec<MeminfoField> {
+    contents.lines()
+        .filter_map(parse_meminfo_line)
+        .map(|(name, value, bytes)| MeminfoField { name: name.to_string(), value, bytes })
+        .collect()
+}
+
+/// The fields of `/proc/meminfo` that can be told from a sample, for when the file itself can't
+/// be read.
+pub fn fields_from_sample(sample: &MemorySample) -> Vec<MeminfoField> {
+    [
+        ("MemTotal", sample.total),
+        ("MemFree", sample.free),
+        ("MemAvailable", sample.available),
+        ("SwapTotal", sample.total_swap),
+        ("SwapFree", sample.total_swap.saturating_sub(sample.used_swap)),
+    ]
+    .into_iter()
+    .map(|(name, value)| MeminfoField { name: name.to_string(), value, bytes: true })
+    .collect()
+}
+
+/// Split a line of `/proc/meminfo` into its key, its value, and whether that value is in bytes.
+fn parse_meminfo_line(line: &str) -> Option<(&str, u64, bool)> {
+    let (key, rest) = line.split_once(':')?;
+    let mut words = rest.split_whitespace();
+    let value = words.next()?.parse::<u64>().ok()?;
+    Some(match words.next() {
+        Some("kB") => (key.trim(), value.saturating_mul(1024), true),
+        _ => (key.trim(), value, false),
+    })
+}
+
+/// Build a memory sample out of the fields of `/proc/meminfo`.
+///
+/// Used memory is computed as `MemTotal - MemAvailable`, which is what `free` reports.
+pub fn sample_from_meminfo(fields: &HashMap<&str, u64>) -> Option<MemorySample> {
+    let total = *fields.get("MemTotal")?;
+    let available = *fields.get("MemAvailable")?;
+    let total_swap = fields.get("SwapTotal").copied().unwrap_or(0);
+    let free_swap = fields.get("SwapFree").copied().unwrap_or(0);
+    Some(MemorySample {
+        used: total.saturating_sub(available),
+        total,
+        available,
+        free: fields.get("MemFree").copied().unwrap_or(0),
+        used_swap: total_swap.saturating_sub(free_swap),
+        total_swap,
+    })
+}
+
+/// Find the directory of the cgroup that holds the user's graphical session, which contains the
+/// current process.
+pub fn session_cgroup() -> io::Result<PathBuf> {
+    let contents = fs::read_to_string("/proc/self/cgroup")?;
+    let path = parse_session_cgroup(&contents).ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        "not running inside a user session cgroup",
+    ))?;
+    Ok(Path::new(CGROUP_DIR).join(path))
+}
+
+/// Extract the session's cgroup from the contents of `/proc/<pid>/cgroup`.
+///
+/// Only the unified hierarchy, with the line `0::/path`, is supported. The session is the closest
+/// `session-*.scope` ancestor, or otherwise the `user@*.service` that the process runs under, so
+/// that the entire session is measured rather than the applet alone.
+fn parse_session_cgroup(contents: &str) -> Option<&str> {
+    let path = contents.lines().find_map(|line| line.strip_prefix("0::"))?.trim_start_matches('/');
+    let mut end = None;
+    let mut offset = 0;
+    for component in path.split('/') {
+        offset += component.len();
+        if component.starts_with("session-") && component.ends_with(".scope") {
+            return Some(&path[..offset])
+        }
+        if component.starts_with("user@") && component.ends_with(".service") {
+            end = Some(offset);
+        }
+        offset += 1;
+    }
+    end.map(|end| &path[..end])
+}
+
+/// Read how much memory is charged to a cgroup, and its limit if it has one, in bytes.
+pub fn read_cgroup_memory(path: &Path) -> io::Result<(u64, Option<u64>)> {
+    let parse = |name: &str, value: &str| value.trim().parse::<u64>().map_err(|error| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid {name} in {}: {error}", path.display()),
+    ));
+    let current = parse("memory.current", &fs::read_to_string(path.join("memory.current"))?)?;
+    let max = match fs::read_to_string(path.join("memory.max")) {
+        Ok(max) if max.trim() == "max" => None,
+        Ok(max) => Some(parse("memory.max", &max)?),
+        // The root cgroup has no limit file
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => return Err(error),
+    };
+    Ok((current, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const MEMINFO: &str = "\
+MemTotal:       16318460 kB
+MemFree:         1234568 kB
+MemAvailable:    8123456 kB
+SwapTotal:       2097148 kB
+SwapFree:        2000000 kB
+HugePages_Total:       4
+Hugepagesize:       2048 kB
+";
+
+    #[test]
+    fn meminfo_values_in_kb_become_bytes() {
+        let fields = parse_meminfo(MEMINFO);
+        assert_eq!(fields["MemTotal"], 16318460 * 1024);
+        assert_eq!(fields["Hugepagesize"], 2048 * 1024);
+        // Page counts have no unit
+        assert_eq!(fields["HugePages_Total"], 4);
+        assert_eq!(fields.len(), 7);
+    }
+
+    #[test]
+    fn meminfo_skips_malformed_lines() {
+        let fields = parse_meminfo("MemTotal 1024 kB\nMemFree:\nMemAvailable: lots kB\n: 5 kB\nCached:  7 kB\n");
+        assert_eq!(fields.get("MemTotal"), None);
+        assert_eq!(fields.get("MemFree"), None);
+        assert_eq!(fields.get("MemAvailable"), None);
+        assert_eq!(fields.get("Cached"), Some(&(7 * 1024)));
+    }
+
+    #[test]
+    fn meminfo_fields_keep_their_order_and_unit() {
+        let fields = parse_meminfo_fields(MEMINFO);
+        let names = fields.iter().map(|field| field.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["MemTotal", "MemFree", "MemAvailable", "SwapTotal", "SwapFree", "HugePages_Total", "Hugepagesize"]);
+        assert_eq!(fields[0], MeminfoField { name: String::from("MemTotal"), value: 16318460 * 1024, bytes: true });
+        assert_eq!(fields[5], MeminfoField { name: String::from("HugePages_Total"), value: 4, bytes: false });
+    }
+
+    #[test]
+    fn meminfo_fields_from_a_sample() {
+        let sample = sample_from_meminfo(&parse_meminfo(MEMINFO)).unwrap();
+        // Everything a sample holds comes back out as it was read
+        assert_eq!(fields_from_sample(&sample), parse_meminfo_fields(MEMINFO)[..5]);
+    }
+
+    #[test]
+    fn meminfo_sample_uses_available_memory() {
+        let sample = sample_from_meminfo(&parse_meminfo(MEMINFO)).unwrap();
+        assert_eq!(sample.total, 16318460 * 1024);
+        assert_eq!(sample.used, (16318460 - 8123456) * 1024);
+        assert_eq!(sample.free, 1234568 * 1024);
+        assert_eq!(sample.used_swap, (2097148 - 2000000) * 1024);
+        assert_eq!(sample_from_meminfo(&parse_meminfo("MemTotal: 1024 kB\n")), None);
+    }
+
+    #[test]
+    fn node_meminfo() {
+        let contents = "\
+Node 0 MemTotal:       16318460 kB
+Node 0 MemFree:         1234568 kB
+Node 0 MemUsed:        15083892 kB
+";
+        assert_eq!(parse_node_meminfo(contents), Some((16318460 * 1024, 1234568 * 1024)));
+    }
+
+    #[test]
+    fn node_meminfo_skips_malformed_lines() {
+        let contents = "\
+MemTotal: 1024 kB
+Node 0 MemFree: many kB
+Node 0
+Node 0 MemTotal:       2048 kB
+Node 0 MemFree:        1024 kB
+";
+        assert_eq!(parse_node_meminfo(contents), Some((2048 * 1024, 1024 * 1024)));
+        assert_eq!(parse_node_meminfo("Node 0 MemTotal: 2048 kB\n"), None);
+        assert_eq!(parse_node_meminfo(""), None);
+    }
+
+    #[test]
+    fn session_cgroup_is_the_closest_session_scope() {
+        let contents = "0::/user.slice/user-1000.slice/user@1000.service/session.slice/session-2.scope/app.scope\n";
+        assert_eq!(
+            parse_session_cgroup(contents),
+            Some("user.slice/user-1000.slice/user@1000.service/session.slice/session-2.scope"),
+        );
+        let contents = "1:name=systemd:/\n0::/user.slice/user-1000.slice/user@1000.service/app.slice/applet.service\n";
+        assert_eq!(parse_session_cgroup(contents), Some("user.slice/user-1000.slice/user@1000.service"));
+    }
+
+    #[test]
+    fn session_cgroup_needs_a_session() {
+        assert_eq!(parse_session_cgroup("0::/system.slice/cron.service\n"), None);
+        // Only the unified hierarchy is supported
+        assert_eq!(parse_session_cgroup("4:memory:/user.slice/user-1000.slice/session-2.scope\n"), None);
+        assert_eq!(parse_session_cgroup(""), None);
+    }
+
+    #[test]
+    fn cgroup_memory_has_an_optional_limit() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("memory.current"), "4096\n").unwrap();
+        assert_eq!(read_cgroup_memory(dir.path()).unwrap(), (4096, None));
+        fs::write(dir.path().join("memory.max"), "max\n").unwrap();
+        assert_eq!(read_cgroup_memory(dir.path()).unwrap(), (4096, None));
+        fs::write(dir.path().join("memory.max"), "8192\n").unwrap();
+        assert_eq!(read_cgroup_memory(dir.path()).unwrap(), (4096, Some(8192)));
+        fs::write(dir.path().join("memory.max"), "lots\n").unwrap();
+        assert_eq!(read_cgroup_memory(dir.path()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn ecc_errors_are_summed_over_controllers() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(read_ecc_errors_in(&dir.path().join("missing")).unwrap(), None);
+        assert_eq!(read_ecc_errors_in(dir.path()).unwrap(), None);
+
+        for (controller, ce, ue) in [("mc0", Some("2\n"), Some("1\n")), ("mc1", Some("3\n"), None)] {
+            let path = dir.path().join(controller);
+            fs::create_dir(&path).unwrap();
+            if let Some(ce) = ce {
+                fs::write(path.join("ce_count"), ce).unwrap();
+            }
+            if let Some(ue) = ue {
+                fs::write(path.join("ue_count"), ue).unwrap();
+            }
+        }
+        // Anything that isn't a memory controller is skipped
+        fs::create_dir(dir.path().join("power")).unwrap();
+        assert_eq!(read_ecc_errors_in(dir.path()).unwrap(), Some(EccErrors { corrected: 5, uncorrected: 1 }));
+
+        fs::write(dir.path().join("mc1").join("ue_count"), "lots").unwrap();
+        assert!(read_ecc_errors_in(dir.path()).is_err());
+    }
+
+    /// Build a raw SMBIOS 3 memory device structure with the given fields, followed by an empty
+    /// string table.
+    fn memory_device(size: u16, extended_size: u32, kind: u8, speed: u16, extended_speed: u32) -> Vec<u8> {
+        let mut raw = vec![0; 0x5C];
+        raw[0] = 17;
+        raw[1] = 0x5C;
+        raw[0x0C..0x0E].copy_from_slice(&size.to_le_bytes());
+        raw[0x12] = kind;
+        raw[0x15..0x17].copy_from_slice(&speed.to_le_bytes());
+        raw[0x1C..0x20].copy_from_slice(&extended_size.to_le_bytes());
+        raw[0x54..0x58].copy_from_slice(&extended_speed.to_le_bytes());
+        raw.extend_from_slice(&[0, 0]);
+        raw
+    }
+
+    #[test]
+    fn memory_device_sizes() {
+        let module = parse_memory_device(&memory_device(8192, 0, 0x1A, 3200, 0)).unwrap();
+        assert_eq!(module, MemoryModule { size: 8 << 30, kind: Some("DDR4"), speed: Some(3200) });
+        // The top bit means that the size is given in KiB
+        let module = parse_memory_device(&memory_device(0x8000 | 512, 0, 0x18, 0, 0)).unwrap();
+        assert_eq!(module, MemoryModule { size: 512 << 10, kind: Some("DDR3"), speed: None });
+        // Unknown kinds are left out
+        assert_eq!(parse_memory_device(&memory_device(1024, 0, 0x02, 800, 0)).unwrap().kind, None);
+    }
+
+    #[test]
+    fn memory_device_extended_fields() {
+        let module = parse_memory_device(&memory_device(0x7FFF, 64 << 10, 0x22, 0xFFFF, 8000)).unwrap();
+        assert_eq!(module, MemoryModule { size: 64 << 30, kind: Some("DDR5"), speed: Some(8000) });
+        // An extended speed of zero is unknown as well
+        assert_eq!(parse_memory_device(&memory_device(8192, 0, 0x22, 0xFFFF, 0)).unwrap().speed, None);
+    }
+
+    #[test]
+    fn memory_device_empty_slots() {
+        assert_eq!(parse_memory_device(&memory_device(0, 0, 0x1A, 0, 0)), None);
+        assert_eq!(parse_memory_device(&memory_device(0xFFFF, 0, 0x1A, 0, 0)), None);
+    }
+
+    #[test]
+    fn memory_device_truncated_structures() {
+        assert_eq!(parse_memory_device(&[]), None);
+        // Cut off before the size
+        assert_eq!(parse_memory_device(&memory_device(8192, 0, 0x1A, 3200, 0)[..0x0D]), None);
+        // An older structure ends before the speed, which is then unknown
+        let mut raw = memory_device(8192, 0, 0x1A, 3200, 0);
+        raw[1] = 0x15;
+        assert_eq!(parse_memory_device(&raw), Some(MemoryModule { size: 8 << 30, kind: Some("DDR4"), speed: None }));
+        // The extended size can't be read past the end of the formatted area, even with strings after it
+        raw[0x0C..0x0E].copy_from_slice(&0x7FFFu16.to_le_bytes());
+        assert_eq!(parse_memory_device(&raw), None);
+    }
+
+    #[test]
+    fn memory_modules_are_read_in_slot_order() {
+        let dir = TempDir::new().unwrap();
+        for (entry, size) in [("17-2", 2048), ("17-10", 4096), ("17-0", 1024), ("16-0", 8192)] {
+            let path = dir.path().join(entry);
+            fs::create_dir(&path).unwrap();
+            fs::write(path.join("raw"), memory_device(size, 0, 0x1A, 3200, 0)).unwrap();
+        }
+        let sizes: Vec<u64> = read_memory_modules_in(dir.path()).unwrap().iter().map(|module| module.size >> 20).collect();
+        assert_eq!(sizes, [1024, 2048, 4096]);
+    }
+
+    #[test]
+    fn memory_modules_summary_groups_identical_ones() {
+        let ddr4 = MemoryModule { size: 8 << 30, kind: Some("DDR4"), speed: Some(3200) };
+        let small = MemoryModule { size: 512 << 20, kind: None, speed: Some(1600) };
+        assert_eq!(summarize_modules(&[ddr4, ddr4, small]), "2×8 GiB DDR4-3200 + 1×512 MiB 1600 MT/s");
+        assert_eq!(summarize_modules(&[]), "");
+    }
+}
@@ -0,0 +1,588 @@
+// Sources from which the applet reads memory statistics
+#[cfg(test)]
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+#[cfg(test)]
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+
+use crate::meminfo::{self, MEMINFO_PATH};
+
+/// The longest a failing command source waits between attempts, on top of the update interval.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How long a command source's command may run before it is killed and counted as a failure.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running command is checked for having exited.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long a background source waits for a sample to be taken before handing out the previous one.
+const BACKGROUND_SAMPLE_WAIT: Duration = Duration::from_millis(100);
+
+/// A single reading of the system's memory statistics, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemorySample {
+    pub used: u64,
+    pub total: u64,
+    pub available: u64,
+    pub free: u64,
+    pub used_swap: u64,
+    pub total_swap: u64,
+}
+
+/// Something that can be asked for the current memory statistics.
+///
+/// The applet only reads memory statistics through this trait, so that they can be provided by
+/// something other than sysinfo.
+pub trait MetricsSource {
+
+    fn sample(&mut self) -> MemorySample;
+
+    /// A description of why the most recent sample could not be taken, if it couldn't.
+    fn error(&self) -> Option<String> {
+        None
+    }
+
+    /// Called whenever the update interval changes, for sources that sample in the background.
+    fn set_interval(&mut self, _msec: u64) {}
+
+    /// Whether the most recent sample describes the whole machine rather than what the source is
+    /// meant to measure, because that couldn't be read.
+    fn fell_back(&self) -> bool {
+        false
+    }
+
+    /// Whether the source is still busy taking an earlier sample, in which case the most recent
+    /// call to [`sample`](MetricsSource::sample) had nothing new to hand out.
+    fn busy(&self) -> bool {
+        false
+    }
+
+}
+
+/// Reads the memory statistics of the local machine through sysinfo.
+#[derive(Default)]
+pub struct SysinfoSource {
+    sys: System,
+}
+
+impl MetricsSource for SysinfoSource {
+
+    fn sample(&mut self) -> MemorySample {
+        self.sys.refresh_memory();
+        MemorySample {
+            used: self.sys.used_memory(),
+            total: self.sys.total_memory(),
+            available: self.sys.available_memory(),
+            free: self.sys.free_memory(),
+            used_swap: self.sys.used_swap(),
+            total_swap: self.sys.total_swap(),
+        }
+    }
+
+}
+
+/// Reads the memory statistics of the local machine straight from `/proc/meminfo`, so that they
+/// match what `free` reports.
+///
+/// Used memory is `MemTotal - MemAvailable`. When the file can't be read or lacks those fields, for
+/// example on a system other than Linux, this falls back to sysinfo.
+pub struct ProcMeminfoSource {
+    fallback: SysinfoSource,
+    /// Reads the contents of `/proc/meminfo`, which tests replace with a fixture
+    read: fn() -> io::Result<String>,
+    /// The last error that was logged, so that it isn't logged again on every sample
+    last_error: Option<String>,
+}
+
+impl Default for ProcMeminfoSource {
+
+    fn default() -> Self {
+        Self {
+            fallback: SysinfoSource::default(),
+            read: || fs::read_to_string(MEMINFO_PATH),
+            last_error: None,
+        }
+    }
+
+}
+
+impl ProcMeminfoSource {
+
+    /// A source that reads what would be in `/proc/meminfo` with `read` instead.
+    #[cfg(test)]
+    pub fn with_reader(read: fn() -> io::Result<String>) -> Self {
+        Self {
+            read,
+            ..Self::default()
+        }
+    }
+
+}
+
+impl MetricsSource for ProcMeminfoSource {
+
+    fn sample(&mut self) -> MemorySample {
+        let result = (self.read)()
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                meminfo::sample_from_meminfo(&meminfo::parse_meminfo(&contents))
+                    .ok_or_else(|| String::from("missing MemTotal or MemAvailable"))
+            });
+        match result {
+            Ok(sample) => {
+                self.last_error = None;
+                sample
+            }
+            Err(error) => {
+                if self.last_error.as_ref() != Some(&error) {
+                    tracing::warn!("Failed to read {}, falling back to sysinfo: {}", MEMINFO_PATH, error);
+                }
+                self.last_error = Some(error);
+                self.fallback.sample()
+            }
+        }
+    }
+
+}
+
+/// Reads how much memory the user's session uses, according to its cgroup.
+///
+/// Swap and the total amount of memory are taken from the whole machine, unless the session has a
+/// memory limit. Whenever the session cgroup can't be found or read, this falls back to the memory
+/// usage of the whole machine.
+pub struct SessionSource {
+    system: SysinfoSource,
+    cgroup: Option<PathBuf>,
+    /// The last error that was logged, so that it isn't logged again on every sample
+    last_error: Option<String>,
+}
+
+impl SessionSource {
+
+    /// Look up the session cgroup of the current process.
+    pub fn detect() -> Self {
+        let cgroup = match meminfo::session_cgroup() {
+            Ok(cgroup) => Some(cgroup),
+            Err(error) => {
+                tracing::warn!("Failed to find the session cgroup, showing usage of the whole system: {}", error);
+                None
+            }
+        };
+        Self {
+            system: SysinfoSource::default(),
+            cgroup,
+            last_error: None,
+        }
+    }
+
+}
+
+impl MetricsSource for SessionSource {
+
+    fn sample(&mut self) -> MemorySample {
+        let mut sample = self.system.sample();
+        let Some(cgroup) = &self.cgroup else {
+            return sample
+        };
+        match meminfo::read_cgroup_memory(cgroup) {
+            Ok((current, max)) => {
+                sample.used = current;
+                if let Some(max) = max {
+                    sample.total = sample.total.min(max);
+                }
+                sample.available = sample.total.saturating_sub(current);
+                self.last_error = None;
+            }
+            Err(error) => {
+                let error = error.to_string();
+                if self.last_error.as_ref() != Some(&error) {
+                    tracing::warn!("Failed to read session memory usage from {}, showing usage of the whole system: {}", cgroup.display(), error);
+                }
+                self.last_error = Some(error);
+            }
+        }
+        sample
+    }
+
+    fn fell_back(&self) -> bool {
+        self.cgroup.is_none() || self.last_error.is_some()
+    }
+
+}
+
+#[derive(Debug, Default)]
+struct CommandState {
+    sample: MemorySample,
+    error: Option<String>,
+    /// The update interval in milliseconds
+    interval: u64,
+}
+
+/// Reads memory statistics by running a command that prints the contents of `/proc/meminfo`, such
+/// as `ssh host cat /proc/meminfo`.
+///
+/// The command runs on a background thread, once per update interval, so that a slow or hanging
+/// command never blocks the applet. Sampling returns the result of the latest run. A command that
+/// runs for longer than [`COMMAND_TIMEOUT`] is killed and counts as a failure, so that a hanging
+/// connection doesn't pass off the last sample as a live one. After a failure, the next attempt is
+/// delayed further up to [`MAX_RETRY_DELAY`].
+pub struct CommandSource {
+    state: Arc<Mutex<CommandState>>,
+}
+
+impl CommandSource {
+
+    pub fn spawn(command: String, interval: u64) -> Self {
+        let state = Arc::new(Mutex::new(CommandState {
+            interval,
+            ..CommandState::default()
+        }));
+        // The thread stops once the source is dropped
+        let weak_state = Arc::downgrade(&state);
+        thread::spawn(move || {
+            let mut retry_delay = Duration::ZERO;
+            loop {
+                let result = run_meminfo_command(&command, COMMAND_TIMEOUT);
+                let Some(state) = weak_state.upgrade() else {
+                    break
+                };
+                let mut state = state.lock().unwrap_or_else(|error| error.into_inner());
+                match result {
+                    Ok(sample) => {
+                        state.sample = sample;
+                        state.error = None;
+                        retry_delay = Duration::ZERO;
+                    }
+                    Err(error) => {
+                        if state.error.as_ref() != Some(&error) {
+                            tracing::warn!("Failed to read memory statistics with '{}': {}", command, error);
+                        }
+                        state.error = Some(error);
+                        retry_delay = (retry_delay * 2).clamp(Duration::from_secs(1), MAX_RETRY_DELAY);
+                    }
+                }
+                let interval = Duration::from_millis(state.interval);
+                drop(state);
+                thread::sleep(interval + retry_delay);
+            }
+        });
+        Self { state }
+    }
+
+}
+
+impl MetricsSource for CommandSource {
+
+    fn sample(&mut self) -> MemorySample {
+        self.state.lock().unwrap_or_else(|error| error.into_inner()).sample
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner()).error.clone()
+    }
+
+    fn set_interval(&mut self, msec: u64) {
+        self.state.lock().unwrap_or_else(|error| error.into_inner()).interval = msec;
+    }
+
+}
+
+/// Everything that a background source hands back about a sample.
+#[derive(Debug, Default)]
+struct BackgroundSample {
+    sample: MemorySample,
+    error: Option<String>,
+    fell_back: bool,
+}
+
+/// Takes the samples of another source on a background thread, so that a source that blocks, such
+/// as sysinfo on a hung `/proc`, never blocks the applet.
+///
+/// Sampling waits up to [`BACKGROUND_SAMPLE_WAIT`] for a fresh sample, which is plenty for a source
+/// that works. After that, the previous sample is handed out and the source is busy until the
+/// thread finishes; no new sample is asked for in the meantime. A thread that never finishes is
+/// left behind once the source is dropped.
+pub struct BackgroundSource {
+    requests: mpsc::Sender<()>,
+    samples: mpsc::Receiver<BackgroundSample>,
+    latest: BackgroundSample,
+    busy: bool,
+}
+
+impl BackgroundSource {
+
+    pub fn spawn(mut source: Box<dyn MetricsSource + Send>) -> Self {
+        let (requests, pending) = mpsc::channel();
+        let (done, samples) = mpsc::channel();
+        // The thread stops once the source is dropped, unless it is stuck in a sample
+        thread::spawn(move || {
+            for () in pending {
+                let sample = source.sample();
+                let result = BackgroundSample {
+                    sample,
+                    error: source.error(),
+                    fell_back: source.fell_back(),
+                };
+                if done.send(result).is_err() {
+                    break
+                }
+            }
+        });
+        Self {
+            requests,
+            samples,
+            latest: BackgroundSample::default(),
+            busy: false,
+        }
+    }
+
+}
+
+impl MetricsSource for BackgroundSource {
+
+    fn sample(&mut self) -> MemorySample {
+        if !self.busy {
+            self.busy = self.requests.send(()).is_ok();
+        }
+        if self.busy {
+            if let Ok(result) = self.samples.recv_timeout(BACKGROUND_SAMPLE_WAIT) {
+                self.latest = result;
+                self.busy = false;
+            }
+        }
+        self.latest.sample
+    }
+
+    fn error(&self) -> Option<String> {
+        self.latest.error.clone()
+    }
+
+    fn fell_back(&self) -> bool {
+        self.latest.fell_back
+    }
+
+    fn busy(&self) -> bool {
+        self.busy
+    }
+
+}
+
+/// Hands out whatever sample it was last given, so that tests don't depend on the memory of the
+/// machine they run on.
+///
+/// Clones share their state, so a test can keep one to change what the applet reads after handing
+/// another to it.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct MockSource {
+    sample: Rc<Cell<MemorySample>>,
+    error: Rc<RefCell<Option<String>>>,
+    fell_back: Rc<Cell<bool>>,
+    samples_taken: Rc<Cell<usize>>,
+}
+
+#[cfg(test)]
+impl MockSource {
+
+    /// Make every following sample return these statistics.
+    pub fn set(&self, sample: MemorySample) {
+        self.sample.set(sample);
+    }
+
+    /// Report this error with every following sample, or stop reporting one with `None`.
+    pub fn fail(&self, error: Option<&str>) {
+        *self.error.borrow_mut() = error.map(String::from);
+    }
+
+    /// Make every following sample count as one of the whole machine, or stop doing so.
+    pub fn fall_back(&self, fell_back: bool) {
+        self.fell_back.set(fell_back);
+    }
+
+    /// How many samples were taken from this source and all of its clones.
+    pub fn samples_taken(&self) -> usize {
+        self.samples_taken.get()
+    }
+
+}
+
+#[cfg(test)]
+impl MetricsSource for MockSource {
+
+    fn sample(&mut self) -> MemorySample {
+        self.samples_taken.set(self.samples_taken.get() + 1);
+        self.sample.get()
+    }
+
+    fn error(&self) -> Option<String> {
+        self.error.borrow().clone()
+    }
+
+    fn fell_back(&self) -> bool {
+        self.fell_back.get()
+    }
+
+}
+
+/// Run `command` and read memory statistics from its output, killing it if it runs for longer than
+/// `timeout`.
+fn run_meminfo_command(command: &str, timeout: Duration) -> Result<MemorySample, String> {
+    let mut words = command.split_whitespace();
+    let program = words.next().ok_or("no command configured")?;
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+    // Read the output as it comes, so that a command with a lot of it can't block on a full pipe
+    let mut pipe = child.stdout.take().expect("stdout is piped");
+    let reader = thread::spawn(move || {
+        let mut stdout = Vec::new();
+        pipe.read_to_end(&mut stdout).map(|_| stdout)
+    });
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|error| error.to_string())? {
+            break status
+        }
+        if started.elapsed() >= timeout {
+            // Whatever the command started may still hold the pipe, so the reader is left behind
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("command didn't finish within {} s", timeout.as_secs_f64()))
+        }
+        thread::sleep(COMMAND_POLL_INTERVAL);
+    };
+    if !status.success() {
+        return Err(format!("command exited with {status}"))
+    }
+    let stdout = reader.join()
+        .map_err(|_| String::from("failed to read the output of the command"))?
+        .map_err(|error| error.to_string())?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    meminfo::sample_from_meminfo(&meminfo::parse_meminfo(&stdout))
+        .ok_or_else(|| String::from("output is missing MemTotal or MemAvailable"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    const MEMINFO: &str = "MemTotal:       16000 kB\nMemFree:         2000 kB\nMemAvailable:    6000 kB\nSwapTotal:       4000 kB\nSwapFree:        3000 kB\n";
+
+    /// A file holding `contents`, which is removed as it is dropped.
+    fn temp_file(contents: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    fn expected() -> MemorySample {
+        MemorySample {
+            used: 10_000 * 1024,
+            total: 16_000 * 1024,
+            available: 6000 * 1024,
+            free: 2000 * 1024,
+            used_swap: 1000 * 1024,
+            total_swap: 4000 * 1024,
+        }
+    }
+
+    #[test]
+    fn meminfo_commands_are_parsed() {
+        let file = temp_file(MEMINFO);
+        assert_eq!(run_meminfo_command(&format!("cat {}", file.path().display()), COMMAND_TIMEOUT), Ok(expected()));
+    }
+
+    #[test]
+    fn failing_meminfo_commands_are_errors() {
+        let file = temp_file("MemTotal: 16000 kB\n");
+        assert_eq!(run_meminfo_command("", COMMAND_TIMEOUT), Err(String::from("no command configured")));
+        assert!(run_meminfo_command("false", COMMAND_TIMEOUT).is_err_and(|error| error.starts_with("command exited with")));
+        assert!(run_meminfo_command("cosmic-applet-ram-usage-does-not-exist", COMMAND_TIMEOUT).is_err());
+        assert_eq!(
+            run_meminfo_command(&format!("cat {}", file.path().display()), COMMAND_TIMEOUT),
+            Err(String::from("output is missing MemTotal or MemAvailable")),
+        );
+    }
+
+    #[test]
+    fn hanging_meminfo_commands_are_killed() {
+        let started = Instant::now();
+        assert_eq!(
+            run_meminfo_command("sleep 30", Duration::from_millis(100)),
+            Err(String::from("command didn't finish within 0.1 s")),
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn command_source_samples_in_the_background() {
+        let file = temp_file(MEMINFO);
+        let mut source = CommandSource::spawn(format!("cat {}", file.path().display()), 10);
+        let mut waited = Duration::ZERO;
+        while source.sample() == MemorySample::default() && waited < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(10));
+            waited += Duration::from_millis(10);
+        }
+        assert_eq!(source.sample(), expected());
+        assert_eq!(source.error(), None);
+    }
+
+    /// Hands out [`expected`] once it is allowed to, blocking until then.
+    struct BlockingSource(mpsc::Receiver<()>);
+
+    impl MetricsSource for BlockingSource {
+
+        fn sample(&mut self) -> MemorySample {
+            let _ = self.0.recv();
+            expected()
+        }
+
+    }
+
+    #[test]
+    fn blocking_sources_are_sampled_in_the_background() {
+        let (release, blocked) = mpsc::channel();
+        let mut source = BackgroundSource::spawn(Box::new(BlockingSource(blocked)));
+        let started = Instant::now();
+        assert_eq!(source.sample(), MemorySample::default());
+        assert!(source.busy());
+        // The wait only happens once, rather than on every sample until the source is unblocked
+        assert_eq!(source.sample(), MemorySample::default());
+        assert!(started.elapsed() < BACKGROUND_SAMPLE_WAIT * 2);
+
+        release.send(()).unwrap();
+        let started = Instant::now();
+        while source.busy() && started.elapsed() < Duration::from_secs(10) {
+            source.sample();
+        }
+        assert!(!source.busy());
+        assert_eq!(source.sample(), expected());
+    }
+
+    #[test]
+    fn mock_sources_share_their_state() {
+        let mock = MockSource::default();
+        let mut source = mock.clone();
+        mock.set(expected());
+        mock.fail(Some("unplugged"));
+        assert_eq!(source.sample(), expected());
+        assert_eq!(source.error().as_deref(), Some("unplugged"));
+        assert_eq!(mock.samples_taken(), 1);
+    }
+}
@@ -0,0 +1,58 @@
+// Memory pressure events from the kernel, through a PSI trigger on /proc/pressure/memory
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+
+use cosmic::iced::futures::SinkExt;
+use cosmic::iced::Subscription;
+use cosmic::iced_futures::stream;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+use crate::{Message, ResultExt};
+
+const PRESSURE_PATH: &str = "/proc/pressure/memory";
+
+/// The trigger that is registered: some task stalled on memory for 150ms within a 2s window.
+///
+/// Unprivileged users may only register windows that are a multiple of 2s.
+const TRIGGER: &str = "some 150000 2000000";
+
+/// Open the pressure file with the trigger registered on it.
+///
+/// The kernel signals the trigger as a priority event on the file, which is why it is only ever
+/// polled and never read.
+fn register() -> io::Result<AsyncFd<std::fs::File>> {
+    let mut file = OpenOptions::new().read(true).write(true).open(PRESSURE_PATH)?;
+    // The trigger must be written in a single write, including the terminating zero
+    file.write_all(format!("{TRIGGER}\0").as_bytes())?;
+    AsyncFd::with_interest(file, Interest::PRIORITY)
+}
+
+/// Tell the applet every time the kernel reports memory pressure, for as long as it is running.
+///
+/// Without PSI, as on kernels that were built without it, a warning is logged and the applet
+/// carries on with the regular updates alone.
+pub fn subscription() -> Subscription<Message> {
+    Subscription::run_with_id(
+        "pressure-sub",
+        stream::channel(1, |mut output| async move {
+            let trigger = match register() {
+                Ok(trigger) => trigger,
+                Err(error) => {
+                    tracing::warn!("Failed to register a memory pressure trigger on {}: {}", PRESSURE_PATH, error);
+                    return
+                }
+            };
+            loop {
+                match trigger.ready(Interest::PRIORITY).await {
+                    Ok(mut guard) => guard.clear_ready(),
+                    Err(error) => {
+                        tracing::warn!("Stopped listening for memory pressure: {}", error);
+                        break
+                    }
+                }
+                output.send(Message::PressureEvent).await.log("Failed sending pressure event to applet");
+            }
+        }),
+    )
+}
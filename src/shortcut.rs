@@ -0,0 +1,126 @@
+// Keyboard shortcuts given as strings such as `Super+R`
+use cosmic::iced::keyboard::{key::Named, Key, Modifiers};
+
+/// A key combination that the user configured.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shortcut {
+    modifiers: Modifiers,
+    key: Key,
+}
+
+impl Shortcut {
+
+    /// Parse a shortcut made of any number of modifiers followed by a single key, separated by
+    /// `+`, such as `Ctrl+Alt+M` or `Super+F9`.
+    ///
+    /// Names are matched case-insensitively.
+    pub fn parse(combo: &str) -> Result<Self, String> {
+        let mut parts = combo.split('+').map(str::trim).collect::<Vec<_>>();
+        let key = parts.pop().filter(|key| !key.is_empty())
+            .ok_or_else(|| format!("'{combo}' does not end in a key"))?;
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "super" | "logo" | "meta" => Modifiers::LOGO,
+                "ctrl" | "control" => Modifiers::CTRL,
+                "alt" => Modifiers::ALT,
+                "shift" => Modifiers::SHIFT,
+                _ => return Err(format!("'{part}' is not a modifier")),
+            };
+        }
+        Ok(Self {
+            modifiers,
+            key: parse_key(key).ok_or_else(|| format!("'{key}' is not a key"))?,
+        })
+    }
+
+    /// Whether pressing `key` while holding `modifiers` triggers this shortcut.
+    pub fn matches(&self, key: &Key, modifiers: Modifiers) -> bool {
+        if modifiers != self.modifiers {
+            return false
+        }
+        match (key, &self.key) {
+            (Key::Character(pressed), Key::Character(expected)) => pressed.to_lowercase() == expected.as_str(),
+            (pressed, expected) => pressed == expected,
+        }
+    }
+
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Character(c.to_lowercase().collect::<String>().into()))
+    }
+    let named = match name.to_lowercase().as_str() {
+        "space" => Named::Space,
+        "enter" | "return" => Named::Enter,
+        "tab" => Named::Tab,
+        "escape" | "esc" => Named::Escape,
+        "backspace" => Named::Backspace,
+        "delete" => Named::Delete,
+        "insert" => Named::Insert,
+        "home" => Named::Home,
+        "end" => Named::End,
+        "pageup" => Named::PageUp,
+        "pagedown" => Named::PageDown,
+        "up" => Named::ArrowUp,
+        "down" => Named::ArrowDown,
+        "left" => Named::ArrowLeft,
+        "right" => Named::ArrowRight,
+        "f1" => Named::F1,
+        "f2" => Named::F2,
+        "f3" => Named::F3,
+        "f4" => Named::F4,
+        "f5" => Named::F5,
+        "f6" => Named::F6,
+        "f7" => Named::F7,
+        "f8" => Named::F8,
+        "f9" => Named::F9,
+        "f10" => Named::F10,
+        "f11" => Named::F11,
+        "f12" => Named::F12,
+        _ => return None,
+    };
+    Some(Key::Named(named))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn character(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    #[test]
+    fn shortcuts_parse_modifiers_and_a_key() {
+        let shortcut = Shortcut::parse("Super + Shift+R").unwrap();
+        assert_eq!(shortcut, Shortcut { modifiers: Modifiers::LOGO | Modifiers::SHIFT, key: character("r") });
+        assert_eq!(
+            Shortcut::parse("ctrl+ALT+pagedown"),
+            Ok(Shortcut { modifiers: Modifiers::CTRL | Modifiers::ALT, key: Key::Named(Named::PageDown) }),
+        );
+        assert_eq!(Shortcut::parse("F9"), Ok(Shortcut { modifiers: Modifiers::empty(), key: Key::Named(Named::F9) }));
+    }
+
+    #[test]
+    fn invalid_shortcuts_are_rejected() {
+        assert!(Shortcut::parse("").is_err());
+        assert!(Shortcut::parse("Ctrl+").is_err());
+        assert!(Shortcut::parse("Hyper+R").is_err());
+        assert!(Shortcut::parse("Ctrl+F13").is_err());
+    }
+
+    #[test]
+    fn shortcuts_match_exactly_their_modifiers() {
+        let shortcut = Shortcut::parse("Ctrl+M").unwrap();
+        assert!(shortcut.matches(&character("m"), Modifiers::CTRL));
+        // Caps Lock or Shift give an upper case character
+        assert!(shortcut.matches(&character("M"), Modifiers::CTRL));
+        assert!(!shortcut.matches(&character("m"), Modifiers::CTRL | Modifiers::SHIFT));
+        assert!(!shortcut.matches(&character("m"), Modifiers::empty()));
+        assert!(!shortcut.matches(&character("n"), Modifiers::CTRL));
+        assert!(!shortcut.matches(&Key::Named(Named::Enter), Modifiers::CTRL));
+    }
+}
@@ -0,0 +1,26 @@
+// Reloads the configuration when the applet receives SIGHUP, for scripts that edit it on disk
+use cosmic::iced::futures::SinkExt;
+use cosmic::iced::Subscription;
+use cosmic::iced_futures::stream;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::{Message, ResultExt};
+
+/// Ask the applet to reload its configuration on every SIGHUP, for as long as it is running.
+pub fn subscription() -> Subscription<Message> {
+    Subscription::run_with_id(
+        "sighup-sub",
+        stream::channel(1, |mut output| async move {
+            let mut hangups = match signal(SignalKind::hangup()) {
+                Ok(hangups) => hangups,
+                Err(error) => {
+                    tracing::error!("Failed to listen for SIGHUP: {}", error);
+                    return
+                }
+            };
+            while hangups.recv().await.is_some() {
+                output.send(Message::ReloadConfig).await.log("Failed sending reload request to applet");
+            }
+        }),
+    )
+}
@@ -0,0 +1,219 @@
+// An optional CSV file to which every memory sample is appended
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::time;
+
+use crate::metrics::MemorySample;
+use crate::ResultExt;
+
+/// How often buffered rows are written out to the file.
+const FLUSH_INTERVAL: time::Duration = time::Duration::from_secs(10);
+
+const HEADER: &str = "timestamp,used,total,available,swap_used\n";
+
+/// The open log file, along with what is needed to notice that it was moved or deleted.
+struct OpenLog {
+    writer: BufWriter<File>,
+    /// The inode of the file when it was opened
+    inode: u64,
+    /// The size of the file, including rows that are still buffered
+    size: u64,
+}
+
+/// Appends samples to a CSV file with the columns in [`HEADER`].
+///
+/// Rows are buffered and flushed every [`FLUSH_INTERVAL`]. If the file is rotated or deleted by
+/// someone else, it is reopened on the next flush. Once the file grows past the maximum size, it
+/// is renamed with a `.1` suffix, replacing the previous one, and a new file is started.
+pub struct UsageLog {
+    path: PathBuf,
+    max_size: u64,
+    file: Option<OpenLog>,
+    flushed_at: time::Instant,
+}
+
+impl UsageLog {
+
+    pub fn new(path: PathBuf, max_size: u64) -> Self {
+        Self {
+            path,
+            max_size,
+            file: None,
+            flushed_at: time::Instant::now(),
+        }
+    }
+
+    /// Append a row for the given sample, opening the file first if needed.
+    pub fn append(&mut self, memory: &MemorySample) -> io::Result<()> {
+        if self.file.as_ref().is_some_and(|file| file.size >= self.max_size) {
+            self.rotate()?;
+        }
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => open(&self.path)?,
+        };
+        let file = self.file.insert(file);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let row = format!(
+            "{},{},{},{},{}\n",
+            timestamp,
+            memory.used,
+            memory.total,
+            memory.available,
+            memory.used_swap,
+        );
+        file.writer.write_all(row.as_bytes())?;
+        file.size += row.len() as u64;
+        if self.flushed_at.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write out buffered rows, and reopen the file if it no longer is the one at the path.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.flushed_at = time::Instant::now();
+        let Some(file) = &mut self.file else {
+            return Ok(())
+        };
+        file.writer.flush()?;
+        let replaced = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.ino() != file.inode,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => true,
+            Err(error) => return Err(error),
+        };
+        if replaced {
+            self.file = Some(open(&self.path)?);
+        }
+        Ok(())
+    }
+
+    /// Move the current file out of the way and start a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.writer.flush()?;
+        }
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        match fs::rename(&self.path, rotated) {
+            // Someone else moved or deleted the file already, so there is nothing to rotate
+            Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+            result => result?,
+        }
+        self.file = Some(open(&self.path)?);
+        Ok(())
+    }
+
+}
+
+impl Drop for UsageLog {
+
+    fn drop(&mut self) {
+        if let Some(file) = &mut self.file {
+            file.writer.flush().log_recoverable("Failed to flush usage log");
+        }
+    }
+
+}
+
+/// Open the log for appending, writing the header if the file is new.
+fn open(path: &Path) -> io::Result<OpenLog> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let mut log = OpenLog {
+        writer: BufWriter::new(file),
+        inode: metadata.ino(),
+        size: metadata.len(),
+    };
+    if log.size == 0 {
+        log.writer.write_all(HEADER.as_bytes())?;
+        log.size = HEADER.len() as u64;
+    }
+    Ok(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sample(used: u64) -> MemorySample {
+        MemorySample { used, total: 100, available: 100 - used, free: 0, used_swap: 1, total_swap: 2 }
+    }
+
+    /// The rows of the file at `path` without the timestamps, which depend on the clock.
+    fn rows(path: &Path) -> Vec<String> {
+        fs::read_to_string(path).unwrap()
+            .lines()
+            .map(|line| line.split_once(',').map_or(line, |(_, row)| row).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn rows_follow_a_header() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("usage.csv");
+        let mut log = UsageLog::new(path.clone(), u64::MAX);
+        log.append(&sample(10)).unwrap();
+        log.append(&sample(20)).unwrap();
+        log.flush().unwrap();
+        assert_eq!(rows(&path), vec!["used,total,available,swap_used", "10,100,90,1", "20,100,80,1"]);
+
+        // An existing log is appended to, without another header
+        drop(log);
+        let mut log = UsageLog::new(path.clone(), u64::MAX);
+        log.append(&sample(30)).unwrap();
+        drop(log);
+        assert_eq!(rows(&path).len(), 4);
+    }
+
+    #[test]
+    fn full_logs_are_rotated() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("usage.csv");
+        let mut rotated = path.clone().into_os_string();
+        rotated.push(".1");
+        let mut log = UsageLog::new(path.clone(), HEADER.len() as u64 + 1);
+        log.append(&sample(10)).unwrap();
+        log.append(&sample(20)).unwrap();
+        drop(log);
+        assert_eq!(rows(Path::new(&rotated)), vec!["used,total,available,swap_used", "10,100,90,1"]);
+        assert_eq!(rows(&path), vec!["used,total,available,swap_used", "20,100,80,1"]);
+    }
+
+    #[test]
+    fn full_logs_that_were_deleted_are_started_again() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("usage.csv");
+        let mut log = UsageLog::new(path.clone(), HEADER.len() as u64 + 1);
+        log.append(&sample(10)).unwrap();
+        log.flush().unwrap();
+        fs::remove_file(&path).unwrap();
+        log.append(&sample(20)).unwrap();
+        drop(log);
+        assert_eq!(rows(&path), vec!["used,total,available,swap_used", "20,100,80,1"]);
+    }
+
+    #[test]
+    fn deleted_logs_are_reopened() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("usage.csv");
+        let mut log = UsageLog::new(path.clone(), u64::MAX);
+        log.append(&sample(10)).unwrap();
+        log.flush().unwrap();
+        fs::remove_file(&path).unwrap();
+        log.flush().unwrap();
+        log.append(&sample(20)).unwrap();
+        drop(log);
+        assert_eq!(rows(&path), vec!["used,total,available,swap_used", "20,100,80,1"]);
+    }
+}